@@ -1,4 +1,12 @@
 fn main() {
-    println!("cargo:rustc-link-search=framework=/System/Library/PrivateFrameworks");
-    println!("cargo:rustc-link-lib=framework=SkyLight");
+    // SkyLight is private, only linked for the private-apis feature
+    if std::env::var_os("CARGO_FEATURE_PRIVATE_APIS").is_some() {
+        println!("cargo:rustc-link-search=framework=/System/Library/PrivateFrameworks");
+        println!("cargo:rustc-link-lib=framework=SkyLight");
+    }
+
+    // Embed Info.plist so the bare binary gets a bundle id for TCC prompts and LSUIElement
+    let plist = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/Info.plist");
+    println!("cargo:rerun-if-changed={plist}");
+    println!("cargo:rustc-link-arg-bins=-Wl,-sectcreate,__TEXT,__info_plist,{plist}");
 }
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::crash::log;
+
+const LABEL: &str = "com.bootreer.switcheroo";
+
+fn plist_path() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    Ok(PathBuf::from(home)
+        .join("Library/LaunchAgents")
+        .join(format!("{LABEL}.plist")))
+}
+
+/// Writes a LaunchAgent for the current executable that starts at login and is relaunched
+/// by launchd whenever it exits unsuccessfully.
+pub fn install() -> Result<()> {
+    let exe = std::env::current_exe().context("Could not locate the switcheroo executable")?;
+    let path = plist_path()?;
+
+    let plist = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+</dict>
+</plist>
+"#,
+        xml_escape(&exe.to_string_lossy())
+    );
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+    // Reinstalling over a loaded agent: unload first so launchd picks up the new plist
+    if path.exists() {
+        let _ = launchctl(&["unload", "-w"], &path);
+    }
+    std::fs::write(&path, plist).with_context(|| format!("Could not write {}", path.display()))?;
+    launchctl(&["load", "-w"], &path)?;
+
+    println!("Installed LaunchAgent at {}", path.display());
+    Ok(())
+}
+
+pub fn uninstall() -> Result<()> {
+    let path = plist_path()?;
+    if !path.exists() {
+        println!("No LaunchAgent installed at {}", path.display());
+        return Ok(());
+    }
+
+    // Not loaded (or already gone from launchd) is no reason to keep the plist around
+    if let Err(e) = launchctl(&["unload", "-w"], &path) {
+        log!("[warn] {e:#}, removing the plist anyway");
+    }
+    std::fs::remove_file(&path).with_context(|| format!("Could not remove {}", path.display()))?;
+
+    println!("Removed LaunchAgent at {}", path.display());
+    Ok(())
+}
+
+fn launchctl(args: &[&str], path: &std::path::Path) -> Result<()> {
+    let status = Command::new("launchctl")
+        .args(args)
+        .arg(path)
+        .status()
+        .context("Could not run launchctl")?;
+    if !status.success() {
+        return Err(anyhow!("launchctl {} failed with {status}", args.join(" ")));
+    }
+    Ok(())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
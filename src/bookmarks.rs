@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::stats::app_key;
+use crate::windows::{App, Manager, Window, glob_matches};
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// A window remembered by app and title, since window ids don't survive restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub app: String,
+    /// The title when it was bookmarked
+    pub title: String,
+    /// Lowercased title glob, "*<title>*" unless edited, so e.g. an unread count showing up
+    /// doesn't lose the window
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+impl Bookmark {
+    fn pattern(&self) -> String {
+        self.pattern
+            .clone()
+            .unwrap_or_else(|| format!("*{}*", self.title.to_lowercase()))
+    }
+}
+
+/// Slots 1..=9, set with Cmd+Shift+N in the picker and focused with Cmd+Option+N.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bookmarks {
+    slots: BTreeMap<u8, Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load() -> Self {
+        config::load_data(BOOKMARKS_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(BOOKMARKS_FILE, self)
+    }
+
+    pub fn set(&mut self, slot: u8, app: &App, window: &Window) {
+        self.slots.insert(
+            slot,
+            Bookmark {
+                app: app_key(app),
+                title: window.title.clone(),
+                pattern: Some(format!("*{}*", window.title.to_lowercase())),
+            },
+        );
+    }
+
+    /// Finds the bookmarked window: the exact title if it's still around, then one matching
+    /// the pattern, otherwise any window of the same app.
+    pub fn resolve(&self, slot: u8, manager: &Manager) -> Option<u32> {
+        let bookmark = self.slots.get(&slot)?;
+        let windows: Vec<&Window> = manager
+            .app_map()
+            .values()
+            .filter(|app| app_key(app) == bookmark.app)
+            .flat_map(|app| &app.windows)
+            .collect();
+
+        let pattern = bookmark.pattern();
+        windows
+            .iter()
+            .find(|w| w.title == bookmark.title)
+            .or_else(|| {
+                windows
+                    .iter()
+                    .find(|w| glob_matches(&pattern, &w.title.to_lowercase()))
+            })
+            .or_else(|| windows.first())
+            .map(|w| w.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn follows_title_changes_through_the_pattern() {
+        let mut manager = Manager::default();
+        manager.refresh_from(&fixture::two_displays()).unwrap();
+        let mut bookmarks = Bookmarks::default();
+        let (app, window) = manager.window(106).unwrap();
+        bookmarks.set(1, app, window);
+
+        manager.set_title(106, String::from("Keynote stream (live)"));
+        assert_eq!(bookmarks.resolve(1, &manager), Some(106));
+
+        bookmarks.slots.get_mut(&1).unwrap().pattern = Some(String::from("*notes"));
+        manager.set_title(106, String::from("Keynote"));
+        assert_eq!(bookmarks.resolve(1, &manager), Some(101));
+    }
+}
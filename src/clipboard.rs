@@ -0,0 +1,47 @@
+use std::collections::VecDeque;
+
+use crate::macos;
+
+/// Markers (see nspasteboard.org) for what shouldn't be kept: passwords, one-off content
+/// and what apps put there on their own
+const SKIPPED_TYPES: &[&str] = &[
+    "org.nspasteboard.ConcealedType",
+    "org.nspasteboard.TransientType",
+    "org.nspasteboard.AutoGeneratedType",
+];
+
+/// Recently copied text, newest first. Only kept in memory, so nothing copied ends up on disk.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: VecDeque<String>,
+    /// Pasteboard change count as of the last poll
+    change_count: isize,
+}
+
+impl History {
+    /// Records what's on the clipboard if it changed since the last poll, keeping `max` entries.
+    pub fn poll(&mut self, max: usize) {
+        let count = macos::clipboard_change_count();
+        if count == self.change_count {
+            return;
+        }
+        self.change_count = count;
+        if macos::clipboard_types()
+            .iter()
+            .any(|t| SKIPPED_TYPES.contains(&t.as_str()))
+        {
+            return;
+        }
+        let Some(text) = macos::clipboard_text().filter(|text| !text.trim().is_empty()) else {
+            return;
+        };
+        // Copying something again moves it to the front
+        self.entries.retain(|entry| *entry != text);
+        self.entries.push_front(text);
+        self.entries.truncate(max);
+    }
+
+    pub fn entries(&self) -> &VecDeque<String> {
+        &self.entries
+    }
+}
@@ -0,0 +1,111 @@
+/// Entries listed in command mode, i.e. when the query starts with `>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Back,
+    Forward,
+    Stats,
+    Snapshot,
+    Restore,
+    Space,
+    MissionControl,
+    Expose,
+    Hotkey,
+    Permissions,
+    Run,
+    Clip,
+    Link,
+}
+
+impl Command {
+    pub const ALL: &[Self] = &[
+        Self::Back,
+        Self::Forward,
+        Self::Stats,
+        Self::Snapshot,
+        Self::Restore,
+        Self::Space,
+        Self::MissionControl,
+        Self::Expose,
+        Self::Hotkey,
+        Self::Permissions,
+        Self::Run,
+        Self::Clip,
+        Self::Link,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Back => "back",
+            Self::Forward => "forward",
+            Self::Stats => "stats",
+            Self::Snapshot => "snapshot",
+            Self::Restore => "restore",
+            Self::Space => "space",
+            Self::MissionControl => "mission-control",
+            Self::Expose => "expose",
+            Self::Hotkey => "hotkey",
+            Self::Permissions => "permissions",
+            Self::Run => "run",
+            Self::Clip => "clip",
+            Self::Link => "link",
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Back => "Go back to the previously focused window",
+            Self::Forward => "Go forward again in the focus history",
+            Self::Stats => "Most switched-to windows and picker session times",
+            Self::Snapshot => "Save the current window layout as <name>",
+            Self::Restore => "Restore the window layout saved as <name>",
+            Self::Space => "Switch to space <n>, on the display under the cursor or <display>",
+            Self::MissionControl => "Open Mission Control",
+            Self::Expose => "App Exposé for the previously focused app, or the app matching <name>",
+            Self::Hotkey => "Change the picker hotkey to <combo>, e.g. cmd+alt+Space",
+            Self::Permissions => "Accessibility and Screen Recording status",
+            Self::Run => "Run the function <name> from init.rhai",
+            Self::Clip => "Search the clipboard history for <text>, Enter pastes",
+            Self::Link => {
+                "Copy a switcheroo:// link to the previous window or the one matching <text>"
+            }
+        }
+    }
+
+    /// Goes through the Dock's private CoreDockSendNotification, so only with `private_apis`.
+    pub const fn private(self) -> bool {
+        matches!(self, Self::MissionControl | Self::Expose)
+    }
+
+    /// The commands there are, leaving out private ones without `private_apis`.
+    pub fn available(private_apis: bool) -> impl Iterator<Item = Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(move |c| private_apis || !c.private())
+    }
+}
+
+pub const PREFIX: char = '>';
+
+/// Returns the command-mode part of the query, if the query is in command mode.
+pub fn command_query(query: &str) -> Option<&str> {
+    query.strip_prefix(PREFIX).map(str::trim)
+}
+
+/// Splits a command-mode query into the command word and its argument.
+pub fn split(query: &str) -> (&str, &str) {
+    match query.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (query, ""),
+    }
+}
+
+/// Available commands whose name contains the query's command word, prefix matches first.
+pub fn filter(query: &str, private_apis: bool) -> Vec<Command> {
+    let query = split(query).0.to_lowercase();
+    let mut matches: Vec<Command> = Command::available(private_apis)
+        .filter(|c| c.name().contains(query.as_str()))
+        .collect();
+    matches.sort_by_key(|c| !c.name().starts_with(query.as_str()));
+    matches
+}
@@ -0,0 +1,735 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::crash::log;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Raise the selected window behind the picker while navigating
+    pub peek: bool,
+    /// What happens to the cursor (or the window) when focusing a window
+    pub focus_mode: FocusMode,
+    /// What Cmd+Enter does with the selected window instead
+    pub alt_action: AltAction,
+    /// Keep the previous query (selected, so typing replaces it) when the picker reopens
+    pub preserve_query: bool,
+    pub saved_searches: Vec<SavedSearch>,
+    pub hotkeys: Hotkeys,
+    pub hooks: Hooks,
+    /// Register Cmd+Option+1..9 to focus bookmarked windows
+    pub bookmarks: bool,
+    pub scratchpads: Vec<Scratchpad>,
+    /// Added to the rank of an app's windows, keyed by bundle id or app name
+    pub boosts: HashMap<String, i64>,
+    pub frecency: Frecency,
+    /// How an app's windows are matched, keyed by bundle id or app name
+    pub matchers: HashMap<String, AppMatcher>,
+    /// Show each row's score breakdown
+    pub debug_scores: bool,
+    /// Rows show app names and icons but no window titles, for screen sharing. Still
+    /// searched by title
+    pub privacy: bool,
+    /// Leave out windows with an empty title
+    pub hide_untitled: bool,
+    /// Leave out windows whose title matches one of these globs (`*` and `?`, any case)
+    pub exclude_titles: Vec<String>,
+    /// Bundle ids or app names whose windows are never listed
+    pub exclude_apps: Vec<String>,
+    /// Show windows of the same app with identical titles as one row
+    pub merge_duplicates: bool,
+    /// What result rows are ordered by
+    pub sort: Sort,
+    /// Once the selection is moved, keep rows where they are until the query changes
+    pub stable_order: bool,
+    /// Windows in the "Recent" section on top of an empty query, 0 turns it off
+    pub recent: usize,
+    /// Titles longer than this are cut in the list (still searched in full)
+    pub max_title_chars: usize,
+    /// Where the cut title gets its "…"
+    pub title_ellipsis: Ellipsis,
+    /// Show a small picture of the window with its full title on hover, or after the
+    /// selection stays put for a moment
+    pub window_snapshots: bool,
+    /// How long the picker takes to fade in and out, 0 (or Reduce Motion) turns it off
+    pub animation_ms: u64,
+    /// Seconds between refreshes of the list while the picker stays open, 0 turns them off
+    pub refresh_secs: u64,
+    /// Focus the window that was in front at logout again when switcheroo next starts
+    pub restore_focus: bool,
+    /// Focus the only window left matching the query once it's been the only one for this
+    /// long, 0 turns it off
+    pub auto_confirm_ms: u64,
+    pub layers: Layers,
+    /// Bundle ids of menu-bar (accessory) apps whose windows should be listed too
+    pub accessory_apps: Vec<String>,
+    /// Ids of the [`crate::sources`] whose rows are listed after the windows
+    pub sources: Vec<String>,
+    /// Entries of your own listed after the windows while searching
+    pub quick_commands: Vec<QuickCommand>,
+    /// Copied texts to remember for `> clip`, 0 turns clipboard history off
+    pub clipboard_history: usize,
+    /// Whether hotkeys fire when pressed or when released
+    pub hotkey_trigger: Trigger,
+    /// Open the picker by pushing the cursor into a screen corner or edge
+    pub hot_corner: Option<HotCorner>,
+    pub icon_style: IconStyle,
+    /// App icon size in points, 16 to 48
+    pub icon_size: u16,
+    /// Show how many windows an app has on its icon
+    pub window_count_badge: bool,
+    pub space_accents: SpaceAccents,
+    /// What the picker does while the screen is shared or mirrored
+    pub while_sharing: WhileSharing,
+    pub theme: Theme,
+    /// What each result row shows, left to right
+    pub columns: Vec<Column>,
+    pub ax: Ax,
+    /// Focus and move windows through SkyLight. Without it focusing sticks to public APIs,
+    /// so no instant space switches or pulling windows over from other spaces
+    pub private_apis: bool,
+    /// Keys a configuration profile sets, see [`MANAGED_KEYS`], plus e.g. `hotkeys.picker`
+    /// for the entries of tables it sets
+    #[serde(skip)]
+    pub managed: Vec<String>,
+}
+
+/// Top-level keys a configuration profile can set in [`crate::macos::PREFERENCES_DOMAIN`],
+/// winning over config.toml.
+const MANAGED_KEYS: &[&str] = &[
+    "hotkeys",
+    "exclude_apps",
+    "exclude_titles",
+    "private_apis",
+    "accessory_apps",
+    "hooks",
+    "quick_commands",
+    "sources",
+    "clipboard_history",
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            peek: false,
+            focus_mode: FocusMode::WarpCursor,
+            alt_action: AltAction::BringHere,
+            preserve_query: false,
+            saved_searches: Vec::new(),
+            hotkeys: Hotkeys::default(),
+            hooks: Hooks::default(),
+            bookmarks: true,
+            scratchpads: Vec::new(),
+            boosts: HashMap::new(),
+            frecency: Frecency::default(),
+            matchers: HashMap::new(),
+            debug_scores: false,
+            privacy: false,
+            hide_untitled: false,
+            exclude_titles: Vec::new(),
+            exclude_apps: Vec::new(),
+            merge_duplicates: false,
+            sort: Sort::Rank,
+            stable_order: false,
+            recent: 5,
+            max_title_chars: 80,
+            title_ellipsis: Ellipsis::End,
+            window_snapshots: false,
+            animation_ms: 120,
+            refresh_secs: 3,
+            auto_confirm_ms: 0,
+            restore_focus: false,
+            layers: Layers::default(),
+            accessory_apps: Vec::new(),
+            sources: Vec::new(),
+            quick_commands: Vec::new(),
+            clipboard_history: 0,
+            hotkey_trigger: Trigger::Release,
+            hot_corner: None,
+            icon_style: IconStyle::Color,
+            icon_size: 20,
+            window_count_badge: false,
+            space_accents: SpaceAccents::Badge,
+            while_sharing: WhileSharing::Show,
+            theme: Theme::default(),
+            columns: vec![
+                Column::new(ColumnKind::Icon, None),
+                Column::new(ColumnKind::App, Some(150.0)),
+                Column::new(ColumnKind::Title, None),
+                Column::new(ColumnKind::Display, None),
+            ],
+            ax: Ax::default(),
+            private_apis: true,
+            managed: Vec::new(),
+        }
+    }
+}
+
+/// Matcher tweaks for one app's windows, see [`Config::matchers`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AppMatcher {
+    /// Match titles on what follows their last `/` only, e.g. a shell's working directory
+    pub last_path_component: bool,
+    /// Respect case even for an all-lowercase query
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Column {
+    pub kind: ColumnKind,
+    /// Fixed width in points; the title fills the leftover space without one
+    pub width: Option<f32>,
+}
+
+impl Column {
+    const fn new(kind: ColumnKind, width: Option<f32>) -> Self {
+        Self { kind, width }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColumnKind {
+    Icon,
+    App,
+    Title,
+    /// "Space N" on its display
+    Space,
+    /// Display name, only with more than one display
+    Display,
+    BundleId,
+    WindowId,
+    /// When the window was last focused, e.g. "2m ago"
+    LastActive,
+}
+
+/// Picker dimensions, in points.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Picker window height, the list shows as many rows as fit
+    pub height: f32,
+    pub row_height: f32,
+    /// Left and right padding inside a row
+    pub row_padding: f32,
+    /// Gap between rows
+    pub row_spacing: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            height: 380.0,
+            row_height: 28.0,
+            row_padding: 8.0,
+            row_spacing: 1.0,
+        }
+    }
+}
+
+impl Theme {
+    /// Row sizes rounded to whole pixels at `scale`, so rows don't land between pixels and blur
+    /// on displays like 1.5x ones. A nonzero gap stays at least a pixel.
+    pub fn snapped(&self, scale: f64) -> Self {
+        let scale = scale.max(1.0) as f32;
+        let snap = |points: f32| (points * scale).round() / scale;
+        Self {
+            height: self.height,
+            row_height: snap(self.row_height),
+            row_padding: snap(self.row_padding),
+            row_spacing: if self.row_spacing > 0.0 {
+                snap(self.row_spacing).max(1.0 / scale)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// How long to wait on apps over Accessibility, in milliseconds. Slow (Electron) apps
+/// may need more to have all their windows listed, at the cost of slower refreshes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Ax {
+    /// For any single AX call
+    pub timeout_ms: u64,
+    /// For finding one app's windows on refresh, apps with few windows get less
+    pub lookup_ms: u64,
+}
+
+impl Default for Ax {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 500,
+            lookup_ms: 500,
+        }
+    }
+}
+
+impl Ax {
+    /// Below this even responsive apps don't make it
+    const FLOOR_MS: u64 = 50;
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.max(Self::FLOOR_MS))
+    }
+
+    pub fn lookup(&self) -> Duration {
+        Duration::from_millis(self.lookup_ms.max(Self::FLOOR_MS))
+    }
+}
+
+/// How past switches add to a window's rank, see [`crate::rank`]. With a handful of windows
+/// habit says a lot, with a hundred the query had better win.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Frecency {
+    /// Days until a switch counts half as much
+    pub half_life_days: f64,
+    /// Points per switch, before it decays
+    pub per_switch: f64,
+    /// Cap on frecency points, so a habitual window can't outrank a clearly better match
+    pub max: f64,
+    /// Points for a window used just now, halving every hour
+    pub recency_bonus: f64,
+    /// Frecency and recency are multiplied by this before being added to the fuzzy score
+    pub weight: f64,
+}
+
+impl Default for Frecency {
+    fn default() -> Self {
+        Self {
+            half_life_days: 7.0,
+            per_switch: 5.0,
+            max: 60.0,
+            recency_bonus: 0.0,
+            weight: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconStyle {
+    #[default]
+    Color,
+    Grayscale,
+    /// Monochrome in the highlight color
+    Tinted,
+}
+
+/// Each space gets a color of its own (by number, so Space 2 keeps its color), for telling
+/// rows of different spaces apart at a glance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpaceAccents {
+    Off,
+    /// Tint the space badge
+    #[default]
+    Badge,
+    /// That and a thin stripe down the left edge of the row
+    Border,
+}
+
+/// Keeps window titles from being flashed to an audience, see [`crate::macos::screen_shared`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhileSharing {
+    #[default]
+    Show,
+    /// App names and icons only, no titles or snapshots
+    Minimal,
+    /// Don't open at all
+    Hide,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HotCorner {
+    pub edge: Edge,
+    /// How long the cursor has to stay there, so passing through doesn't count
+    #[serde(default = "default_hot_corner_delay")]
+    pub delay_ms: u64,
+}
+
+fn default_hot_corner_delay() -> u64 {
+    150
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Edge {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// Snappier, fires as soon as the combo goes down
+    Press,
+    #[default]
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusMode {
+    /// Move the cursor to the center of the focused window
+    #[default]
+    WarpCursor,
+    /// Move the focused window so it's centered under the cursor
+    MoveWindow,
+    /// Leave both where they are
+    Stay,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AltAction {
+    /// Move the window to this space and display, centered
+    #[default]
+    BringHere,
+    /// Focus it like Enter, but without touching the cursor (or the window)
+    FocusWithoutMouseWarp,
+    /// Focus it, then fill the display with it
+    FocusAndMaximize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sort {
+    /// Fuzzy score plus frecency and boosts
+    #[default]
+    Rank,
+    /// Most recently focused first, rank breaks ties
+    LastActive,
+    /// Apps in their Dock order, the rest after them by name; rank within an app
+    Dock,
+    /// Apps in the order they were launched, oldest first; rank within an app
+    Launched,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ellipsis {
+    /// "…src/ui.rs", keeps the end (file names in paths)
+    Start,
+    /// "~/code/…/ui.rs"
+    Middle,
+    #[default]
+    End,
+}
+
+/// Shell commands run after something happens, see [`crate::hooks`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// After a window was focused
+    pub on_focus: Option<String>,
+    /// When the picker opens, with the window that was in front
+    pub on_show: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Hotkeys {
+    /// Opens the picker, Cmd+D if unset
+    pub picker: Option<String>,
+    /// Jump back through the focus history
+    pub back: Option<String>,
+    /// Jump forward again after going back
+    pub forward: Option<String>,
+    /// Toggles `privacy`
+    pub privacy: Option<String>,
+}
+
+/// Windows off the normal layer (floating palettes, PiP, utility panels) are left out
+/// unless listed here.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Layers {
+    /// Window layers to list besides 0, e.g. 3 for floating windows
+    pub include: Vec<i32>,
+    /// Bundle ids or app names whose windows are listed whatever their layer
+    pub apps: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    /// Applied with Ctrl+<key> while the picker is open
+    pub key: Option<String>,
+    /// Global hotkey that opens the picker pre-filtered, e.g. "cmd+alt+KeyP"
+    pub hotkey: Option<String>,
+}
+
+/// A search result that runs a shell command or opens a URL on Enter.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QuickCommand {
+    pub name: String,
+    /// Path of a file or app whose icon to show
+    pub icon: Option<String>,
+    /// Run with `sh -c`
+    pub command: Option<String>,
+    /// Opened with the default handler, when there's no command
+    pub url: Option<String>,
+}
+
+/// A window toggled onto the current space (centered and focused) and hidden again by a hotkey.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scratchpad {
+    /// Bundle id or app name
+    pub app: String,
+    /// Only windows whose title contains this
+    pub title: Option<String>,
+    pub hotkey: String,
+}
+
+impl Config {
+    pub fn icon_size(&self) -> u16 {
+        self.icon_size.clamp(16, 48)
+    }
+
+    /// Loads `~/.config/switcheroo/config.toml`, falling back to defaults if it's missing or broken.
+    /// Managed settings apply either way.
+    pub fn load() -> Self {
+        match Self::try_load() {
+            Ok(config) => config,
+            Err(e) => {
+                log!("[warn] failed to load config, using defaults: {e:#}");
+                Self::with_managed(toml::Table::new()).unwrap_or_else(|e| {
+                    log!("[warn] ignoring managed settings: {e:#}");
+                    Self::default()
+                })
+            }
+        }
+    }
+
+    pub fn try_load() -> Result<Self> {
+        let path = config_path();
+        let mut table = toml::Table::new();
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            table = toml::from_str(&contents)
+                .with_context(|| format!("Could not parse {}", path.display()))?;
+        }
+        Self::with_managed(table).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    /// The config in `table` with what a configuration profile forces on top.
+    fn with_managed(table: toml::Table) -> Result<Self> {
+        Self::overlaid(table, crate::macos::forced_preferences(MANAGED_KEYS))
+    }
+
+    /// The config in `table` with `forced` on top. A forced value that doesn't fit is logged
+    /// and left out, the rest still apply.
+    fn overlaid(
+        mut table: toml::Table,
+        forced: impl IntoIterator<Item = (String, serde_json::Value)>,
+    ) -> Result<Self> {
+        let mut config: Self = toml::Value::Table(table.clone()).try_into()?;
+        let mut managed = Vec::new();
+        for (key, value) in forced {
+            let mut candidate = table.clone();
+            let result = toml::Value::try_from(value)
+                .map_err(anyhow::Error::from)
+                .and_then(|value| {
+                    let names = managed_names(&key, &value);
+                    overlay(&mut candidate, key.clone(), value);
+                    let config: Self = toml::Value::Table(candidate.clone()).try_into()?;
+                    Ok((config, names))
+                });
+            match result {
+                Ok((forced, names)) => {
+                    (config, table) = (forced, candidate);
+                    managed.extend(names);
+                }
+                Err(e) => log!("[warn] ignoring managed setting {key}: {e:#}"),
+            }
+        }
+        if !managed.is_empty() {
+            log!("managed by a configuration profile: {}", managed.join(", "));
+        }
+        config.managed = managed;
+        Ok(config)
+    }
+
+    /// Whether a configuration profile sets `key`, so changing it here won't stick.
+    pub fn is_managed(&self, key: &str) -> bool {
+        self.managed.iter().any(|k| k == key)
+    }
+}
+
+/// `key`, and `key.entry` for each entry when the profile sets a table like `hotkeys`.
+fn managed_names(key: &str, value: &toml::Value) -> Vec<String> {
+    let entries = value
+        .as_table()
+        .into_iter()
+        .flat_map(|entries| entries.keys())
+        .map(|entry| format!("{key}.{entry}"));
+    std::iter::once(key.to_string()).chain(entries).collect()
+}
+
+/// Sets `key` in `table`, merging into a table that's already there so a profile can set
+/// single hotkeys.
+fn overlay(table: &mut toml::Table, key: String, value: toml::Value) {
+    if let toml::Value::Table(entries) = &value
+        && let Some(toml::Value::Table(existing)) = table.get_mut(&key)
+    {
+        for (key, value) in entries.clone() {
+            overlay(existing, key, value);
+        }
+        return;
+    }
+    table.insert(key, value);
+}
+
+/// When the config file was last changed, None if there isn't one.
+pub fn modified() -> Option<SystemTime> {
+    std::fs::metadata(config_path())
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_default()
+        .join("switcheroo")
+}
+
+/// Where persisted state (stats etc.) lives: ~/Library/Application Support/switcheroo
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join("Library/Application Support/switcheroo")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Loads a JSON file from the data dir, falling back to the default if missing or broken.
+pub fn load_data<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = data_dir().join(name);
+    if !path.exists() {
+        return T::default();
+    }
+    match std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|s| serde_json::from_str(&s).map_err(anyhow::Error::from))
+    {
+        Ok(data) => data,
+        Err(e) => {
+            log!("[warn] could not load {}: {e}", path.display());
+            T::default()
+        }
+    }
+}
+
+pub fn save_data<T: Serialize>(name: &str, data: &T) -> Result<()> {
+    let path = data_dir().join(name);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+    let json = serde_json::to_string(data)?;
+    std::fs::write(&path, json).with_context(|| format!("Could not write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn managed_settings_override_config() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            exclude_titles = ["*Private*"]
+            [hotkeys]
+            picker = "cmd+KeyD"
+            back = "cmd+alt+BracketLeft"
+            "#,
+        )
+        .unwrap();
+        let managed: toml::Table = toml::from_str(
+            r#"
+            exclude_titles = ["*Payroll*"]
+            private_apis = false
+            [hotkeys]
+            picker = "hyper+KeyK"
+            "#,
+        )
+        .unwrap();
+        for (key, value) in managed {
+            overlay(&mut table, key, value);
+        }
+
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(config.exclude_titles, ["*Payroll*"]);
+        assert!(!config.private_apis);
+        assert_eq!(config.hotkeys.picker.as_deref(), Some("hyper+KeyK"));
+        assert_eq!(config.hotkeys.back.as_deref(), Some("cmd+alt+BracketLeft"));
+    }
+
+    #[test]
+    fn bad_managed_settings_are_left_out() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            private_apis = false
+            exclude_apps = ["Finder"]
+            "#,
+        )
+        .unwrap();
+        let forced = [
+            (String::from("private_apis"), serde_json::json!("yes")),
+            (
+                String::from("exclude_titles"),
+                serde_json::json!(["*Payroll*"]),
+            ),
+            (
+                String::from("hotkeys"),
+                serde_json::json!({ "picker": "hyper+KeyK" }),
+            ),
+        ];
+
+        let config = Config::overlaid(table, forced).unwrap();
+        assert!(!config.private_apis);
+        assert_eq!(config.exclude_apps, ["Finder"]);
+        assert_eq!(config.exclude_titles, ["*Payroll*"]);
+        assert!(config.is_managed("hotkeys.picker"));
+        assert!(!config.is_managed("private_apis"));
+    }
+
+    #[test]
+    fn theme_snaps_to_pixels() {
+        let theme = Theme {
+            row_height: 27.7,
+            row_spacing: 0.2,
+            ..Theme::default()
+        };
+        assert_eq!(theme.snapped(1.5).row_height, 28.0);
+        let retina = theme.snapped(2.0);
+        assert_eq!(retina.row_height, 27.5);
+        // Rounds down to nothing, kept at a pixel
+        assert_eq!(retina.row_spacing, 0.5);
+        assert_eq!(retina.row_padding, 8.0);
+        assert_eq!(retina.height, theme.height);
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use crate::config::{Edge, HotCorner};
+use crate::macos;
+
+/// Pixels from the edge that still count as being in it
+const REACH: f64 = 2.0;
+/// How far the cursor has to back off before the corner can fire again
+const REARM: f64 = 40.0;
+
+/// Polls the cursor for a hot corner hit, firing once per visit.
+#[derive(Debug, Default)]
+pub struct Detector {
+    entered: Option<Instant>,
+    fired: bool,
+}
+
+impl Detector {
+    pub fn poll(&mut self, corner: &HotCorner) -> bool {
+        let Some((x, y)) = macos::cursor_location() else {
+            return false;
+        };
+        // Corners of whichever display the cursor is on
+        let Some((sx, sy, sw, sh)) = macos::active_display_frame_at_cursor() else {
+            return false;
+        };
+        let distance = distance(
+            corner.edge,
+            x - sx as f64,
+            y - sy as f64,
+            sw as f64,
+            sh as f64,
+        );
+
+        if self.fired {
+            self.fired = distance <= REARM;
+            return false;
+        }
+        if distance > REACH {
+            self.entered = None;
+            return false;
+        }
+
+        let entered = *self.entered.get_or_insert_with(Instant::now);
+        if entered.elapsed() < Duration::from_millis(corner.delay_ms) {
+            return false;
+        }
+        self.entered = None;
+        self.fired = true;
+        true
+    }
+}
+
+/// How far a display-relative cursor position is from the edge (for corners, from both edges).
+fn distance(edge: Edge, x: f64, y: f64, width: f64, height: f64) -> f64 {
+    let left = x;
+    let right = width - 1.0 - x;
+    let top = y;
+    let bottom = height - 1.0 - y;
+    match edge {
+        Edge::TopLeft => left.max(top),
+        Edge::TopRight => right.max(top),
+        Edge::BottomLeft => left.max(bottom),
+        Edge::BottomRight => right.max(bottom),
+        Edge::Top => top,
+        Edge::Bottom => bottom,
+        Edge::Left => left,
+        Edge::Right => right,
+    }
+}
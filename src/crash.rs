@@ -0,0 +1,83 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const MAX_RECENT_LINES: usize = 200;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// `eprintln!` that also keeps the line around for crash reports.
+macro_rules! log {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        eprintln!("{line}");
+        $crate::crash::remember(line);
+    }};
+}
+pub(crate) use log;
+
+pub fn remember(line: String) {
+    let Ok(mut recent) = RECENT.lock() else {
+        return;
+    };
+    if recent.len() == MAX_RECENT_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(line);
+}
+
+fn crash_log_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join("Library/Logs/switcheroo/last-crash.log")
+}
+
+/// Writes panics (message, backtrace and recent log lines) to ~/Library/Logs/switcheroo,
+/// since an Accessory app that dies silently just looks like a broken hotkey.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let mut report = format!("{info}\n\n{}\n\nrecent log:\n", Backtrace::force_capture());
+        // Don't block on the lock: the panic may have happened while holding it
+        if let Ok(recent) = RECENT.try_lock() {
+            for line in recent.iter() {
+                report.push_str(line);
+                report.push('\n');
+            }
+        }
+
+        let path = crash_log_path();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&path, report) {
+            eprintln!("[crash] could not write {}: {e}", path.display());
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// Lets the user know about a crash from the previous run, then archives its log.
+pub fn report_previous_crash() {
+    let path = crash_log_path();
+    if !path.exists() {
+        return;
+    }
+
+    let message = format!(
+        "switcheroo crashed last time, see {}",
+        path.with_file_name("previous-crash.log").display()
+    );
+    log!("[crash] {message}");
+    let _ = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {message:?} with title \"switcheroo\""
+        ))
+        .spawn();
+
+    let _ = std::fs::rename(&path, path.with_file_name("previous-crash.log"));
+}
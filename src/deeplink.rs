@@ -0,0 +1,148 @@
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::stats::app_key;
+use crate::windows::{App, Manager, Window};
+
+const SCHEME: &str = "switcheroo://";
+
+/// A `switcheroo://focus?window=<id>` link. App and title come along so the window can still
+/// be found once its id is gone (closed and reopened, switcheroo restarted).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Link {
+    pub window: Option<u32>,
+    pub app: Option<String>,
+    pub title: Option<String>,
+}
+
+impl Link {
+    pub fn to_window(app: &App, window: &Window) -> Self {
+        Self {
+            window: Some(window.id),
+            app: Some(app_key(app)),
+            title: Some(window.title.clone()),
+        }
+    }
+
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| anyhow!("{url:?} is not a switcheroo link"))?;
+        let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+        if action.trim_end_matches('/') != "focus" {
+            bail!("Unknown link action {action:?}");
+        }
+
+        let mut link = Self::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = decode(value);
+            match key {
+                "window" => {
+                    link.window = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("Bad window id {value:?}"))?,
+                    );
+                }
+                "app" => link.app = Some(value),
+                "title" => link.title = Some(value),
+                _ => {}
+            }
+        }
+        if link.window.is_none() && link.app.is_none() {
+            bail!("{url:?} doesn't name a window or app");
+        }
+        Ok(link)
+    }
+
+    pub fn url(&self) -> String {
+        let params: Vec<String> = [
+            ("window", self.window.map(|id| id.to_string())),
+            ("app", self.app.clone()),
+            ("title", self.title.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", encode(&value?))))
+        .collect();
+        format!("{SCHEME}focus?{}", params.join("&"))
+    }
+
+    /// The window with the link's id while it's open, otherwise the app's window with the
+    /// same title (any of its windows for links without one).
+    pub fn resolve(&self, manager: &Manager) -> Option<u32> {
+        if let Some(wid) = self.window
+            && manager.window(wid).is_some()
+        {
+            return Some(wid);
+        }
+        let app = self.app.as_deref()?;
+        manager
+            .app_map()
+            .values()
+            .filter(|a| app_key(a) == app)
+            .flat_map(|a| &a.windows)
+            .find(|w| self.title.as_deref().is_none_or(|title| w.title == title))
+            .map(|w| w.id)
+    }
+}
+
+/// Percent-encodes everything but unreserved characters.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Undoes [`encode`], plus `+` for spaces as hand-written links tend to have.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (escaped, bytes[i]) {
+            (Some(b), _) => {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+            (None, b'+') => out.push(b' '),
+            (None, b) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_round_trip() {
+        let link = Link {
+            window: Some(4711),
+            app: Some(String::from("com.apple.Safari")),
+            title: Some(String::from("Pull request #12 – a/b & c?")),
+        };
+        assert_eq!(Link::parse(&link.url()).unwrap(), link);
+
+        let by_hand = Link::parse("switcheroo://focus/?app=Notes&title=To+do%21").unwrap();
+        assert_eq!(by_hand.window, None);
+        assert_eq!(by_hand.title.as_deref(), Some("To do!"));
+
+        assert!(Link::parse("switcheroo://focus?window=abc").is_err());
+        assert!(Link::parse("switcheroo://focus").is_err());
+        assert!(Link::parse("switcheroo://close?window=1").is_err());
+        assert!(Link::parse("https://focus?window=1").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::config::{self, Config};
+use crate::macos;
+use crate::slow_apps::SlowApps;
+use crate::stats::app_key;
+use crate::windows::Manager;
+
+/// `switcheroo doctor`: checks permissions and private APIs, times a refresh and every
+/// app's AX lookup, and prints it all for bug reports.
+pub fn run() -> Result<()> {
+    let mut problems = 0;
+    let mut check = |ok: bool, what: &str, hint: &str| {
+        if ok {
+            println!("  [ok]   {what}");
+        } else {
+            println!("  [fail] {what}: {hint}");
+            problems += 1;
+        }
+    };
+
+    println!("switcheroo {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "macOS {}",
+        macos::os_version().as_deref().unwrap_or("(unknown)")
+    );
+    println!("config {}", config::config_path().display());
+
+    println!("\nPermissions");
+    check(
+        macos::accessibility_granted(),
+        "Accessibility",
+        "needed to focus windows, grant it in System Settings > Privacy & Security",
+    );
+    check(
+        macos::screen_recording_granted(),
+        "Screen Recording",
+        "needed for window titles, grant it in System Settings > Privacy & Security",
+    );
+
+    let config = Config::load();
+    let mut manager = Manager::new(&config);
+    // Only reporting, what refreshes learn about slow apps shouldn't stick
+    manager.read_only();
+    println!("\nPrivate APIs");
+    if manager.private_apis() {
+        // The symbols are linked at launch, so they're there; what can change is what they do
+        check(
+            unsafe { macos::SLSMainConnectionID() } != 0,
+            "SLSMainConnectionID",
+            "no connection, this macOS release probably changed it",
+        );
+        check(
+            !macos::copy_managed_display_spaces().is_empty(),
+            "SLSCopyManagedDisplaySpaces",
+            "no displays listed, this macOS release probably changed it",
+        );
+    } else {
+        let reason = if !cfg!(feature = "private-apis") {
+            "built without the private-apis feature"
+        } else if macos::sandboxed() {
+            "running in the App Sandbox"
+        } else {
+            "private_apis = false"
+        };
+        println!("  off, public APIs only ({reason})");
+    }
+    println!("\nWindows");
+    let cold = time(|| manager.refresh())?;
+    let warm = time(|| manager.refresh())?;
+    let windows: usize = manager
+        .app_map()
+        .values()
+        .map(|app| app.windows.len())
+        .sum();
+    println!("  refresh {} cold, {} cached", millis(cold), millis(warm));
+    println!(
+        "  {windows} windows of {} apps listed",
+        manager.app_map().len()
+    );
+    check(
+        windows > 0,
+        "Windows found",
+        "nothing listed, check the permissions above",
+    );
+
+    // The lookup below goes through a private AX call
+    if !manager.private_apis() {
+        return summary(problems);
+    }
+    println!(
+        "\nAX lookups (timeout {}, lookup budget {})",
+        millis(config.ax.timeout()),
+        millis(config.ax.lookup())
+    );
+    let slow_apps = SlowApps::load();
+    let mut lookups = Vec::new();
+    for (pid, app) in manager.app_map() {
+        let wids: HashSet<u32> = app.windows.iter().map(|w| w.id).collect();
+        let start = Instant::now();
+        let (found, timed_out) = macos::resolve_ax_for_pid(*pid, &wids, config.ax.lookup());
+        lookups.push((
+            start.elapsed(),
+            app_key(app),
+            found.len(),
+            wids.len(),
+            timed_out,
+        ));
+    }
+    lookups.sort_by(|a, b| b.0.cmp(&a.0));
+    for (elapsed, app, found, total, timed_out) in lookups {
+        let mut notes = Vec::new();
+        if timed_out {
+            notes.push("timed out");
+        }
+        if slow_apps.is_slow(&app) {
+            notes.push("resolved on demand");
+        }
+        println!(
+            "  {:>7}  {found}/{total} windows  {app} {}",
+            millis(elapsed),
+            notes.join(", ")
+        );
+    }
+
+    summary(problems)
+}
+
+fn summary(problems: usize) -> Result<()> {
+    println!();
+    match problems {
+        0 => println!("No problems found"),
+        1 => println!("1 problem found"),
+        n => println!("{n} problems found"),
+    }
+    Ok(())
+}
+
+fn time(f: impl FnOnce() -> Result<()>) -> Result<Duration> {
+    let start = Instant::now();
+    f()?;
+    Ok(start.elapsed())
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{}ms", duration.as_millis())
+}
@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use objc2_app_kit::NSApplicationActivationPolicy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::macos;
+use crate::windows::{Live, System};
+
+/// Everything a window refresh reads from macOS, as JSON. `switcheroo record-fixture <file>`
+/// writes one, tests replay them through [`crate::windows::Manager::refresh_from`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    /// SLSCopyManagedDisplaySpaces output
+    pub display_spaces: Value,
+    pub spans_displays: bool,
+    /// Window ids on each space, front to back
+    pub space_windows: BTreeMap<u64, Vec<u32>>,
+    /// CGWindowListCopyWindowInfo output
+    pub window_list: Value,
+    pub apps: Vec<FixtureApp>,
+    pub display_names: HashMap<String, String>,
+    /// x, y, width and height of each display, top-left origin
+    #[serde(default)]
+    pub display_frames: BTreeMap<String, [f64; 4]>,
+    pub active_space: u64,
+    pub cursor_display: Option<String>,
+    /// Windows whose AX element was found, with what it said
+    pub ax: BTreeMap<u32, FixtureAx>,
+    /// Recorded without Accessibility access
+    #[serde(default)]
+    pub ax_denied: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FixtureApp {
+    pub pid: i32,
+    pub name: String,
+    pub bundle_id: Option<String>,
+    /// "regular", "accessory" or "prohibited"
+    pub policy: String,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureAx {
+    pub minimized: bool,
+    pub fullscreen: bool,
+    pub document: Option<String>,
+}
+
+/// `switcheroo record-fixture <file>`. Window titles and document paths end up in the file,
+/// so look it over before sharing it.
+pub fn record(path: Option<String>) -> Result<()> {
+    let path = path.context("Usage: switcheroo record-fixture <file>")?;
+    let live = Live;
+    let display_spaces = live.display_spaces();
+    let spans_displays = live.spans_displays();
+    let displays = macos::parse_display_spaces(&display_spaces, spans_displays);
+    let space_windows: BTreeMap<u64, Vec<u32>> = displays
+        .iter()
+        .flat_map(|(_, spaces)| spaces)
+        .map(|space| (space.id, live.windows_on_space(space.id)))
+        .collect();
+    let window_list = live.window_list().context("Could not list windows")?;
+
+    let visible = macos::locate_windows(&displays, |space| {
+        space_windows.get(&space).cloned().unwrap_or_default()
+    });
+    let mut wids_by_pid: HashMap<i32, HashSet<u32>> = HashMap::new();
+    for info in macos::parse_window_list(&window_list, &visible) {
+        wids_by_pid.entry(info.pid).or_default().insert(info.id);
+    }
+    let mut ax = BTreeMap::new();
+    for (pid, wids) in &wids_by_pid {
+        let (resolved, _) = live.resolve_windows(*pid, wids, Duration::from_secs(1));
+        for (wid, element) in resolved {
+            let state = live.window_state(wid, &element);
+            let recorded = FixtureAx {
+                minimized: state.minimized,
+                fullscreen: state.fullscreen,
+                document: live.document(wid, &element),
+            };
+            ax.insert(wid, recorded);
+        }
+    }
+
+    let apps = live
+        .running_apps()
+        .into_iter()
+        .map(|app| FixtureApp {
+            pid: app.pid,
+            name: app.name,
+            bundle_id: app.bundle_id,
+            policy: match app.policy {
+                NSApplicationActivationPolicy::Regular => "regular",
+                NSApplicationActivationPolicy::Accessory => "accessory",
+                _ => "prohibited",
+            }
+            .to_string(),
+            hidden: app.hidden,
+        })
+        .collect();
+
+    let fixture = Fixture {
+        display_spaces: macos::cf_to_json(&display_spaces),
+        spans_displays,
+        space_windows,
+        window_list: macos::cf_to_json(&window_list),
+        apps,
+        display_names: live.display_names(),
+        display_frames: live
+            .display_frames()
+            .into_iter()
+            .map(|(uuid, f)| (uuid, [f.origin.x, f.origin.y, f.size.width, f.size.height]))
+            .collect(),
+        active_space: live.active_space(),
+        cursor_display: live.cursor_display(),
+        ax,
+        ax_denied: !live.ax_trusted(),
+    };
+    std::fs::write(&path, serde_json::to_string_pretty(&fixture)?)
+        .with_context(|| format!("Could not write {path}"))?;
+    println!("Wrote {path}");
+    Ok(())
+}
+
+/// Two displays, a fullscreen space and a bit of everything that shouldn't be listed.
+#[cfg(test)]
+pub fn two_displays() -> Fixture {
+    serde_json::from_str(include_str!("../tests/fixtures/two_displays.json")).unwrap()
+}
+
+#[cfg(test)]
+mod replay {
+    use std::collections::{HashMap, HashSet};
+    use std::time::Duration;
+
+    use objc2::rc::Retained;
+    use objc2_app_kit::{NSApplicationActivationPolicy, NSRunningApplication};
+    use objc2_application_services::AXUIElement;
+    use objc2_core_foundation::{CFArray, CFRetained, CGPoint, CGRect, CGSize};
+    use serde_json::Value;
+
+    use super::Fixture;
+    use crate::macos::{self, CFDict};
+    use crate::windows::{RunningApp, System, WindowState};
+
+    fn dict_array(value: &Value) -> CFRetained<CFArray<CFDict>> {
+        let array = macos::cf_from_json(value)
+            .and_then(|value| value.downcast::<CFArray>().ok())
+            .expect("fixture value is an array");
+        unsafe { CFRetained::cast_unchecked(array) }
+    }
+
+    impl System for Fixture {
+        fn display_spaces(&self) -> CFRetained<CFArray<CFDict>> {
+            dict_array(&self.display_spaces)
+        }
+
+        fn spans_displays(&self) -> bool {
+            self.spans_displays
+        }
+
+        fn windows_on_space(&self, space_id: u64) -> Vec<u32> {
+            self.space_windows
+                .get(&space_id)
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        fn window_list(&self) -> Option<CFRetained<CFArray<CFDict>>> {
+            Some(dict_array(&self.window_list))
+        }
+
+        /// All backed by the test process's own app, only what's recorded gets looked at
+        fn running_apps(&self) -> Vec<RunningApp> {
+            self.apps
+                .iter()
+                .map(|app| RunningApp {
+                    handle: NSRunningApplication::currentApplication(),
+                    pid: app.pid,
+                    name: app.name.clone(),
+                    bundle_id: app.bundle_id.clone(),
+                    policy: match app.policy.as_str() {
+                        "regular" => NSApplicationActivationPolicy::Regular,
+                        "accessory" => NSApplicationActivationPolicy::Accessory,
+                        _ => NSApplicationActivationPolicy::Prohibited,
+                    },
+                    hidden: app.hidden,
+                })
+                .collect()
+        }
+
+        fn display_names(&self) -> HashMap<String, String> {
+            self.display_names.clone()
+        }
+
+        fn display_frames(&self) -> Vec<(String, CGRect)> {
+            self.display_frames
+                .iter()
+                .map(|(uuid, &[x, y, width, height])| {
+                    (
+                        uuid.clone(),
+                        CGRect::new(CGPoint::new(x, y), CGSize::new(width, height)),
+                    )
+                })
+                .collect()
+        }
+
+        fn active_space(&self) -> u64 {
+            self.active_space
+        }
+
+        fn cursor_display(&self) -> Option<String> {
+            self.cursor_display.clone()
+        }
+
+        /// Stands the app's element in for each recorded window, they're told apart by id
+        fn resolve_windows(
+            &self,
+            pid: i32,
+            wids: &HashSet<u32>,
+            _budget: Duration,
+        ) -> (HashMap<u32, Retained<AXUIElement>>, bool) {
+            let resolved = wids
+                .iter()
+                .filter(|wid| self.ax.contains_key(wid))
+                .filter_map(|&wid| Some((wid, macos::application_element(pid)?)))
+                .collect();
+            (resolved, false)
+        }
+
+        fn window_state(&self, wid: u32, _element: &AXUIElement) -> WindowState {
+            let ax = &self.ax[&wid];
+            WindowState {
+                minimized: ax.minimized,
+                fullscreen: ax.fullscreen,
+                hidden: false,
+            }
+        }
+
+        fn document(&self, wid: u32, _element: &AXUIElement) -> Option<String> {
+            self.ax[&wid].document.clone()
+        }
+
+        fn ax_trusted(&self) -> bool {
+            !self.ax_denied
+        }
+    }
+}
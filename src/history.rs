@@ -0,0 +1,50 @@
+const MAX_ENTRIES: usize = 100;
+
+/// Browser-style back/forward stack of focused window ids.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<u32>,
+    cursor: usize,
+}
+
+impl History {
+    /// Records a switch from `from` (the window that was in front) to `to`,
+    /// dropping anything ahead of the cursor like a browser does.
+    pub fn push(&mut self, from: Option<u32>, to: u32) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.cursor + 1);
+        }
+        if let Some(from) = from
+            && self.entries.last() != Some(&from)
+        {
+            self.entries.push(from);
+        }
+        if self.entries.last() != Some(&to) {
+            self.entries.push(to);
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+        self.cursor = self.entries.len() - 1;
+    }
+
+    /// Moves back to the closest earlier window that still exists.
+    pub fn back(&mut self, exists: impl Fn(u32) -> bool) -> Option<u32> {
+        let idx = self.entries[..self.cursor]
+            .iter()
+            .rposition(|wid| exists(*wid))?;
+        self.cursor = idx;
+        Some(self.entries[idx])
+    }
+
+    /// Moves forward to the closest later window that still exists.
+    pub fn forward(&mut self, exists: impl Fn(u32) -> bool) -> Option<u32> {
+        let offset = self
+            .entries
+            .get(self.cursor + 1..)?
+            .iter()
+            .position(|wid| exists(*wid))?;
+        self.cursor += 1 + offset;
+        Some(self.entries[self.cursor])
+    }
+}
@@ -0,0 +1,37 @@
+use std::process::{Command, Stdio};
+
+use crate::crash::log;
+use crate::windows::{App, Window};
+
+/// Runs a user hook through `sh -c` without waiting on it, describing the window in
+/// `SWITCHEROO_*` environment variables.
+pub fn run(command: &str, target: Option<(&App, &Window)>) {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null());
+    if let Some((app, window)) = target {
+        cmd.env("SWITCHEROO_APP", &app.name)
+            .env(
+                "SWITCHEROO_BUNDLE_ID",
+                app.bundle_id.as_deref().unwrap_or_default(),
+            )
+            .env("SWITCHEROO_PID", app.pid.to_string())
+            .env("SWITCHEROO_TITLE", &window.title)
+            .env("SWITCHEROO_WINDOW_ID", window.id.to_string());
+    }
+    match cmd.spawn() {
+        // Reaped off the UI thread so slow hooks don't hold anything up or linger as zombies
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                if let Ok(status) = child.wait()
+                    && !status.success()
+                {
+                    log!("[warn] hook {status}");
+                }
+            });
+        }
+        Err(e) => log!("[warn] could not run hook: {e}"),
+    }
+}
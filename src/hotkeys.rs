@@ -0,0 +1,256 @@
+use global_hotkey::{
+    GlobalHotKeyManager,
+    hotkey::{Code, HotKey, Modifiers},
+};
+
+use anyhow::{Result, anyhow};
+use objc2_core_graphics::CGEventFlags;
+
+use crate::config::Config;
+use crate::crash::log;
+use crate::macos;
+
+/// Offered when the picker hotkey can't be registered.
+pub const PICKER_ALTERNATIVES: &[&str] = &[
+    "cmd+alt+Space",
+    "ctrl+alt+Space",
+    "cmd+shift+KeyD",
+    "ctrl+KeyD",
+    "alt+Tab",
+];
+
+/// What opens the picker: a regular hotkey, or modifiers tapped on their own (e.g. "hyper").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Picker {
+    Hotkey(HotKey),
+    Tap(Modifiers),
+}
+
+impl Picker {
+    pub fn label(self) -> String {
+        match self {
+            Self::Hotkey(hotkey) => hotkey.into_string(),
+            Self::Tap(modifiers) => format!("{} (tapped)", modifiers_label(modifiers)),
+        }
+    }
+}
+
+/// The configured picker trigger, Cmd+D by default.
+pub fn picker(config: &Config) -> Picker {
+    config
+        .hotkeys
+        .picker
+        .as_deref()
+        .and_then(parse_picker)
+        .unwrap_or_else(|| Picker::Hotkey(HotKey::new(Some(Modifiers::META), Code::KeyD)))
+}
+
+pub fn parse_picker(s: &str) -> Option<Picker> {
+    parse_modifiers(s)
+        .map(Picker::Tap)
+        .or_else(|| parse(s).map(Picker::Hotkey))
+}
+
+/// Cmd+Option+N, focusing the window bookmarked in slot N (1..=9).
+pub fn bookmark_hotkey(slot: u8) -> Option<HotKey> {
+    let code = match slot {
+        1 => Code::Digit1,
+        2 => Code::Digit2,
+        3 => Code::Digit3,
+        4 => Code::Digit4,
+        5 => Code::Digit5,
+        6 => Code::Digit6,
+        7 => Code::Digit7,
+        8 => Code::Digit8,
+        9 => Code::Digit9,
+        _ => return None,
+    };
+    Some(HotKey::new(Some(Modifiers::META | Modifiers::ALT), code))
+}
+
+/// Parses hotkeys like "cmd+alt+KeyP", "shift+super+1" or "hyper+KeyK", warning on bad input.
+pub fn parse(s: &str) -> Option<HotKey> {
+    match expand_aliases(s).parse::<HotKey>() {
+        Ok(hotkey) => Some(hotkey),
+        Err(e) => {
+            log!("[warn] invalid hotkey {s:?}: {e}");
+            None
+        }
+    }
+}
+
+/// Karabiner-style shorthands: "hyper" is all four modifiers, "meh" all but cmd.
+fn expand_aliases(s: &str) -> String {
+    s.split('+')
+        .map(|token| match token.trim().to_lowercase().as_str() {
+            "hyper" => "shift+control+alt+super",
+            "meh" => "shift+control+alt",
+            _ => token,
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Parses definitions made only of modifiers, like "hyper" or "cmd+alt".
+pub fn parse_modifiers(s: &str) -> Option<Modifiers> {
+    let mut modifiers = Modifiers::empty();
+    for token in expand_aliases(s).split('+') {
+        modifiers |= match token.trim().to_lowercase().as_str() {
+            "shift" => Modifiers::SHIFT,
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "alt" | "option" => Modifiers::ALT,
+            "cmd" | "command" | "super" | "meta" => Modifiers::META,
+            _ => return None,
+        };
+    }
+    Some(modifiers)
+}
+
+fn modifiers_label(modifiers: Modifiers) -> String {
+    if modifiers == Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::META {
+        return String::from("hyper");
+    }
+    let names = [
+        (Modifiers::CONTROL, "ctrl"),
+        (Modifiers::ALT, "alt"),
+        (Modifiers::SHIFT, "shift"),
+        (Modifiers::META, "cmd"),
+    ];
+    names
+        .iter()
+        .filter(|(m, _)| modifiers.contains(*m))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Spots a tap of modifiers alone: all of them held, then released without any key in between.
+#[derive(Debug, Default)]
+pub struct ModifierTap {
+    /// Key-down count when the modifiers went down
+    armed: Option<u32>,
+}
+
+impl ModifierTap {
+    /// Looks at the current modifier state, true once per completed tap.
+    pub fn poll(&mut self, target: Modifiers) -> bool {
+        let held = macos::modifier_flags();
+        let mut modifiers = Modifiers::empty();
+        for (flag, modifier) in [
+            (CGEventFlags::MaskShift, Modifiers::SHIFT),
+            (CGEventFlags::MaskControl, Modifiers::CONTROL),
+            (CGEventFlags::MaskAlternate, Modifiers::ALT),
+            (CGEventFlags::MaskCommand, Modifiers::META),
+        ] {
+            if held.contains(flag) {
+                modifiers |= modifier;
+            }
+        }
+
+        match self.armed {
+            None if modifiers == target => {
+                self.armed = Some(macos::key_down_count());
+                false
+            }
+            Some(start) if modifiers.is_empty() => {
+                self.armed = None;
+                macos::key_down_count() == start
+            }
+            // Another modifier joined in, that's some other combo
+            Some(_) if !target.contains(modifiers) => {
+                self.armed = None;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Every hotkey the config asks for besides the picker one, with what it's for.
+fn extra_hotkeys(config: &Config) -> Vec<(HotKey, String)> {
+    let mut hotkeys = Vec::new();
+
+    let history = [&config.hotkeys.back, &config.hotkeys.forward];
+    for hotkey in history.into_iter().flatten().filter_map(|h| parse(h)) {
+        hotkeys.push((hotkey, String::from("history")));
+    }
+
+    if let Some(hotkey) = config.hotkeys.privacy.as_deref().and_then(parse) {
+        hotkeys.push((hotkey, String::from("privacy mode")));
+    }
+
+    if config.bookmarks {
+        for hotkey in (1..=9).filter_map(bookmark_hotkey) {
+            hotkeys.push((hotkey, String::from("bookmarks")));
+        }
+    }
+
+    for scratchpad in &config.scratchpads {
+        if let Some(hotkey) = parse(&scratchpad.hotkey) {
+            hotkeys.push((hotkey, format!("scratchpad {:?}", scratchpad.app)));
+        }
+    }
+
+    for search in &config.saved_searches {
+        if let Some(hotkey) = search.hotkey.as_deref().and_then(parse) {
+            hotkeys.push((hotkey, format!("saved search {:?}", search.name)));
+        }
+    }
+
+    hotkeys
+}
+
+/// Registers the extra hotkeys from the config. The picker hotkey goes through
+/// [`swap_picker`] instead, since not getting it has to be shown to the user.
+pub fn register(config: &Config) -> GlobalHotKeyManager {
+    let manager = GlobalHotKeyManager::new().expect("Could not create GlobalHotKeyManager");
+    for (hotkey, what) in extra_hotkeys(config) {
+        if let Err(e) = manager.register(hotkey) {
+            log!("[warn] could not register hotkey for {what}: {e}");
+        }
+    }
+    manager
+}
+
+/// Swaps `old`'s extra hotkeys for `new`'s. If any of the new ones can't be registered,
+/// the old ones are put back.
+pub fn reregister(manager: &GlobalHotKeyManager, old: &Config, new: &Config) -> Result<()> {
+    let old: Vec<HotKey> = extra_hotkeys(old).into_iter().map(|(h, _)| h).collect();
+    // Some may have failed to register in the first place, nothing to undo for those
+    for hotkey in &old {
+        let _ = manager.unregister(*hotkey);
+    }
+
+    let mut registered = Vec::new();
+    for (hotkey, what) in extra_hotkeys(new) {
+        if let Err(e) = manager.register(hotkey) {
+            let _ = manager.unregister_all(&registered);
+            for hotkey in &old {
+                let _ = manager.register(*hotkey);
+            }
+            return Err(anyhow!("Could not register hotkey for {what}: {e}"));
+        }
+        registered.push(hotkey);
+    }
+    Ok(())
+}
+
+/// Switches the picker trigger to `new`. A previous hotkey stays registered if `new` fails.
+pub fn swap_picker(
+    manager: &GlobalHotKeyManager,
+    old: Option<Picker>,
+    new: Picker,
+) -> Result<(), global_hotkey::Error> {
+    if old == Some(new) {
+        return Ok(());
+    }
+    if let Picker::Hotkey(hotkey) = new {
+        manager.register(hotkey)?;
+    }
+    if let Some(Picker::Hotkey(old)) = old
+        && let Err(e) = manager.unregister(old)
+    {
+        log!("[warn] could not unregister old picker hotkey: {e}");
+    }
+    Ok(())
+}
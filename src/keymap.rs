@@ -0,0 +1,272 @@
+use iced::keyboard::{Key, Modifiers, key::Named};
+
+use crate::ui::{CHIPS, CopyField, Message};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger {
+    Named(Named),
+    Char(&'static str),
+    /// 1 up to the given digit, passed on to the action
+    Digits(u8),
+    /// Any character, passed on to the action
+    AnyChar,
+}
+
+/// A key chord inside the picker. The help overlay is generated from these.
+pub struct Binding {
+    pub modifiers: Modifiers,
+    pub trigger: Trigger,
+    pub description: &'static str,
+    /// Only fires when the search field didn't handle the key itself
+    uncaptured_only: bool,
+    action: fn(&str) -> Option<Message>,
+}
+
+const CMD: Modifiers = Modifiers::COMMAND;
+const CMD_SHIFT: Modifiers = Modifiers::COMMAND.union(Modifiers::SHIFT);
+const NONE: Modifiers = Modifiers::empty();
+
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::Enter),
+        description: "Focus the selected window or run the selected command",
+        uncaptured_only: false,
+        action: |_| Some(Message::Confirm),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Named(Named::Enter),
+        description: "Bring the selected window here (or whatever alt_action is set to)",
+        uncaptured_only: false,
+        action: |_| Some(Message::ConfirmAlt),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::Escape),
+        description: "Close the picker",
+        uncaptured_only: false,
+        action: |_| Some(Message::HidePicker),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::ArrowDown),
+        description: "Select the next row",
+        uncaptured_only: true,
+        action: |_| Some(Message::SelectNext),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::ArrowUp),
+        description: "Select the previous row",
+        uncaptured_only: true,
+        action: |_| Some(Message::SelectPrev),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::PageDown),
+        description: "Jump to the next page of results",
+        uncaptured_only: false,
+        action: |_| Some(Message::PageDown),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::PageUp),
+        description: "Jump to the previous page of results",
+        uncaptured_only: false,
+        action: |_| Some(Message::PageUp),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("/"),
+        description: "Toggle this help",
+        uncaptured_only: false,
+        action: |_| Some(Message::ToggleHelp),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::Space),
+        description: "Preview the selected window while the search is empty, close the preview",
+        uncaptured_only: false,
+        action: |_| Some(Message::SpacePressed),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("y"),
+        description: "Preview the selected window",
+        uncaptured_only: false,
+        action: |_| Some(Message::ToggleQuickLook),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("e"),
+        description: "Label the selected window, Enter saves it",
+        uncaptured_only: false,
+        action: |_| Some(Message::EditLabel),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("p"),
+        description: "Keep the picker open after focusing",
+        uncaptured_only: false,
+        action: |_| Some(Message::ToggleKeepOpen),
+    },
+    Binding {
+        modifiers: NONE,
+        trigger: Trigger::Named(Named::F5),
+        description: "Reload every window from scratch",
+        uncaptured_only: false,
+        action: |_| Some(Message::Reload),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("r"),
+        description: "Reload every window from scratch",
+        uncaptured_only: false,
+        action: |_| Some(Message::Reload),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("r"),
+        description: "Reveal the window's document (or its app) in Finder",
+        uncaptured_only: false,
+        action: |_| Some(Message::RevealInFinder),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("c"),
+        description: "Copy the window title",
+        uncaptured_only: false,
+        action: |_| Some(Message::CopyMetadata(CopyField::Title)),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("a"),
+        description: "Copy the app name",
+        uncaptured_only: false,
+        action: |_| Some(Message::CopyMetadata(CopyField::AppName)),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("b"),
+        description: "Copy the bundle id",
+        uncaptured_only: false,
+        action: |_| Some(Message::CopyMetadata(CopyField::BundleId)),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("i"),
+        description: "Copy the window id",
+        uncaptured_only: false,
+        action: |_| Some(Message::CopyMetadata(CopyField::WindowId)),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("l"),
+        description: "Copy a switcheroo:// link to the window",
+        uncaptured_only: false,
+        action: |_| Some(Message::CopyMetadata(CopyField::DeepLink)),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("d"),
+        description: "Toggle showing each row's score breakdown",
+        uncaptured_only: false,
+        action: |_| Some(Message::ToggleDebugScores),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Char("h"),
+        description: "Toggle privacy mode, hiding window titles",
+        uncaptured_only: false,
+        action: |_| Some(Message::TogglePrivacy),
+    },
+    Binding {
+        modifiers: CMD_SHIFT,
+        trigger: Trigger::Digits(9),
+        description: "Bookmark the window, focus it anywhere with Cmd+Option+N",
+        uncaptured_only: false,
+        action: |key| key.parse().ok().map(Message::SetBookmark),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Digits(CHIPS),
+        description: "Toggle the nth filter chip (Current Space, Current Display, Minimized, Hidden)",
+        uncaptured_only: false,
+        action: |key| key.parse().ok().map(Message::ToggleChip),
+    },
+    Binding {
+        modifiers: CMD,
+        trigger: Trigger::Char("0"),
+        description: "Turn all filter chips off",
+        uncaptured_only: false,
+        action: |_| Some(Message::ToggleChip(0)),
+    },
+    Binding {
+        modifiers: Modifiers::CTRL,
+        trigger: Trigger::AnyChar,
+        description: "Apply the saved search bound to the key",
+        uncaptured_only: false,
+        action: |key| Some(Message::SavedSearchKey(key.to_string())),
+    },
+];
+
+impl Binding {
+    fn message(&self, key: &Key, modifiers: Modifiers) -> Option<Message> {
+        if modifiers != self.modifiers {
+            return None;
+        }
+        match (self.trigger, key) {
+            (Trigger::Named(named), Key::Named(pressed)) if named == *pressed => (self.action)(""),
+            (Trigger::Char(c), Key::Character(pressed)) if pressed.as_str() == c => {
+                (self.action)(pressed)
+            }
+            (Trigger::Digits(max), Key::Character(pressed))
+                if pressed.parse::<u8>().is_ok_and(|n| (1..=max).contains(&n)) =>
+            {
+                (self.action)(pressed)
+            }
+            (Trigger::AnyChar, Key::Character(pressed)) => (self.action)(pressed),
+            _ => None,
+        }
+    }
+
+    /// Human readable chord, e.g. "Cmd+Shift+R".
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if self.modifiers.command() {
+            parts.push(String::from("Cmd"));
+        }
+        if self.modifiers.control() {
+            parts.push(String::from("Ctrl"));
+        }
+        if self.modifiers.alt() {
+            parts.push(String::from("Option"));
+        }
+        if self.modifiers.shift() {
+            parts.push(String::from("Shift"));
+        }
+        parts.push(match self.trigger {
+            Trigger::Named(Named::Enter) => String::from("Enter"),
+            Trigger::Named(Named::Escape) => String::from("Esc"),
+            Trigger::Named(Named::ArrowDown) => String::from("↓"),
+            Trigger::Named(Named::ArrowUp) => String::from("↑"),
+            Trigger::Named(Named::PageDown) => String::from("PgDn"),
+            Trigger::Named(Named::PageUp) => String::from("PgUp"),
+            Trigger::Named(named) => format!("{named:?}"),
+            Trigger::Char(c) => c.to_uppercase(),
+            Trigger::Digits(max) => format!("1..{max}"),
+            Trigger::AnyChar => String::from("<key>"),
+        });
+        parts.join("+")
+    }
+}
+
+/// Maps a key press in the picker to a message. `captured` is whether the search field
+/// already handled the key.
+pub fn lookup(key: &Key, modifiers: Modifiers, captured: bool) -> Option<Message> {
+    BINDINGS
+        .iter()
+        .filter(|binding| !(captured && binding.uncaptured_only))
+        .find_map(|binding| binding.message(key, modifiers))
+}
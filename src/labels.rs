@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::stats::window_key;
+use crate::windows::{App, Window};
+
+const LABELS_FILE: &str = "labels.json";
+
+/// Names given to windows with Cmd+E, shown next to their title and matched like it.
+/// Keyed by app and title, so a label sticks around as long as the title doesn't change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Labels {
+    windows: BTreeMap<String, String>,
+}
+
+impl Labels {
+    pub fn load() -> Self {
+        config::load_data(LABELS_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(LABELS_FILE, self)
+    }
+
+    pub fn get(&self, app: &App, window: &Window) -> Option<&str> {
+        self.windows
+            .get(&window_key(app, window))
+            .map(String::as_str)
+    }
+
+    /// An empty label removes the window's label.
+    pub fn set(&mut self, app: &App, window: &Window, label: &str) {
+        let key = window_key(app, window);
+        if label.is_empty() {
+            self.windows.remove(&key);
+        } else {
+            self.windows.insert(key, label.to_string());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::ffi::c_void;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use std::{
     collections::{HashMap, HashSet},
@@ -6,23 +7,42 @@ use std::{
 };
 
 use anyhow::{Result, anyhow};
+use block2::RcBlock;
 
 use objc2::MainThreadMarker;
 use objc2::rc::Retained;
-use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy, NSImage};
-use objc2_application_services::{AXError, AXUIElement};
+use objc2_app_kit::{
+    NSApplication, NSApplicationActivationPolicy, NSImage, NSPasteboard, NSPasteboardTypeString,
+    NSRunningApplication, NSScreen, NSWindowCollectionBehavior, NSWorkspace,
+    NSWorkspaceApplicationKey, NSWorkspaceDidActivateApplicationNotification,
+    NSWorkspaceDidTerminateApplicationNotification,
+};
+#[allow(deprecated)]
+use objc2_application_services::{
+    AXError, AXIsProcessTrusted, AXUIElement, AXValue, AXValueType, GetProcessForPID,
+};
 use objc2_core_foundation::{
-    CFArray, CFData, CFDictionary, CFNumber, CFRetained, CFString, CFType, CGRect, CGSize,
-    ConcreteType,
+    CFArray, CFBoolean, CFData, CFDictionary, CFNumber, CFPreferencesAppValueIsForced,
+    CFPreferencesCopyAppValue, CFRetained, CFString, CFType, CGPoint, CGRect, CGSize, ConcreteType,
+    Type,
 };
+#[allow(deprecated)]
+use objc2_core_graphics::CGWindowListCreateImage;
 use objc2_core_graphics::{
-    CGDataProvider, CGDisplayBounds, CGError, CGEvent, CGEventField, CGEventTapLocation,
-    CGEventType, CGGetDisplaysWithPoint, CGImage, CGWindowID, CGWindowListCopyWindowInfo,
-    CGWindowListOption as Options, kCGNullWindowID as NullID, kCGWindowLayer, kCGWindowName,
+    CGDataProvider, CGDisplayBounds, CGError, CGEvent, CGEventField, CGEventFlags, CGEventSource,
+    CGEventSourceStateID, CGEventTapLocation, CGEventType, CGGetActiveDisplayList,
+    CGGetDisplaysWithPoint, CGImage, CGPreflightScreenCaptureAccess, CGWarpMouseCursorPosition,
+    CGWindowID, CGWindowImageOption, CGWindowListCopyWindowInfo, CGWindowListOption as Options,
+    kCGNullWindowID as NullID, kCGWindowBounds, kCGWindowIsOnscreen, kCGWindowLayer, kCGWindowName,
     kCGWindowNumber, kCGWindowOwnerPID,
 };
+use objc2_foundation::{NSArray, NSNotification, NSNotificationName, NSNumber, NSString, NSURL};
+use serde_json::Value;
+
+use crate::crash::log;
 
 // Undocumented internal macos framework
+#[cfg(feature = "private-apis")]
 #[link(name = "Skylight", kind = "framework")]
 #[allow(dead_code)]
 unsafe extern "C" {
@@ -48,6 +68,7 @@ unsafe extern "C" {
     ) -> i32;
     fn SLSShowSpaces(cid: u32, space_ids: *const c_void) -> i32;
     pub fn SLSGetWindowBounds(cid: u32, wid: CGWindowID, bounds: *mut CGRect) -> CGError;
+    fn SLSMoveWindowsToManagedSpace(cid: u32, window_ids: *const c_void, space_id: u64);
 }
 
 // Constants and post_dock_swipe ported from github.com/jurplel/InstantSpaceSwitcher
@@ -101,8 +122,10 @@ pub fn switch_to_space_instant(target_space_id: u64, display_uuid: &str) {
     let mut current_space_id: Option<u64> = None;
     let mut ordered_space_ids: Vec<u64> = Vec::new();
 
+    let spans = displays_span_spaces();
     for display in copy_managed_display_spaces() {
-        if display_uuid_of(&display).as_deref() != Some(display_uuid) {
+        // With shared spaces there is a single managed entry regardless of the window's display
+        if !spans && display_uuid_of(&display).as_deref() != Some(display_uuid) {
             continue;
         }
 
@@ -167,15 +190,418 @@ pub fn activate_application() {
     app.activate();
 }
 
+/// Calls `handler` with the pid of every app that comes to the front, for as long as
+/// switcheroo runs.
+pub fn watch_activations(handler: impl Fn(i32) + 'static) {
+    observe_workspace(
+        unsafe { NSWorkspaceDidActivateApplicationNotification },
+        handler,
+    );
+}
+
+/// Calls `handler` with the pid of every app that quits, for as long as switcheroo runs.
+pub fn watch_terminations(handler: impl Fn(i32) + 'static) {
+    observe_workspace(
+        unsafe { NSWorkspaceDidTerminateApplicationNotification },
+        handler,
+    );
+}
+
+fn observe_workspace(name: &NSNotificationName, handler: impl Fn(i32) + 'static) {
+    let block = RcBlock::new(move |notification: NonNull<NSNotification>| {
+        let notification = unsafe { notification.as_ref() };
+        let app = notification
+            .userInfo()
+            .and_then(|info| info.objectForKey(unsafe { NSWorkspaceApplicationKey }))
+            .and_then(|app| app.downcast::<NSRunningApplication>().ok());
+        if let Some(app) = app {
+            handler(app.processIdentifier());
+        }
+    });
+    let center = NSWorkspace::sharedWorkspace().notificationCenter();
+    let observer = unsafe {
+        center.addObserverForName_object_queue_usingBlock(Some(name), None, None, &block)
+    };
+    // Never removed
+    std::mem::forget(observer);
+}
+
+// kInternetEventClass and kAEGetURL are both 'GURL'
+const GET_URL_EVENT: u32 = u32::from_be_bytes(*b"GURL");
+const KEY_DIRECT_OBJECT: u32 = u32::from_be_bytes(*b"----");
+const TYPE_UTF8_TEXT: u32 = u32::from_be_bytes(*b"utf8");
+
+type AEEventHandler =
+    unsafe extern "C" fn(event: *const c_void, reply: *mut c_void, refcon: *mut c_void) -> i16;
+
+#[link(name = "CoreServices", kind = "framework")]
+unsafe extern "C" {
+    fn AEInstallEventHandler(
+        class: u32,
+        id: u32,
+        handler: AEEventHandler,
+        refcon: *mut c_void,
+        is_sys_handler: u8,
+    ) -> i16;
+    fn AEGetParamPtr(
+        event: *const c_void,
+        keyword: u32,
+        desired_type: u32,
+        actual_type: *mut u32,
+        data: *mut c_void,
+        max_size: isize,
+        actual_size: *mut isize,
+    ) -> i16;
+}
+
+type UrlHandler = Box<dyn Fn(String) + Send>;
+static URL_HANDLER: Mutex<Option<UrlHandler>> = Mutex::new(None);
+/// URLs that came in before [`set_url_handler`], e.g. the one switcheroo was launched with
+static PENDING_URLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Where `switcheroo://` URLs opened by other apps go, for as long as switcheroo runs. Any
+/// that came in since [`install_url_handler`] are handed over right away.
+pub fn set_url_handler(handler: impl Fn(String) + Send + 'static) {
+    let Ok(mut current) = URL_HANDLER.lock() else {
+        return;
+    };
+    if let Ok(mut pending) = PENDING_URLS.lock() {
+        pending.drain(..).for_each(&handler);
+    }
+    *current = Some(Box::new(handler));
+}
+
+/// Starts taking `switcheroo://` URLs, before the event loop so the one that launched
+/// switcheroo isn't lost. They're held until [`set_url_handler`].
+pub fn install_url_handler() {
+    // Takes over from NSApplication's own handler, which only tells the app delegate
+    let err = unsafe {
+        AEInstallEventHandler(
+            GET_URL_EVENT,
+            GET_URL_EVENT,
+            url_opened,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if err != 0 {
+        log!("[warn] could not install the URL handler: error {err}");
+    }
+}
+
+unsafe extern "C" fn url_opened(
+    event: *const c_void,
+    _reply: *mut c_void,
+    _refcon: *mut c_void,
+) -> i16 {
+    let mut actual_type = 0;
+    let mut size = 0;
+    // Asking for 0 bytes still reports the size
+    let err = unsafe {
+        AEGetParamPtr(
+            event,
+            KEY_DIRECT_OBJECT,
+            TYPE_UTF8_TEXT,
+            &mut actual_type,
+            std::ptr::null_mut(),
+            0,
+            &mut size,
+        )
+    };
+    if err != 0 || size <= 0 {
+        return err;
+    }
+    let mut url = vec![0u8; size as usize];
+    let err = unsafe {
+        AEGetParamPtr(
+            event,
+            KEY_DIRECT_OBJECT,
+            TYPE_UTF8_TEXT,
+            &mut actual_type,
+            url.as_mut_ptr().cast(),
+            size,
+            &mut size,
+        )
+    };
+    if err != 0 {
+        return err;
+    }
+    let url = String::from_utf8_lossy(&url).into_owned();
+    if let Ok(handler) = URL_HANDLER.lock() {
+        match handler.as_ref() {
+            Some(handler) => handler(url),
+            None => {
+                if let Ok(mut pending) = PENDING_URLS.lock() {
+                    pending.push(url);
+                }
+            }
+        }
+    }
+    0
+}
+
+/// The app's process serial number, which SkyLight wants instead of its pid.
+pub fn psn_for_pid(pid: i32) -> Option<ProcessSerialNumber> {
+    let mut psn = ProcessSerialNumber::default();
+    #[allow(deprecated)]
+    let res = unsafe { GetProcessForPID(pid, (&mut psn as *mut _) as _) };
+    (res == 0).then_some(psn)
+}
+
+/// The app's own AX element, what its windows hang off.
+pub fn application_element(pid: i32) -> Option<Retained<AXUIElement>> {
+    unsafe { Retained::from_raw(AXUIElementCreateApplication(pid) as *mut AXUIElement) }
+}
+
+/// Id of the app's focused window, if it has one.
+pub fn focused_window(pid: i32) -> Option<u32> {
+    let app = application_element(pid)?;
+    let window = get_attribute(&app, "AXFocusedWindow")?;
+    let mut wid = 0;
+    let res = unsafe { _AXUIElementGetWindow(CFRetained::as_ptr(&window).as_ptr() as _, &mut wid) };
+    (res == AXError::Success).then_some(wid)
+}
+
+/// Title of the app's focused window, through public AX alone.
+pub fn focused_window_title(pid: i32) -> Option<String> {
+    let app = application_element(pid)?;
+    let window = get_attribute(&app, "AXFocusedWindow")?;
+    let window: Retained<AXUIElement> =
+        unsafe { Retained::retain(CFRetained::as_ptr(&window).as_ptr().cast()) }?;
+    let title = get_attribute(&window, "AXTitle")?;
+    title.downcast::<CFString>().ok().map(|t| t.to_string())
+}
+
 pub fn hide_application() {
     let mtm = unsafe { MainThreadMarker::new_unchecked() };
     let app = NSApplication::sharedApplication(mtm);
     app.hide(None);
 }
 
+pub fn reveal_in_finder(url: &NSURL) {
+    let ws = NSWorkspace::sharedWorkspace();
+    ws.activateFileViewerSelectingURLs(&NSArray::from_slice(&[url]));
+}
+
+/// E.g. "15.1", from sw_vers.
+pub fn os_version() -> Option<String> {
+    let output = std::process::Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn accessibility_granted() -> bool {
+    AXIsProcessTrusted()
+}
+
+pub fn screen_recording_granted() -> bool {
+    CGPreflightScreenCaptureAccess()
+}
+
+/// Opens System Settings > Privacy & Security at `pane`, e.g. "Privacy_Accessibility".
+pub fn open_privacy_settings(pane: &str) {
+    let url = format!("x-apple.systempreferences:com.apple.preference.security?{pane}");
+    if let Some(url) = NSURL::URLWithString(&NSString::from_str(&url)) {
+        NSWorkspace::sharedWorkspace().openURL(&url);
+    }
+}
+
+pub fn reduce_transparency() -> bool {
+    NSWorkspace::sharedWorkspace().accessibilityDisplayShouldReduceTransparency()
+}
+
+pub fn increase_contrast() -> bool {
+    NSWorkspace::sharedWorkspace().accessibilityDisplayShouldIncreaseContrast()
+}
+
+pub fn reduce_motion() -> bool {
+    NSWorkspace::sharedWorkspace().accessibilityDisplayShouldReduceMotion()
+}
+
+/// Fades switcheroo's windows, i.e. the picker, iced has no say over window opacity.
+pub fn set_window_alpha(alpha: f64) {
+    let mtm = unsafe { MainThreadMarker::new_unchecked() };
+    for window in NSApplication::sharedApplication(mtm).windows().iter() {
+        window.setAlphaValue(alpha);
+    }
+}
+
+/// Lets switcheroo's windows, i.e. the picker, show up on whatever space is active and over
+/// fullscreen apps, rather than macOS switching to the space they were first opened on.
+/// Does nothing off the main thread.
+pub fn show_on_all_spaces() {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    for window in NSApplication::sharedApplication(mtm).windows().iter() {
+        window.setCollectionBehavior(
+            window.collectionBehavior()
+                | NSWindowCollectionBehavior::CanJoinAllSpaces
+                | NSWindowCollectionBehavior::FullScreenAuxiliary,
+        );
+    }
+}
+
+pub fn set_clipboard(text: &str) {
+    let pb = NSPasteboard::generalPasteboard();
+    pb.clearContents();
+    pb.setString_forType(&NSString::from_str(text), unsafe { NSPasteboardTypeString });
+}
+
+pub fn clipboard_text() -> Option<String> {
+    let pb = NSPasteboard::generalPasteboard();
+    pb.stringForType(unsafe { NSPasteboardTypeString })
+        .map(|text| text.to_string())
+}
+
+/// Pasteboard types the clipboard's current contents come with, e.g. the
+/// `org.nspasteboard.ConcealedType` password managers mark theirs with.
+pub fn clipboard_types() -> Vec<String> {
+    NSPasteboard::generalPasteboard()
+        .types()
+        .map(|types| types.iter().map(|t| t.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Goes up whenever anything is copied.
+pub fn clipboard_change_count() -> isize {
+    NSPasteboard::generalPasteboard().changeCount()
+}
+
+/// Presses Cmd+V in whatever app is in front.
+pub fn paste() {
+    const KEY_V: u16 = 9;
+    for down in [true, false] {
+        if let Some(ev) = CGEvent::new_keyboard_event(None, KEY_V, down) {
+            CGEvent::set_flags(Some(&ev), CGEventFlags::MaskCommand);
+            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&ev));
+        }
+    }
+}
+
 /// Returns (x, y, width, height) of the display containing the cursor,
 /// in the global display coordinate space (top-left of primary display is origin, Y downward).
 pub fn active_display_frame_at_cursor() -> Option<(f32, f32, f32, f32)> {
+    let b = CGDisplayBounds(display_at_cursor()?);
+    Some((b.origin.x as f32, b.origin.y as f32, b.size.width as f32, b.size.height as f32))
+}
+
+#[link(name = "ColorSync", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> *const c_void;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFUUIDCreateString(alloc: *const c_void, uuid: *const c_void) -> *mut CFString;
+    fn CFRelease(cf: *const c_void);
+    fn CFRunLoopGetMain() -> *mut c_void;
+    fn CFRunLoopAddSource(run_loop: *mut c_void, source: *mut c_void, mode: *const CFString);
+    fn CFRunLoopRemoveSource(run_loop: *mut c_void, source: *mut c_void, mode: *const CFString);
+    static kCFRunLoopCommonModes: *const CFString;
+}
+
+/// Display UUIDs (as in SLSCopyManagedDisplaySpaces) to their user-facing names,
+/// e.g. "Built-in Retina Display". Empty off the main thread.
+pub fn display_names() -> HashMap<String, String> {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return HashMap::new();
+    };
+    let key = NSString::from_str("NSScreenNumber");
+    NSScreen::screens(mtm)
+        .iter()
+        .filter_map(|screen| {
+            let number = screen
+                .deviceDescription()
+                .objectForKey(&key)?
+                .downcast::<NSNumber>()
+                .ok()?;
+            let uuid = display_uuid(number.unsignedIntValue())?;
+            Some((uuid, screen.localizedName().to_string()))
+        })
+        .collect()
+}
+
+/// Frame of the display under the cursor without the menu bar and Dock, in global
+/// top-left-origin coordinates. None off the main thread.
+pub fn visible_frame_at_cursor() -> Option<CGRect> {
+    let screens = NSScreen::screens(MainThreadMarker::new()?);
+    let primary_height = screens.firstObject()?.frame().size.height;
+    let frame = screen_at_cursor(&screens)?.visibleFrame();
+    Some(flip_frame(frame, primary_height))
+}
+
+/// Like [`visible_frame_at_cursor`], for the display holding the center of `window`, a global
+/// top-left-origin frame.
+pub fn visible_frame_around(window: CGRect) -> Option<CGRect> {
+    let screens = NSScreen::screens(MainThreadMarker::new()?);
+    let primary_height = screens.firstObject()?.frame().size.height;
+    let center = flip_frame(window, primary_height);
+    let x = center.origin.x + center.size.width / 2.;
+    let y = center.origin.y + center.size.height / 2.;
+    let screen = screens.iter().find(|screen| {
+        let f = screen.frame();
+        (f.origin.x..f.origin.x + f.size.width).contains(&x)
+            && (f.origin.y..f.origin.y + f.size.height).contains(&y)
+    })?;
+    Some(flip_frame(screen.visibleFrame(), primary_height))
+}
+
+/// Between Cocoa's frames, counting y up from the bottom of the primary display, and the
+/// top-left-origin ones everything else uses. Works both ways.
+fn flip_frame(frame: CGRect, primary_height: f64) -> CGRect {
+    CGRect::new(
+        CGPoint::new(
+            frame.origin.x,
+            primary_height - frame.origin.y - frame.size.height,
+        ),
+        frame.size,
+    )
+}
+
+/// Points to pixels factor of the display under the cursor, where the picker opens. Falls back
+/// to the main display's.
+pub fn backing_scale_at_cursor() -> f64 {
+    MainThreadMarker::new()
+        .map(NSScreen::screens)
+        .and_then(|screens| screen_at_cursor(&screens))
+        .map_or_else(backing_scale, |screen| screen.backingScaleFactor())
+}
+
+fn screen_at_cursor(screens: &NSArray<NSScreen>) -> Option<Retained<NSScreen>> {
+    let display = display_at_cursor()?;
+    let key = NSString::from_str("NSScreenNumber");
+    screens.iter().find(|screen| {
+        screen
+            .deviceDescription()
+            .objectForKey(&key)
+            .and_then(|number| number.downcast::<NSNumber>().ok())
+            .is_some_and(|number| number.unsignedIntValue() == display)
+    })
+}
+
+fn display_uuid(display: u32) -> Option<String> {
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display);
+        if uuid.is_null() {
+            return None;
+        }
+        let string = CFUUIDCreateString(std::ptr::null(), uuid);
+        CFRelease(uuid);
+        Some(CFRetained::from_raw(NonNull::new(string)?).to_string())
+    }
+}
+
+/// Cursor position in global top-left-origin coordinates.
+pub fn cursor_location() -> Option<(f64, f64)> {
+    let ev = CGEvent::new(None)?;
+    let loc = CGEvent::location(Some(&ev));
+    Some((loc.x, loc.y))
+}
+
+fn display_at_cursor() -> Option<u32> {
     let ev = CGEvent::new(None)?;
     let loc = CGEvent::location(Some(&ev));
 
@@ -183,12 +609,100 @@ pub fn active_display_frame_at_cursor() -> Option<(f32, f32, f32, f32)> {
     let mut count = 0u32;
     unsafe { CGGetDisplaysWithPoint(loc, 1, &mut display, &mut count) };
 
-    if count == 0 {
-        return None;
+    (count > 0).then_some(display)
+}
+
+/// Modifier keys held right now.
+pub fn modifier_flags() -> CGEventFlags {
+    CGEventSource::flags_state(CGEventSourceStateID::CombinedSessionState)
+}
+
+/// Key presses so far this session, to tell whether any happened in between two calls.
+pub fn key_down_count() -> u32 {
+    CGEventSource::counter_for_event_type(
+        CGEventSourceStateID::CombinedSessionState,
+        CGEventType::KeyDown,
+    )
+}
+
+pub fn display_uuid_at_cursor() -> Option<String> {
+    display_uuid(display_at_cursor()?)
+}
+
+/// Moves the cursor to the center of the display, so space swipes land there.
+pub fn warp_to_display(uuid: &str) {
+    let mut displays = [0u32; 16];
+    let mut count = 0u32;
+    unsafe { CGGetActiveDisplayList(displays.len() as u32, displays.as_mut_ptr(), &mut count) };
+    if let Some(&display) = displays[..count as usize]
+        .iter()
+        .find(|&&d| display_uuid(d).as_deref() == Some(uuid))
+    {
+        let b = CGDisplayBounds(display);
+        CGWarpMouseCursorPosition(CGPoint::new(
+            b.origin.x + b.size.width / 2.0,
+            b.origin.y + b.size.height / 2.0,
+        ));
     }
+}
 
-    let b = CGDisplayBounds(display);
-    Some((b.origin.x as f32, b.origin.y as f32, b.size.width as f32, b.size.height as f32))
+/// UUID and global frame of every active display, main display first.
+pub fn display_frames() -> Vec<(String, CGRect)> {
+    let mut displays = [0u32; 16];
+    let mut count = 0u32;
+    unsafe { CGGetActiveDisplayList(displays.len() as u32, displays.as_mut_ptr(), &mut count) };
+    displays[..count as usize]
+        .iter()
+        .filter_map(|&display| Some((display_uuid(display)?, CGDisplayBounds(display))))
+        .collect()
+}
+
+/// Index of the display in `frames` that holds the center of `frame`.
+pub fn display_of(frames: &[(String, CGRect)], frame: CGRect) -> Option<usize> {
+    let x = frame.origin.x + frame.size.width / 2.;
+    let y = frame.origin.y + frame.size.height / 2.;
+    frames.iter().position(|(_, d)| {
+        (d.origin.x..d.origin.x + d.size.width).contains(&x)
+            && (d.origin.y..d.origin.y + d.size.height).contains(&y)
+    })
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGSessionCopyCurrentDictionary() -> *mut c_void;
+    fn CGGetOnlineDisplayList(max: u32, displays: *mut u32, count: *mut u32) -> i32;
+    fn CGDisplayIsInMirrorSet(display: u32) -> u32;
+}
+
+/// Whether the screen is being shared (Screen Sharing, Remote Management) or mirrored, as when
+/// presenting on a projector. Meeting apps capturing the screen can't be told apart from
+/// outside, those go unnoticed.
+pub fn screen_shared() -> bool {
+    let session = NonNull::new(unsafe { CGSessionCopyCurrentDictionary() })
+        .map(|ptr| unsafe { CFRetained::<CFType>::from_raw(ptr.cast()) });
+    let shared = session.is_some_and(|session| {
+        cf_to_json(&session)
+            .get("CGSSessionScreenIsShared")
+            .and_then(Value::as_bool)
+            == Some(true)
+    });
+
+    let mut displays = [0u32; 16];
+    let mut count = 0u32;
+    unsafe { CGGetOnlineDisplayList(displays.len() as u32, displays.as_mut_ptr(), &mut count) };
+    let mirrored = displays[..count as usize]
+        .iter()
+        .any(|&display| unsafe { CGDisplayIsInMirrorSet(display) } != 0);
+
+    shared || mirrored
+}
+
+/// How long any AX call waits for an app to answer before giving up.
+pub fn set_ax_timeout(timeout: Duration) {
+    unsafe {
+        let system_wide = AXUIElement::new_system_wide();
+        AXUIElement::set_messaging_timeout(&system_wide, timeout.as_secs_f32());
+    }
 }
 
 pub fn set_accessory_mode() {
@@ -204,6 +718,7 @@ pub struct ProcessSerialNumber {
     low_long_of_psn: u32,
 }
 
+#[cfg(feature = "private-apis")]
 #[link(name = "ApplicationServices", kind = "framework")]
 unsafe extern "C" {
     fn _AXUIElementCreateWithRemoteToken(data: *const c_void) -> *mut c_void;
@@ -214,11 +729,245 @@ unsafe extern "C" {
         options: u32,
     ) -> CGError;
     fn SLPSPostEventRecordTo(psn: *const ProcessSerialNumber, bytes: *mut u8) -> CGError;
+    fn CoreDockSendNotification(notification: *const CFString, flags: i32) -> i32;
+}
+
+#[cfg(not(feature = "private-apis"))]
+pub use no_private_apis::*;
+
+/// Stand-ins for the private symbols above when building without them (for the hardened
+/// runtime or App Sandbox), failing the way the real ones do when they can't help.
+#[cfg(not(feature = "private-apis"))]
+#[allow(dead_code, non_snake_case, clippy::missing_safety_doc)]
+mod no_private_apis {
+    use std::ffi::c_void;
+
+    use objc2_application_services::AXError;
+    use objc2_core_foundation::{CFArray, CFRetained, CFString, CFType, CGRect};
+    use objc2_core_graphics::{CGError, CGWindowID};
+
+    use super::ProcessSerialNumber;
+
+    /// Callers take ownership of the copied arrays, so they get an empty one rather than null
+    fn empty_array() -> *mut c_void {
+        let array = CFArray::<CFType>::from_retained_objects(&[]);
+        CFRetained::into_raw(array).as_ptr().cast()
+    }
+
+    pub unsafe fn SLSMainConnectionID() -> u32 {
+        0
+    }
+    pub unsafe fn SLSGetActiveSpace(_cid: u32) -> u64 {
+        0
+    }
+    pub unsafe fn SLSWindowIsOnSpace(_cid: u32, _window_id: CGWindowID, _space_id: u64) -> bool {
+        false
+    }
+    pub unsafe fn SLSCopyManagedDisplaySpaces(_cid: u32) -> *mut c_void {
+        empty_array()
+    }
+    pub unsafe fn SLSCopyWindowsWithOptionsAndTags(
+        _cid: u32,
+        _owner: u32,
+        _spaces: *const c_void,
+        _options: u32,
+        _set_tags: *mut u64,
+        _clear_tags: *mut u64,
+    ) -> *const c_void {
+        empty_array()
+    }
+    pub unsafe fn SLSOrderWindow(_cid: u32, _wid: u32, _mode: i32, _relative_to: u32) -> i32 {
+        CGError::Failure.0
+    }
+    pub unsafe fn SLSManagedDisplaySetCurrentSpace(
+        _cid: u32,
+        _display_uuid: *const c_void,
+        _space_id: u64,
+    ) -> i32 {
+        CGError::Failure.0
+    }
+    pub unsafe fn SLSShowSpaces(_cid: u32, _space_ids: *const c_void) -> i32 {
+        CGError::Failure.0
+    }
+    pub unsafe fn SLSGetWindowBounds(_cid: u32, _wid: CGWindowID, _bounds: *mut CGRect) -> CGError {
+        CGError::Failure
+    }
+    pub unsafe fn SLSMoveWindowsToManagedSpace(
+        _cid: u32,
+        _window_ids: *const c_void,
+        _space_id: u64,
+    ) {
+    }
+    pub unsafe fn _AXUIElementCreateWithRemoteToken(_data: *const c_void) -> *mut c_void {
+        std::ptr::null_mut()
+    }
+    pub unsafe fn _AXUIElementGetWindow(
+        _element: *const c_void,
+        _cg_w_id: *mut CGWindowID,
+    ) -> AXError {
+        AXError::Failure
+    }
+    pub unsafe fn _SLPSSetFrontProcessWithOptions(
+        _psn: *const ProcessSerialNumber,
+        _wid: CGWindowID,
+        _options: u32,
+    ) -> CGError {
+        CGError::Failure
+    }
+    pub unsafe fn SLPSPostEventRecordTo(
+        _psn: *const ProcessSerialNumber,
+        _bytes: *mut u8,
+    ) -> CGError {
+        CGError::Failure
+    }
+    pub unsafe fn CoreDockSendNotification(_notification: *const CFString, _flags: i32) -> i32 {
+        CGError::Failure.0
+    }
+}
+
+/// Whether SkyLight and the other private symbols may be used: built with them and not
+/// sandboxed, where they'd be denied anyway.
+pub fn private_apis_available() -> bool {
+    cfg!(feature = "private-apis") && !sandboxed()
 }
 
-type CFDict = CFDictionary<CFString, CFType>;
+/// Whether we run inside the App Sandbox, which sets this for every sandboxed process.
+pub fn sandboxed() -> bool {
+    std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some()
+}
+
+type AXObserverCallback = unsafe extern "C" fn(
+    observer: *mut c_void,
+    element: *mut c_void,
+    notification: *const CFString,
+    refcon: *mut c_void,
+);
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXObserverCreate(
+        pid: i32,
+        callback: AXObserverCallback,
+        observer: *mut *mut c_void,
+    ) -> AXError;
+    fn AXObserverAddNotification(
+        observer: *mut c_void,
+        element: *const c_void,
+        notification: *const CFString,
+        refcon: *mut c_void,
+    ) -> AXError;
+    fn AXObserverGetRunLoopSource(observer: *mut c_void) -> *mut c_void;
+    fn AXUIElementCreateApplication(pid: i32) -> *mut c_void;
+}
 
-fn copy_managed_display_spaces() -> CFRetained<CFArray<CFDict>> {
+type TitleHandler = Box<dyn Fn(u32, String) + Send>;
+static TITLE_HANDLER: Mutex<Option<TitleHandler>> = Mutex::new(None);
+
+/// Where title changes seen by a [`TitleWatch`] go, as (window id, new title).
+pub fn set_title_handler(handler: impl Fn(u32, String) + Send + 'static) {
+    if let Ok(mut current) = TITLE_HANDLER.lock() {
+        *current = Some(Box::new(handler));
+    }
+}
+
+unsafe extern "C" fn title_changed(
+    _observer: *mut c_void,
+    element: *mut c_void,
+    _notification: *const CFString,
+    refcon: *mut c_void,
+) {
+    // The window id rides along as the refcon, so this works without the private APIs too
+    let wid = refcon as usize as u32;
+    let element = unsafe { &*(element as *const AXUIElement) };
+    let Some(title) = get_attribute(element, "AXTitle").and_then(|t| t.downcast::<CFString>().ok())
+    else {
+        return;
+    };
+    if let Ok(handler) = TITLE_HANDLER.lock()
+        && let Some(handler) = handler.as_ref()
+    {
+        handler(wid, title.to_string());
+    }
+}
+
+/// AX observers reporting title changes of some windows, one per app. They stop when dropped.
+pub struct TitleWatch {
+    observers: Vec<*mut c_void>,
+}
+
+impl TitleWatch {
+    /// Watches each (pid, window id, element).
+    pub fn new<'a>(windows: impl IntoIterator<Item = (i32, u32, &'a AXUIElement)>) -> Self {
+        let notification = CFString::from_static_str("AXTitleChanged");
+        let mut observers: HashMap<i32, *mut c_void> = HashMap::new();
+        for (pid, wid, element) in windows {
+            let observer = match observers.get(&pid) {
+                Some(observer) => *observer,
+                None => {
+                    let mut observer = std::ptr::null_mut();
+                    if unsafe { AXObserverCreate(pid, title_changed, &mut observer) }
+                        != AXError::Success
+                    {
+                        continue;
+                    }
+                    observers.insert(pid, observer);
+                    observer
+                }
+            };
+            unsafe {
+                AXObserverAddNotification(
+                    observer,
+                    (element as *const AXUIElement).cast(),
+                    &*notification,
+                    wid as usize as *mut c_void,
+                )
+            };
+        }
+
+        let run_loop = unsafe { CFRunLoopGetMain() };
+        for observer in observers.values() {
+            unsafe {
+                let source = AXObserverGetRunLoopSource(*observer);
+                CFRunLoopAddSource(run_loop, source, kCFRunLoopCommonModes);
+            }
+        }
+        Self {
+            observers: observers.into_values().collect(),
+        }
+    }
+}
+
+impl Drop for TitleWatch {
+    fn drop(&mut self) {
+        let run_loop = unsafe { CFRunLoopGetMain() };
+        for observer in &self.observers {
+            unsafe {
+                let source = AXObserverGetRunLoopSource(*observer);
+                CFRunLoopRemoveSource(run_loop, source, kCFRunLoopCommonModes);
+                CFRelease(*observer);
+            }
+        }
+    }
+}
+
+/// Tells the Dock to do something, e.g. "com.apple.expose.awake" for Mission Control.
+fn send_dock_notification(name: &'static str) {
+    let name = CFString::from_static_str(name);
+    unsafe { CoreDockSendNotification(CFRetained::as_ptr(&name).as_ptr(), 0) };
+}
+
+pub fn mission_control() {
+    send_dock_notification("com.apple.expose.awake");
+}
+
+/// App Exposé for the frontmost app.
+pub fn app_expose() {
+    send_dock_notification("com.apple.expose.front.awake");
+}
+
+pub type CFDict = CFDictionary<CFString, CFType>;
+
+pub fn copy_managed_display_spaces() -> CFRetained<CFArray<CFDict>> {
     let cid = unsafe { SLSMainConnectionID() };
     unsafe {
         let ptr = NonNull::new_unchecked(SLSCopyManagedDisplaySpaces(cid) as *mut CFArray<CFDict>);
@@ -226,6 +975,180 @@ fn copy_managed_display_spaces() -> CFRetained<CFArray<CFDict>> {
     }
 }
 
+/// Whether "Displays have separate Spaces" is turned off. In that mode one set of spaces
+/// spans every display and SLSCopyManagedDisplaySpaces reports a single "Main" entry.
+pub fn displays_span_spaces() -> bool {
+    CFPreferencesCopyAppValue(
+        &CFString::from_static_str("spans-displays"),
+        &CFString::from_static_str("com.apple.spaces"),
+    )
+    .and_then(|v| v.downcast::<CFBoolean>().ok())
+    .is_some_and(|v| v.as_bool())
+}
+
+/// Preferences domain of the app bundle, which configuration profiles target.
+pub const PREFERENCES_DOMAIN: &str = "com.bootreer.switcheroo";
+
+/// Those of `keys` a configuration profile (MDM) forces for switcheroo.
+pub fn forced_preferences(keys: &[&str]) -> serde_json::Map<String, Value> {
+    let domain = CFString::from_static_str(PREFERENCES_DOMAIN);
+    keys.iter()
+        .filter_map(|&key| {
+            let cf_key = CFString::from_str(key);
+            if !CFPreferencesAppValueIsForced(&cf_key, &domain) {
+                return None;
+            }
+            let value = CFPreferencesCopyAppValue(&cf_key, &domain)?;
+            Some((key.to_string(), cf_to_json(&value)))
+        })
+        .collect()
+}
+
+/// Bundle ids of the apps kept in the Dock, left to right (top to bottom).
+pub fn dock_apps() -> Vec<String> {
+    let key = CFString::from_static_str("persistent-apps");
+    let domain = CFString::from_static_str("com.apple.dock");
+    let Some(apps) = CFPreferencesCopyAppValue(&key, &domain) else {
+        return Vec::new();
+    };
+    match cf_to_json(&apps) {
+        Value::Array(tiles) => tiles
+            .iter()
+            .filter_map(|tile| tile.pointer("/tile-data/bundle-identifier")?.as_str())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Strings, numbers, booleans, arrays and string-keyed dictionaries, anything else is null.
+pub fn cf_to_json(value: &CFType) -> Value {
+    if let Some(string) = value.downcast_ref::<CFString>() {
+        Value::String(string.to_string())
+    } else if let Some(boolean) = value.downcast_ref::<CFBoolean>() {
+        Value::Bool(boolean.as_bool())
+    } else if let Some(number) = value.downcast_ref::<CFNumber>() {
+        if number.is_float_type() {
+            number.as_f64().map_or(Value::Null, Value::from)
+        } else {
+            number.as_i64().map_or(Value::Null, Value::from)
+        }
+    } else if let Some(array) = value.downcast_ref::<CFArray>() {
+        let array = unsafe { array.cast_unchecked::<CFType>() };
+        Value::Array(array.iter().map(|item| cf_to_json(&item)).collect())
+    } else if let Some(dict) = value.downcast_ref::<CFDictionary>() {
+        let dict = unsafe { dict.cast_unchecked::<CFString, CFType>() };
+        let (keys, values) = dict.to_vecs();
+        Value::Object(
+            keys.iter()
+                .zip(values)
+                .map(|(key, value)| (key.to_string(), cf_to_json(&value)))
+                .collect(),
+        )
+    } else {
+        Value::Null
+    }
+}
+
+/// The other way round from [`cf_to_json`], None for null. Nulls in arrays and objects are
+/// left out.
+pub fn cf_from_json(value: &Value) -> Option<CFRetained<CFType>> {
+    fn upcast<T: std::ops::Deref<Target = CFType>>(value: &T) -> CFRetained<CFType> {
+        (**value).retain()
+    }
+    Some(match value {
+        Value::Null => return None,
+        Value::String(string) => upcast(&*CFString::from_str(string)),
+        Value::Bool(boolean) => upcast(CFBoolean::new(*boolean)),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => upcast(&*CFNumber::new_i64(int)),
+            None => upcast(&*CFNumber::new_f64(number.as_f64()?)),
+        },
+        Value::Array(items) => {
+            let items: Vec<_> = items.iter().filter_map(cf_from_json).collect();
+            upcast(&*CFArray::from_retained_objects(&items))
+        }
+        Value::Object(map) => {
+            let (keys, values): (Vec<_>, Vec<_>) = map
+                .iter()
+                .filter_map(|(key, value)| Some((CFString::from_str(key), cf_from_json(value)?)))
+                .unzip();
+            let keys: Vec<&CFString> = keys.iter().map(|key| &**key).collect();
+            let values: Vec<&CFType> = values.iter().map(|value| &**value).collect();
+            upcast(&*CFDictionary::from_slices(&keys, &values))
+        }
+    })
+}
+
+/// A space as listed by SLSCopyManagedDisplaySpaces.
+#[derive(Debug, Clone, Copy)]
+pub struct Space {
+    pub id: u64,
+    /// 0 for desktops, 4 for fullscreen apps
+    pub kind: i64,
+}
+
+/// Display UUID (when there is one) and spaces of each entry in SLSCopyManagedDisplaySpaces
+/// output, just the first with shared spaces: anything past the spanning entry would
+/// duplicate the same space ids.
+pub fn parse_display_spaces(
+    displays: &CFArray<CFDict>,
+    spans: bool,
+) -> Vec<(Option<String>, Vec<Space>)> {
+    let mut result = Vec::new();
+    for (idx, display) in displays.iter().enumerate() {
+        if spans && idx > 0 {
+            break;
+        }
+        let uuid = display_uuid_of(&display);
+        if uuid.is_none() {
+            log!("[warn] missing Display Identifier in SLSCopyManagedDisplaySpaces dict");
+        }
+        let Some(spaces) = get_value::<CFArray>(&display, &CFString::from_static_str("Spaces"))
+        else {
+            continue;
+        };
+        let spaces = unsafe { spaces.cast_unchecked::<CFDict>() }
+            .into_iter()
+            .filter_map(|space| {
+                let number = |key: &'static str| {
+                    get_value::<CFNumber>(&space, &CFString::from_static_str(key))?.as_i64()
+                };
+                Some(Space {
+                    id: number("id64")? as u64,
+                    kind: number("type").unwrap_or(-1),
+                })
+            })
+            .collect();
+        result.push((uuid, spaces));
+    }
+    result
+}
+
+/// Desktop spaces (no fullscreen apps) of each display, in Mission Control order.
+/// With shared spaces there's a single "Main" entry.
+pub fn user_spaces() -> Vec<(String, Vec<u64>)> {
+    desktop_spaces(&parse_display_spaces(
+        &copy_managed_display_spaces(),
+        displays_span_spaces(),
+    ))
+}
+
+/// [`user_spaces`] out of already parsed display spaces.
+pub fn desktop_spaces(displays: &[(Option<String>, Vec<Space>)]) -> Vec<(String, Vec<u64>)> {
+    displays
+        .iter()
+        .filter_map(|(uuid, spaces)| {
+            let ids = spaces
+                .iter()
+                .filter(|space| space.kind == 0)
+                .map(|space| space.id)
+                .collect();
+            Some((uuid.clone()?, ids))
+        })
+        .collect()
+}
+
 fn display_uuid_of(display: &CFDict) -> Option<String> {
     get_value::<CFString>(display, &CFString::from_static_str("Display Identifier"))
         .map(|v| v.to_string())
@@ -243,110 +1166,142 @@ pub struct WindowInfo {
     pub pid: i32,
     pub space_id: u64,
     pub display_uuid: Option<String>,
+    /// 0 for normal windows, higher for floating panels, PiP players etc.
+    pub layer: i32,
+    /// Global top-left-origin frame
+    pub bounds: Option<CGRect>,
 }
 
-pub fn get_visible_window_ids() -> HashMap<u32, WindowLocation> {
+/// Ids of the windows on a space, front to back.
+pub fn windows_on_space(space_id: u64) -> Vec<u32> {
     let cid = unsafe { SLSMainConnectionID() };
-    let mut visible = HashMap::new();
-
-    for display in copy_managed_display_spaces() {
-        let display_uuid = display_uuid_of(&display);
-        if display_uuid.is_none() {
-            eprintln!("[warn] missing Display Identifier in SLSCopyManagedDisplaySpaces dict");
-        }
-
-        let spaces = get_value_unchecked::<CFArray>(&display, &CFString::from_static_str("Spaces"));
-
-        for space in unsafe { spaces.cast_unchecked::<CFDict>() } {
-            let id = get_value_unchecked::<CFNumber>(&space, &CFString::from_static_str("id64"));
-            let Some(space_id) = id.as_i64().map(|v| v as u64) else {
-                continue;
-            };
-
-            let options = 0x2;
-            let mut set_tags: u64 = 0;
-            let mut clear_tags: u64 = 0;
-            let space_ids = CFArray::from_retained_objects(std::slice::from_ref(&id));
-
-            let w_ptr = unsafe {
-                SLSCopyWindowsWithOptionsAndTags(
-                    cid,
-                    0,
-                    CFRetained::as_ptr(&space_ids).as_ptr() as _,
-                    options,
-                    &mut set_tags,
-                    &mut clear_tags,
-                )
-            };
+    let options = 0x2;
+    let mut set_tags: u64 = 0;
+    let mut clear_tags: u64 = 0;
+    let id = CFNumber::new_i64(space_id as i64);
+    let space_ids = CFArray::from_retained_objects(std::slice::from_ref(&id));
+
+    let w_ptr = unsafe {
+        SLSCopyWindowsWithOptionsAndTags(
+            cid,
+            0,
+            CFRetained::as_ptr(&space_ids).as_ptr() as _,
+            options,
+            &mut set_tags,
+            &mut clear_tags,
+        )
+    };
 
-            let arr = unsafe {
-                let ptr = NonNull::new_unchecked(w_ptr as *mut CFArray<CFNumber>);
-                CFRetained::from_raw(ptr)
-            };
+    let arr = unsafe {
+        let ptr = NonNull::new_unchecked(w_ptr as *mut CFArray<CFNumber>);
+        CFRetained::from_raw(ptr)
+    };
+    arr.into_iter()
+        .filter_map(|wid| wid.as_i64().map(|wid| wid as u32))
+        .collect()
+}
 
-            for wid in arr {
-                if let Some(wid) = wid.as_i64() {
-                    visible.insert(
-                        wid as u32,
-                        WindowLocation {
-                            space_id,
-                            display_uuid: display_uuid.clone(),
-                        },
-                    );
-                }
+/// Where each window on any of the spaces is, by window id.
+pub fn locate_windows(
+    displays: &[(Option<String>, Vec<Space>)],
+    windows_on_space: impl Fn(u64) -> Vec<u32>,
+) -> HashMap<u32, WindowLocation> {
+    let mut visible = HashMap::new();
+    for (display_uuid, spaces) in displays {
+        for space in spaces {
+            for wid in windows_on_space(space.id) {
+                visible.insert(
+                    wid,
+                    WindowLocation {
+                        space_id: space.id,
+                        display_uuid: display_uuid.clone(),
+                    },
+                );
             }
         }
     }
-
     visible
 }
 
-pub fn get_window_info_list(visible: &HashMap<u32, WindowLocation>) -> Result<Vec<WindowInfo>> {
-    let Some(window_list) = CGWindowListCopyWindowInfo(Options::ExcludeDesktopElements, NullID)
-    else {
-        return Err(anyhow!("CGWindowListCopyWindowInfo failed."));
-    };
+pub fn copy_window_list() -> Option<CFRetained<CFArray<CFDict>>> {
+    let window_list = CGWindowListCopyWindowInfo(Options::ExcludeDesktopElements, NullID)?;
+    Some(unsafe { CFRetained::cast_unchecked(window_list) })
+}
 
+/// The windows in CGWindowListCopyWindowInfo output that are on one of the `visible` spaces.
+/// Entries without a window number or owner are skipped, windows without a title get "".
+pub fn parse_window_list(
+    window_list: &CFArray<CFDict>,
+    visible: &HashMap<u32, WindowLocation>,
+) -> Vec<WindowInfo> {
     let mut result = Vec::new();
-    for dict in unsafe { window_list.cast_unchecked() } {
-        let layer = get_value_unchecked::<CFNumber>(&dict, unsafe { kCGWindowLayer })
-            .as_i32()
-            .unwrap();
-        if layer != 0 {
+    for dict in window_list.iter() {
+        let number = |key: &CFString| get_value::<CFNumber>(&dict, key)?.as_i64();
+        let (Some(window_number), Some(pid)) = (
+            number(unsafe { kCGWindowNumber }),
+            number(unsafe { kCGWindowOwnerPID }),
+        ) else {
             continue;
-        }
-
-        let window_number = get_value_unchecked::<CFNumber>(&dict, unsafe { kCGWindowNumber })
-            .as_i64()
-            .unwrap() as u32;
-
+        };
+        let window_number = window_number as u32;
         let Some(loc) = visible.get(&window_number) else {
             continue;
         };
 
-        let pid = get_value_unchecked::<CFNumber>(&dict, unsafe { kCGWindowOwnerPID })
-            .as_i32()
-            .unwrap();
+        let layer = number(unsafe { kCGWindowLayer }).unwrap_or_default() as i32;
         let title = get_value::<CFString>(&dict, unsafe { kCGWindowName })
             .map(|v| v.to_string())
             .unwrap_or_default();
+        let bounds = get_value::<CFDictionary>(&dict, unsafe { kCGWindowBounds })
+            .and_then(|b| rect_from_dict(&unsafe { CFRetained::cast_unchecked::<CFDict>(b) }));
 
         result.push(WindowInfo {
             id: window_number,
             title,
-            pid,
+            pid: pid as i32,
             space_id: loc.space_id,
             display_uuid: loc.display_uuid.clone(),
+            layer,
+            bounds,
         });
     }
+    result
+}
 
-    Ok(result)
+/// Id, frame and whether it's on screen of each window in CGWindowListCopyWindowInfo output,
+/// for going without spaces.
+pub fn window_frames(window_list: &CFArray<CFDict>) -> Vec<(u32, CGRect, bool)> {
+    window_list
+        .iter()
+        .filter_map(|dict| {
+            let id = get_value::<CFNumber>(&dict, unsafe { kCGWindowNumber })?.as_i64()?;
+            let bounds = get_value::<CFDictionary>(&dict, unsafe { kCGWindowBounds })?;
+            let bounds = rect_from_dict(&unsafe { CFRetained::cast_unchecked::<CFDict>(bounds) })?;
+            let on_screen = get_value::<CFBoolean>(&dict, unsafe { kCGWindowIsOnscreen })
+                .is_some_and(|on_screen| on_screen.as_bool());
+            Some((id as u32, bounds, on_screen))
+        })
+        .collect()
 }
 
+/// Reads a `{X, Y, Width, Height}` dictionary like kCGWindowBounds.
+fn rect_from_dict(dict: &CFDict) -> Option<CGRect> {
+    let number = |key: &'static str| {
+        get_value::<CFNumber>(dict, &CFString::from_static_str(key)).and_then(|n| n.as_f64())
+    };
+    Some(CGRect::new(
+        CGPoint::new(number("X")?, number("Y")?),
+        CGSize::new(number("Width")?, number("Height")?),
+    ))
+}
+
+/// Finds the AX elements of the given windows within `budget`, also saying whether it ran
+/// out of time before finding them all.
 pub fn resolve_ax_for_pid(
     pid: i32,
     target_wids: &HashSet<u32>,
-) -> HashMap<u32, Retained<AXUIElement>> {
+    budget: Duration,
+) -> (HashMap<u32, Retained<AXUIElement>>, bool) {
     let mut buffer = init_ax_buffer(pid);
     let mut cg_w_id = 0;
     let mut result = HashMap::new();
@@ -354,9 +1309,7 @@ pub fn resolve_ax_for_pid(
 
     // AX element IDs are per-process and indexed over all elements (not just windows),
     // some apps (Chrome with many windows, Ghostty) push window IDs well past any small cap.
-    // => bound by wall-clock instead, scaled by how many windows we need to
-    // find: small lookups *should* stay snappy, large ones get room to complete.
-    let budget = Duration::from_millis((50 + 25 * target_wids.len() as u64).min(500));
+    // => bound by wall-clock instead
     let deadline = Instant::now() + budget;
 
     let mut id = 0u64;
@@ -376,7 +1329,7 @@ pub fn resolve_ax_for_pid(
         }
     }
 
-    result
+    (result, !remaining.is_empty())
 }
 
 fn get_value<T: ConcreteType>(
@@ -394,21 +1347,9 @@ fn get_value_unchecked<T: ConcreteType>(
 }
 
 pub fn make_key_window(id: u32, psn: &ProcessSerialNumber) -> CGError {
-    let mut bytes = [0u8; 0xf8];
-
-    bytes[0x04] = 0xf8;
-    bytes[0x3a] = 0x10;
-
-    let wid_bytes = id.to_ne_bytes();
-    bytes[0x3c] = wid_bytes[0];
-    bytes[0x3d] = wid_bytes[1];
-    bytes[0x3e] = wid_bytes[2];
-    bytes[0x3f] = wid_bytes[3];
-
-    bytes[0x20..0x30].fill(0xff);
+    let mut bytes = key_window_event(id);
 
     bytes[0x08] = 0x01;
-
     let res = unsafe { SLPSPostEventRecordTo(psn, bytes.as_mut_ptr()) };
     if res != CGError::Success {
         return res;
@@ -422,6 +1363,16 @@ pub fn make_key_window(id: u32, psn: &ProcessSerialNumber) -> CGError {
     CGError::Success
 }
 
+/// The event record [`make_key_window`] posts, minus the press/release byte at 0x08.
+fn key_window_event(id: u32) -> [u8; 0xf8] {
+    let mut bytes = [0u8; 0xf8];
+    bytes[0x04] = 0xf8;
+    bytes[0x3a] = 0x10;
+    bytes[0x3c..0x40].copy_from_slice(&id.to_ne_bytes());
+    bytes[0x20..0x30].fill(0xff);
+    bytes
+}
+
 fn init_ax_buffer(pid: i32) -> [u8; 20] {
     let mut buffer = [0u8; 20];
     buffer[0..4].copy_from_slice(&pid.to_ne_bytes());
@@ -466,32 +1417,196 @@ pub fn is_window(element: &AXUIElement) -> bool {
     false
 }
 
+/// The app's windows as AX lists them. Most apps only list those on the current space.
+pub fn ax_windows(pid: i32) -> Vec<Retained<AXUIElement>> {
+    let Some(windows) = application_element(pid)
+        .and_then(|app| get_attribute(&app, "AXWindows"))
+        .and_then(|windows| windows.downcast::<CFArray>().ok())
+    else {
+        return Vec::new();
+    };
+    unsafe { windows.cast_unchecked::<AXUIElement>() }
+        .iter()
+        .filter_map(|window| unsafe { Retained::retain(CFRetained::as_ptr(&window).as_ptr()) })
+        .collect()
+}
+
+/// The element's AXPosition and AXSize, in global top-left-origin coordinates like the
+/// window list.
+pub fn ax_frame(element: &AXUIElement) -> Option<CGRect> {
+    let read = |attr: &str, kind: AXValueType, out: NonNull<c_void>| {
+        let value = get_attribute(element, attr)?.downcast::<AXValue>().ok()?;
+        unsafe { value.value(kind, out) }.then_some(())
+    };
+    let mut origin = CGPoint::new(0., 0.);
+    let mut size = CGSize::new(0., 0.);
+    read(
+        "AXPosition",
+        AXValueType::CGPoint,
+        NonNull::from(&mut origin).cast(),
+    )?;
+    read(
+        "AXSize",
+        AXValueType::CGSize,
+        NonNull::from(&mut size).cast(),
+    )?;
+    Some(CGRect::new(origin, size))
+}
+
+pub fn set_ax_frame(element: &AXUIElement, frame: CGRect) -> Result<()> {
+    let position = unsafe {
+        AXValue::new(
+            AXValueType::CGPoint,
+            NonNull::from(&frame.origin).cast::<c_void>(),
+        )
+    }
+    .ok_or_else(|| anyhow!("Could not create AXPosition value"))?;
+    let size = unsafe {
+        AXValue::new(
+            AXValueType::CGSize,
+            NonNull::from(&frame.size).cast::<c_void>(),
+        )
+    }
+    .ok_or_else(|| anyhow!("Could not create AXSize value"))?;
+
+    for (attr, value) in [("AXPosition", &position), ("AXSize", &size)] {
+        let res = unsafe { element.set_attribute_value(&CFString::from_str(attr), value) };
+        if res != AXError::Success {
+            return Err(anyhow!("Setting {attr} failed with {res:?}"));
+        }
+    }
+    Ok(())
+}
+
+/// Moves a window without resizing it, in global top-left-origin coordinates.
+pub fn set_ax_position(element: &AXUIElement, origin: CGPoint) -> Result<()> {
+    let position = unsafe {
+        AXValue::new(
+            AXValueType::CGPoint,
+            NonNull::from(&origin).cast::<c_void>(),
+        )
+    }
+    .ok_or_else(|| anyhow!("Could not create AXPosition value"))?;
+    let res =
+        unsafe { element.set_attribute_value(&CFString::from_static_str("AXPosition"), &position) };
+    if res != AXError::Success {
+        return Err(anyhow!("Setting AXPosition failed with {res:?}"));
+    }
+    Ok(())
+}
+
+/// Sets a boolean AX attribute, e.g. AXMain.
+pub fn set_ax_bool(element: &AXUIElement, attr: &str, value: bool) -> Result<()> {
+    let res =
+        unsafe { element.set_attribute_value(&CFString::from_str(attr), CFBoolean::new(value)) };
+    if res != AXError::Success {
+        return Err(anyhow!("Setting {attr} failed with {res:?}"));
+    }
+    Ok(())
+}
+
+/// Best effort: recent macOS versions ignore this for windows owned by other processes.
+pub fn move_window_to_space(wid: u32, space_id: u64) {
+    let cid = unsafe { SLSMainConnectionID() };
+    let wid = CFNumber::new_i64(wid as i64);
+    let window_ids = CFArray::from_retained_objects(std::slice::from_ref(&wid));
+    unsafe {
+        SLSMoveWindowsToManagedSpace(cid, CFRetained::as_ptr(&window_ids).as_ptr() as _, space_id)
+    };
+}
+
+/// Launches an app by bundle id, or by name for apps without one.
+pub fn launch_app(app: &str) {
+    let flag = if app.contains('.') { "-b" } else { "-a" };
+    if let Err(e) = std::process::Command::new("open").args([flag, app]).spawn() {
+        log!("[warn] could not launch {app}: {e}");
+    }
+}
+
 pub fn get_attribute(element: &AXUIElement, attr: &str) -> Option<CFRetained<CFType>> {
     let mut ptr: *const CFType = std::ptr::null();
     let attr = CFString::from_str(attr);
     let res = unsafe { element.copy_attribute_value(&attr, NonNull::new_unchecked(&mut ptr)) };
     if res != AXError::Success {
-        eprintln!("AXUIElement::copy_attribute_value failed with {res:#?}");
+        log!("AXUIElement::copy_attribute_value failed with {res:#?}");
         return None;
     }
     Some(unsafe { CFRetained::from_raw(NonNull::new(ptr as *mut CFType)?) })
 }
 
+/// A boolean attribute like AXMinimized, false when the element doesn't have it.
+pub fn ax_flag(element: &AXUIElement, attr: &str) -> bool {
+    let mut ptr: *const CFType = std::ptr::null();
+    let attr = CFString::from_str(attr);
+    let res = unsafe { element.copy_attribute_value(&attr, NonNull::new_unchecked(&mut ptr)) };
+    if res != AXError::Success {
+        return false;
+    }
+    NonNull::new(ptr as *mut CFType)
+        .map(|ptr| unsafe { CFRetained::from_raw(ptr) })
+        .and_then(|value| value.downcast::<CFBoolean>().ok())
+        .is_some_and(|value| value.as_bool())
+}
+
 pub struct IconData {
     pub rgba: Vec<u8>,
     pub width: u32,
     pub height: u32,
 }
 
+impl IconData {
+    /// A fraction of the RGBA size for app icons, which are mostly flat color and transparency.
+    pub fn to_png(&self) -> Option<Vec<u8>> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Fast);
+        let mut writer = encoder.write_header().ok()?;
+        writer.write_image_data(&self.rgba).ok()?;
+        writer.finish().ok()?;
+        Some(png)
+    }
+
+    pub fn from_png(png: &[u8]) -> Option<Self> {
+        let mut reader = png::Decoder::new(png).read_info().ok()?;
+        let mut rgba = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut rgba).ok()?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return None;
+        }
+        rgba.truncate(info.buffer_size());
+        Some(Self {
+            rgba,
+            width: info.width,
+            height: info.height,
+        })
+    }
+}
+
+/// Points to pixels factor of the main display, 2.0 on Retina.
+pub fn backing_scale() -> f64 {
+    MainThreadMarker::new()
+        .and_then(NSScreen::mainScreen)
+        .map_or(1.0, |screen| screen.backingScaleFactor())
+}
+
+/// The icon Finder shows for a file or app bundle.
+pub fn file_icon(path: &str, pixels: f64) -> Option<IconData> {
+    let icon = NSWorkspace::sharedWorkspace().iconForFile(&NSString::from_str(path));
+    ns_image_to_rgba(&icon, pixels)
+}
+
 // TODO: why not just render the CGImage?
-pub fn ns_image_to_rgba(image: &NSImage) -> Option<IconData> {
-    image.setSize(CGSize::new(16., 16.));
+/// Rasterizes the image at `pixels` x `pixels`, so it's neither blurry nor oversized.
+pub fn ns_image_to_rgba(image: &NSImage, pixels: f64) -> Option<IconData> {
+    image.setSize(CGSize::new(pixels, pixels));
 
-    let cg_image =
-        unsafe { image.CGImageForProposedRect_context_hints(std::ptr::null_mut(), None, None) };
+    let mut rect = CGRect::new(CGPoint::new(0., 0.), CGSize::new(pixels, pixels));
+    let cg_image = unsafe { image.CGImageForProposedRect_context_hints(&mut rect, None, None) };
 
     if cg_image.is_none() {
-        eprintln!("[icon] CGImageForProposedRect returned None");
+        log!("[icon] CGImageForProposedRect returned None");
         return None;
     }
 
@@ -502,52 +1617,244 @@ pub fn ns_image_to_rgba(image: &NSImage) -> Option<IconData> {
 
     let data_provider = CGImage::data_provider(cg_image.as_deref());
     let data = CGDataProvider::data(data_provider.as_deref())?;
-    let raw_data = data.to_vec();
-
-    // Convert to RGBA8 regardless of source format
-    let rgba = match bits_per_pixel {
-        24 => {
-            // RGB -> RGBA
-            let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
-            for chunk in raw_data.chunks_exact(3) {
-                rgba.extend_from_slice(chunk);
-                rgba.push(255);
+    let rgba = to_rgba8(&data.to_vec(), width, height, bytes_per_row, bits_per_pixel)?;
+
+    Some(IconData {
+        rgba,
+        width,
+        height,
+    })
+}
+
+/// Tightly packed RGBA8 out of CGImage bitmap data, whatever padding its rows have.
+/// None for pixel formats we don't know or data that's shorter than the image says.
+fn to_rgba8(
+    raw: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_row: usize,
+    bits_per_pixel: usize,
+) -> Option<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let pixel_bytes = bits_per_pixel / 8;
+    if !matches!(bits_per_pixel, 24 | 32 | 64) {
+        log!("[icon] Unsupported bits_per_pixel: {bits_per_pixel}");
+        return None;
+    }
+    let row_bytes = width * pixel_bytes;
+    // The last row needn't be padded
+    let needed = height
+        .checked_sub(1)
+        .map_or(0, |rows| rows * bytes_per_row + row_bytes);
+    if bytes_per_row < row_bytes || raw.len() < needed {
+        log!(
+            "[icon] {width}x{height} image doesn't fit in {} bytes",
+            raw.len()
+        );
+        return None;
+    }
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = &raw[y * bytes_per_row..y * bytes_per_row + row_bytes];
+        for pixel in row.chunks_exact(pixel_bytes) {
+            match bits_per_pixel {
+                // RGB -> RGBA
+                24 => {
+                    rgba.extend_from_slice(pixel);
+                    rgba.push(255);
+                }
+                32 => rgba.extend_from_slice(pixel),
+                // Half-float RGBA
+                _ => {
+                    for channel in pixel.chunks_exact(2) {
+                        let value = half::f16::from_le_bytes([channel[0], channel[1]]);
+                        rgba.push((value.to_f32().clamp(0.0, 1.0) * 255.0) as u8);
+                    }
+                }
             }
-            rgba
         }
-        32 => raw_data,
-        64 => {
-            let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
-            for y in 0..height as usize {
-                for x in 0..width as usize {
-                    let offset = y * bytes_per_row + x * 8;
-                    if offset + 7 < raw_data.len() {
-                        let r = half::f16::from_le_bytes([raw_data[offset], raw_data[offset + 1]]);
-                        let g =
-                            half::f16::from_le_bytes([raw_data[offset + 2], raw_data[offset + 3]]);
-                        let b =
-                            half::f16::from_le_bytes([raw_data[offset + 4], raw_data[offset + 5]]);
-                        let a =
-                            half::f16::from_le_bytes([raw_data[offset + 6], raw_data[offset + 7]]);
-
-                        rgba.push((r.to_f32().clamp(0.0, 1.0) * 255.0) as u8);
-                        rgba.push((g.to_f32().clamp(0.0, 1.0) * 255.0) as u8);
-                        rgba.push((b.to_f32().clamp(0.0, 1.0) * 255.0) as u8);
-                        rgba.push((a.to_f32().clamp(0.0, 1.0) * 255.0) as u8);
+    }
+    Some(rgba)
+}
+
+/// A picture of the window as it is right now (even if covered), box-filtered down to at most
+/// `max_width` points wide, in pixels at `scale`. Needs Screen Recording, like listing window
+/// titles does.
+pub fn window_snapshot(wid: CGWindowID, max_width: u32, scale: f64) -> Option<IconData> {
+    let resolution = if scale > 1.0 {
+        CGWindowImageOption::BestResolution
+    } else {
+        CGWindowImageOption::NominalResolution
+    };
+    let max_width = (f64::from(max_width) * scale).round() as u32;
+    // CGRectNull, for just the window's own bounds
+    let null_rect = CGRect::new(
+        CGPoint::new(f64::INFINITY, f64::INFINITY),
+        CGSize::new(0., 0.),
+    );
+    #[allow(deprecated)]
+    let image = CGWindowListCreateImage(
+        null_rect,
+        Options::OptionIncludingWindow,
+        wid,
+        CGWindowImageOption::BoundsIgnoreFraming | resolution,
+    )?;
+
+    let src_width = CGImage::width(Some(&image));
+    let src_height = CGImage::height(Some(&image));
+    let bytes_per_row = CGImage::bytes_per_row(Some(&image));
+    if CGImage::bits_per_pixel(Some(&image)) != 32 || src_width == 0 || src_height == 0 {
+        return None;
+    }
+    let data_provider = CGImage::data_provider(Some(&image));
+    let raw_data = CGDataProvider::data(data_provider.as_deref())?.to_vec();
+
+    let factor = src_width.div_ceil(max_width.max(1) as usize).max(1);
+    let width = src_width / factor;
+    let height = src_height / factor;
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            for dy in 0..factor {
+                let row = (y * factor + dy) * bytes_per_row;
+                for dx in 0..factor {
+                    let offset = row + (x * factor + dx) * 4;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += u32::from(raw_data.get(offset + channel).copied().unwrap_or(0));
                     }
                 }
             }
-            rgba
+            let n = (factor * factor) as u32;
+            // Window captures come out as BGRA
+            rgba.extend_from_slice(&[
+                (sum[2] / n) as u8,
+                (sum[1] / n) as u8,
+                (sum[0] / n) as u8,
+                (sum[3] / n) as u8,
+            ]);
         }
-        other => {
-            eprintln!("[icon] Unsupported bits_per_pixel: {other}");
-            return None;
-        }
-    };
+    }
 
     Some(IconData {
         rgba,
-        width,
-        height,
+        width: width as u32,
+        height: height as u32,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn key_window_event_layout(id in any::<u32>()) {
+            let bytes = key_window_event(id);
+            prop_assert_eq!(&bytes[0x3c..0x40], &id.to_ne_bytes());
+            for (offset, byte) in bytes.iter().enumerate() {
+                let expected = match offset {
+                    0x04 => 0xf8,
+                    0x20..0x30 => 0xff,
+                    0x3a => 0x10,
+                    0x3c..0x40 => id.to_ne_bytes()[offset - 0x3c],
+                    // Includes 0x08, set when posting
+                    _ => 0,
+                };
+                prop_assert_eq!(*byte, expected, "byte {:#x}", offset);
+            }
+        }
+
+        #[test]
+        fn rgba_skips_row_padding(
+            width in 0u32..48,
+            height in 0u32..48,
+            bits_per_pixel in prop::sample::select(vec![24usize, 32, 64]),
+            padding in 0usize..24,
+        ) {
+            let pixel_bytes = bits_per_pixel / 8;
+            let bytes_per_row = width as usize * pixel_bytes + padding;
+            // Channel c of pixel (x, y), as the source stores it and as RGBA8
+            let channel = |x: usize, y: usize, c: usize| ((x * 7 + y * 13 + c * 61) % 256) as u8;
+            let to_half = |value: u8| half::f16::from_f32(f32::from(value) / 255.0);
+
+            let mut raw = vec![0xab; bytes_per_row * height as usize];
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let pixel = &mut raw[y * bytes_per_row + x * pixel_bytes..][..pixel_bytes];
+                    match bits_per_pixel {
+                        64 => {
+                            for (c, bytes) in pixel.chunks_exact_mut(2).enumerate() {
+                                bytes.copy_from_slice(&to_half(channel(x, y, c)).to_le_bytes());
+                            }
+                        }
+                        _ => {
+                            for (c, byte) in pixel.iter_mut().enumerate() {
+                                *byte = channel(x, y, c);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let rgba = to_rgba8(&raw, width, height, bytes_per_row, bits_per_pixel).unwrap();
+            prop_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+            for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+                let (x, y) = (i % width as usize, i / width as usize);
+                for (c, &value) in pixel.iter().enumerate() {
+                    let expected = match (bits_per_pixel, c) {
+                        (24, 3) => 255,
+                        (64, _) => (to_half(channel(x, y, c)).to_f32() * 255.0) as u8,
+                        _ => channel(x, y, c),
+                    };
+                    prop_assert_eq!(value, expected, "pixel ({}, {}) channel {}", x, y, c);
+                }
+            }
+        }
+
+        #[test]
+        fn rgba_rejects_short_data(
+            width in 1u32..32,
+            height in 1u32..32,
+            bits_per_pixel in prop::sample::select(vec![24usize, 32, 64]),
+            padding in 0usize..16,
+            missing in 1usize..64,
+        ) {
+            let row_bytes = width as usize * bits_per_pixel / 8;
+            let bytes_per_row = row_bytes + padding;
+            let needed = bytes_per_row * (height as usize - 1) + row_bytes;
+            let raw = vec![0; needed.saturating_sub(missing)];
+            prop_assert!(to_rgba8(&raw, width, height, bytes_per_row, bits_per_pixel).is_none());
+        }
+
+        #[test]
+        fn icons_survive_png(
+            width in 1u32..48,
+            height in 1u32..48,
+            seed in any::<u8>(),
+        ) {
+            let rgba = (0..width * height * 4)
+                .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+                .collect();
+            let icon = IconData { rgba, width, height };
+            let decoded = IconData::from_png(&icon.to_png().unwrap()).unwrap();
+            prop_assert_eq!((decoded.width, decoded.height), (width, height));
+            prop_assert_eq!(decoded.rgba, icon.rgba);
+        }
+
+        #[test]
+        fn rgba_never_panics(
+            raw in prop::collection::vec(any::<u8>(), 0..4096),
+            width in 0u32..128,
+            height in 0u32..128,
+            bytes_per_row in 0usize..1024,
+            bits_per_pixel in 0usize..128,
+        ) {
+            if let Some(rgba) = to_rgba8(&raw, width, height, bytes_per_row, bits_per_pixel) {
+                prop_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+            }
+        }
+    }
+}
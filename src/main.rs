@@ -1,31 +1,64 @@
-use global_hotkey::{
-    GlobalHotKeyManager,
-    hotkey::{Code, HotKey, Modifiers},
-};
-use objc2_application_services::AXUIElement;
+use anyhow::anyhow;
 
+mod agent;
+mod bookmarks;
+mod clipboard;
+mod commands;
+mod config;
+mod corner;
+mod crash;
+mod deeplink;
+mod doctor;
+mod fixture;
+mod history;
+mod hooks;
+mod hotkeys;
+mod keymap;
+mod labels;
 mod macos;
+mod matching;
+mod metrics;
+mod query;
+mod rank;
+mod scripting;
+mod session;
+mod slow_apps;
+mod sources;
+mod stats;
 mod ui;
 mod windows;
+mod workspaces;
 
 fn main() -> iced::Result {
-    unsafe {
-        let system_wide = AXUIElement::new_system_wide();
-        AXUIElement::set_messaging_timeout(&system_wide, 0.5);
+    // Finder used to pass -psn_* when launching bundles, ignore those
+    if let Some(command) = std::env::args().skip(1).find(|a| !a.starts_with("-psn")) {
+        let result = match command.as_str() {
+            "install-agent" => agent::install(),
+            "uninstall-agent" => agent::uninstall(),
+            "doctor" => doctor::run(),
+            "metrics" => metrics::dump(),
+            "record-fixture" => fixture::record(
+                std::env::args()
+                    .skip_while(|a| a != "record-fixture")
+                    .nth(1),
+            ),
+            other => Err(anyhow!("Unknown command {other:?}")),
+        };
+        if let Err(e) = result {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
     }
 
-    macos::set_accessory_mode();
+    crash::install_panic_hook();
+    crash::report_previous_crash();
 
-    let hotkey_manager = GlobalHotKeyManager::new().expect("Could not create GlobalHotKeyManager");
-    let hotkey = HotKey::new(Some(Modifiers::META), Code::KeyD);
-    hotkey_manager
-        .register(hotkey)
-        .expect("Could not register hot key");
+    macos::set_accessory_mode();
 
-    // Leak the hotkey manager
-    std::mem::forget(hotkey_manager);
+    let config = config::Config::load();
 
-    iced::daemon(ui::boot, ui::update, ui::view)
+    iced::daemon(move || ui::boot(config.clone()), ui::update, ui::view)
         .title(ui::title)
         .subscription(ui::subscription)
         .style(
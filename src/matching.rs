@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nucleo::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo::{Config, Matcher, Nucleo};
+
+use crate::config::AppMatcher;
+use crate::labels::Labels;
+use crate::stats::app_key;
+use crate::windows::Manager;
+
+/// Fuzzy matches window ids against "<app name> <title>" (plus the folder of Finder windows and
+/// the window's label, in that order) on nucleo's worker threads, so a big window list never
+/// blocks the UI. Apps can be matched differently, see [`AppMatcher`].
+pub struct Engine {
+    nucleo: Nucleo<u32>,
+    /// Window id to its prebuilt haystack in nucleo
+    items: HashMap<u32, Haystack>,
+    /// Last pattern handed to nucleo, typing on from it only narrows the previous matches
+    pattern: String,
+    /// The same pattern respecting case, for apps matched case-sensitively. Nucleo only
+    /// knows one case mode per pattern, so their matches get checked again with this
+    case_sensitive: Pattern,
+    matcher: Matcher,
+    /// Per-app tweaks, by [`app_key`]
+    app_matchers: HashMap<String, AppMatcher>,
+    labels: Labels,
+    running: bool,
+}
+
+struct Haystack {
+    /// Index in nucleo
+    idx: u32,
+    /// Where the title starts, in chars
+    title_start: u32,
+    /// Chars left off the front of the title ([`AppMatcher::last_path_component`])
+    title_skipped: u32,
+    case_sensitive: bool,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            // Polled from a subscription while running, nothing to wake up
+            nucleo: Nucleo::new(Config::DEFAULT, Arc::new(|| {}), None, 1),
+            items: HashMap::new(),
+            pattern: String::new(),
+            case_sensitive: Pattern::default(),
+            matcher: Matcher::new(Config::DEFAULT),
+            app_matchers: HashMap::new(),
+            labels: Labels::default(),
+            running: false,
+        }
+    }
+
+    /// Applies from the next [`Engine::set_windows`] on.
+    pub fn set_app_matchers(&mut self, app_matchers: &HashMap<String, AppMatcher>) {
+        self.app_matchers = app_matchers.clone();
+    }
+
+    /// Applies from the next [`Engine::set_windows`] on.
+    pub fn set_labels(&mut self, labels: &Labels) {
+        self.labels = labels.clone();
+    }
+
+    /// Swaps the matched items for the manager's current windows.
+    pub fn set_windows(&mut self, manager: &Manager) {
+        self.nucleo.restart(false);
+        self.items.clear();
+        let injector = self.nucleo.injector();
+        for app in manager.app_map().values() {
+            let tweaks = self.app_matchers.get(&app_key(app));
+            let last_path_component = tweaks.is_some_and(|t| t.last_path_component);
+            for window in &app.windows {
+                let start = match window.title.trim_end_matches('/').rfind('/') {
+                    Some(slash) if last_path_component => slash + 1,
+                    _ => 0,
+                };
+                let title = &window.title[start..];
+                let label = self.labels.get(app, window);
+                let idx = injector.push(window.id, |_, columns| {
+                    let mut haystack = format!("{} {title}", app.name);
+                    for extra in [window.folder.as_deref(), label].into_iter().flatten() {
+                        haystack.push(' ');
+                        haystack.push_str(extra);
+                    }
+                    columns[0] = haystack.as_str().into();
+                });
+                let haystack = Haystack {
+                    idx,
+                    title_start: app.name.chars().count() as u32 + 1,
+                    title_skipped: window.title[..start].chars().count() as u32,
+                    case_sensitive: tweaks.is_some_and(|t| t.case_sensitive),
+                };
+                self.items.insert(window.id, haystack);
+            }
+        }
+        self.running = true;
+    }
+
+    pub fn set_pattern(&mut self, text: &str) {
+        if text == self.pattern {
+            return;
+        }
+        // A trailing backslash escapes whatever comes next, so that can't be appended to
+        let append = text.starts_with(self.pattern.as_str()) && !self.pattern.ends_with('\\');
+        self.nucleo
+            .pattern
+            .reparse(0, text, CaseMatching::Smart, Normalization::Smart, append);
+        self.case_sensitive = Pattern::parse(text, CaseMatching::Respect, Normalization::Smart);
+        self.pattern = text.to_string();
+        self.running = true;
+    }
+
+    /// Waits up to `timeout_ms` for the workers to catch up, true while they're still busy.
+    pub fn tick(&mut self, timeout_ms: u64) -> bool {
+        self.running = self.nucleo.tick(timeout_ms).running;
+        self.running
+    }
+
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    /// Fills `scores` with the fuzzy score of every matching window, as of the last tick.
+    pub fn scores(&mut self, scores: &mut HashMap<u32, u32>) {
+        let snapshot = self.nucleo.snapshot();
+        scores.clear();
+        for (item, m) in snapshot.matched_items(..).zip(snapshot.matches()) {
+            let wid = *item.data;
+            if !self.items.get(&wid).is_some_and(|h| h.case_sensitive) {
+                scores.insert(wid, m.score);
+                continue;
+            }
+            let haystack = item.matcher_columns[0].slice(..);
+            if let Some(score) = self.case_sensitive.score(haystack, &mut self.matcher) {
+                scores.insert(wid, score);
+            }
+        }
+    }
+
+    /// Char indices of the window's "<app name> <title>" matched by the current pattern,
+    /// for highlighting. Only worth it for rows that are actually shown.
+    pub fn indices(&self, matcher: &mut nucleo::Matcher, wid: u32) -> Vec<u32> {
+        let mut indices = Vec::new();
+        let snapshot = self.nucleo.snapshot();
+        let Some(haystack) = self.items.get(&wid) else {
+            return indices;
+        };
+        let Some(item) = snapshot.get_item(haystack.idx) else {
+            return indices;
+        };
+        let pattern = if haystack.case_sensitive {
+            &self.case_sensitive
+        } else {
+            self.nucleo.pattern.column_pattern(0)
+        };
+        pattern.indices(item.matcher_columns[0].slice(..), matcher, &mut indices);
+        // Back to positions in the full title
+        for index in &mut indices {
+            if *index >= haystack.title_start {
+                *index += haystack.title_skipped;
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
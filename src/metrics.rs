@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::macos;
+
+const METRICS_FILE: &str = "metrics.json";
+/// Samples kept per metric and macOS version, older ones are dropped
+const MAX_SAMPLES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    /// Listing windows
+    Refresh,
+    /// Focusing the picked window
+    Focus,
+    /// Updating the results after the query changed
+    Match,
+    /// From the hotkey going off until the picker window draws its first frame
+    Show,
+    /// From Enter until the picked window is key
+    Switch,
+}
+
+/// Timings in milliseconds by macOS version, so slowdowns after an update stand out.
+/// `switcheroo metrics` prints them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metrics {
+    versions: BTreeMap<String, BTreeMap<Metric, Vec<f64>>>,
+    #[serde(skip)]
+    os_version: String,
+}
+
+impl Metrics {
+    pub fn load() -> Self {
+        let mut metrics: Self = config::load_data(METRICS_FILE);
+        metrics.os_version = macos::os_version().unwrap_or_else(|| String::from("unknown"));
+        metrics
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(METRICS_FILE, self)
+    }
+
+    pub fn record(&mut self, metric: Metric, duration: Duration) {
+        let samples = self
+            .versions
+            .entry(self.os_version.clone())
+            .or_default()
+            .entry(metric)
+            .or_default();
+        samples.push(duration.as_secs_f64() * 1000.0);
+        if samples.len() > MAX_SAMPLES {
+            samples.drain(..samples.len() - MAX_SAMPLES);
+        }
+    }
+}
+
+/// Prints p50/p95/max of every metric per macOS version.
+pub fn dump() -> Result<()> {
+    let metrics = Metrics::load();
+    if metrics.versions.is_empty() {
+        println!("No metrics recorded yet");
+    }
+    for (version, samples) in &metrics.versions {
+        println!("macOS {version}");
+        for (metric, samples) in samples {
+            let mut sorted = samples.clone();
+            sorted.sort_by(f64::total_cmp);
+            println!(
+                "  {:<8} {:>4} samples  p50 {:>7.1}ms  p95 {:>7.1}ms  max {:>7.1}ms",
+                format!("{metric:?}").to_lowercase(),
+                sorted.len(),
+                percentile(&sorted, 0.5),
+                percentile(&sorted, 0.95),
+                sorted.last().copied().unwrap_or_default()
+            );
+        }
+    }
+    println!(
+        "\n(from {})",
+        config::data_dir().join(METRICS_FILE).display()
+    );
+    Ok(())
+}
+
+/// One time the picker was open, end to end. Logged as a single line when it closes, so
+/// "feels slower since the update" comes with numbers.
+#[derive(Debug)]
+pub struct Invocation {
+    started: Instant,
+    /// The window was made visible, its next frame is when it's shown
+    opened: bool,
+    shown: Option<Duration>,
+    confirmed: Option<Instant>,
+    switched: Option<Duration>,
+}
+
+impl Invocation {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            opened: false,
+            shown: None,
+            confirmed: None,
+            switched: None,
+        }
+    }
+
+    /// Moves the start back to when the hotkey event came in, before it got handled.
+    pub fn triggered_at(&mut self, at: Instant) {
+        self.started = self.started.min(at);
+    }
+
+    pub fn opened(&mut self) {
+        self.opened = true;
+    }
+
+    /// Whether the window is up but hasn't drawn a frame yet.
+    pub fn awaiting_frame(&self) -> bool {
+        self.opened && self.shown.is_none()
+    }
+
+    pub fn shown(&mut self, metrics: &mut Metrics) {
+        if self.shown.is_none() {
+            let elapsed = self.started.elapsed();
+            metrics.record(Metric::Show, elapsed);
+            self.shown = Some(elapsed);
+        }
+    }
+
+    pub fn confirmed(&mut self) {
+        self.confirmed = Some(Instant::now());
+    }
+
+    pub fn switched(&mut self, metrics: &mut Metrics) {
+        if let Some(confirmed) = self.confirmed.take() {
+            let elapsed = confirmed.elapsed();
+            metrics.record(Metric::Switch, elapsed);
+            self.switched = Some(elapsed);
+        }
+    }
+
+    /// E.g. "shown 41ms, switched 12ms after Enter, open 1850ms".
+    pub fn summary(&self) -> String {
+        let millis = |d: Option<Duration>| {
+            d.map_or_else(|| String::from("-"), |d| format!("{}ms", d.as_millis()))
+        };
+        format!(
+            "shown {}, switched {} after Enter, open {}",
+            millis(self.shown),
+            millis(self.switched),
+            millis(Some(self.started.elapsed()))
+        )
+    }
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
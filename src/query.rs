@@ -0,0 +1,77 @@
+use crate::windows::{App, WindowState};
+
+/// Operators understood by [`Query::parse`], shown in the help overlay.
+pub const OPERATORS: &[(&str, &str)] = &[
+    (
+        "app:<name>",
+        "Only windows whose app name or bundle id contains <name>",
+    ),
+    (
+        "display:<name>",
+        "Only windows on displays whose name contains <name>",
+    ),
+    (
+        "is:<state>",
+        "Only windows that are minimized, hidden or fullscreen",
+    ),
+    (">", "Command mode, lists commands instead of windows"),
+];
+
+/// A parsed picker query: `key:value` operators plus the free text that gets fuzzy matched.
+#[derive(Debug, Default)]
+pub struct Query {
+    pub text: String,
+    apps: Vec<String>,
+    displays: Vec<String>,
+    states: Vec<String>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Self::default();
+        let mut text = Vec::new();
+
+        for token in input.split_whitespace() {
+            match token.split_once(':') {
+                Some(("app", value)) if !value.is_empty() => {
+                    query.apps.push(value.to_lowercase());
+                }
+                Some(("display", value)) if !value.is_empty() => {
+                    query.displays.push(value.to_lowercase());
+                }
+                Some(("is", value)) if !value.is_empty() => {
+                    query.states.push(value.to_lowercase());
+                }
+                _ => text.push(token),
+            }
+        }
+
+        query.text = text.join(" ");
+        query
+    }
+
+    /// `app:` operators match the app name or bundle id case-insensitively; all must match.
+    pub fn matches_app(&self, app: &App) -> bool {
+        let name = app.name.to_lowercase();
+        let bundle_id = app.bundle_id.as_deref().unwrap_or_default().to_lowercase();
+        self.apps
+            .iter()
+            .all(|a| name.contains(a.as_str()) || bundle_id.contains(a.as_str()))
+    }
+
+    /// `display:` operators match the window's display name case-insensitively.
+    pub fn matches_display(&self, display: Option<&str>) -> bool {
+        let display = display.unwrap_or_default().to_lowercase();
+        self.displays.iter().all(|d| display.contains(d.as_str()))
+    }
+
+    /// `is:minimized`, `is:hidden` and `is:fullscreen`; all must hold, other states never do.
+    pub fn matches_state(&self, state: WindowState) -> bool {
+        self.states.iter().all(|s| match s.as_str() {
+            "minimized" => state.minimized,
+            "hidden" => state.hidden,
+            "fullscreen" => state.fullscreen,
+            _ => false,
+        })
+    }
+}
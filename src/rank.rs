@@ -0,0 +1,87 @@
+use crate::config::{Config, Frecency};
+use crate::stats::{Stats, app_key, unix_now};
+use crate::windows::{App, Window};
+
+/// Shortest half-lives taken, anything less forgets a switch right away anyway
+const MIN_HALF_LIFE_DAYS: f64 = 0.01;
+
+/// How a row's rank came about; rows are sorted by [`Score::total`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Score {
+    pub fuzzy: u32,
+    /// Already weighted, like `recency`
+    pub frecency: u32,
+    pub recency: u32,
+    pub boost: i64,
+    /// From the user script's `rank(window)`
+    pub script: i64,
+}
+
+impl Score {
+    pub fn new(fuzzy: u32, stats: &Stats, config: &Config, app: &App, window: &Window) -> Self {
+        let (frecency, recency) = match stats.window_usage(app, window) {
+            Some(usage) => history(&config.frecency, usage.count, usage.last_used),
+            None => (0, 0),
+        };
+        Self {
+            fuzzy,
+            frecency,
+            recency,
+            boost: config
+                .boosts
+                .get(&app_key(app))
+                .copied()
+                .unwrap_or_default(),
+            script: 0,
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.fuzzy as i64 + self.frecency as i64 + self.recency as i64 + self.boost + self.script
+    }
+}
+
+/// Frecency and recency points for `count` switches, the last at `last_used`: switch count
+/// decayed by how long ago that was, capped so a habitual window can't outrank a clearly
+/// better text match, and a bonus for having just been used.
+fn history(params: &Frecency, count: u64, last_used: u64) -> (u32, u32) {
+    let age_secs = unix_now().saturating_sub(last_used) as f64;
+    let half_life = params.half_life_days.max(MIN_HALF_LIFE_DAYS);
+    let decay = 0.5_f64.powf(age_secs / 86_400.0 / half_life);
+    let frecency = (count as f64 * params.per_switch * decay).min(params.max);
+    let recency = params.recency_bonus * 0.5_f64.powf(age_secs / 3_600.0);
+    let weight = params.weight.max(0.0);
+    (
+        (frecency * weight).round() as u32,
+        (recency * weight).round() as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decays_and_weighs_history() {
+        let params = Frecency {
+            recency_bonus: 40.0,
+            ..Frecency::default()
+        };
+        let now = unix_now();
+        assert_eq!(history(&params, 4, now), (20, 40));
+        assert_eq!(history(&params, 100, now), (60, 40));
+        // A week on, the switches count half and the bonus is long gone
+        assert_eq!(history(&params, 4, now - 7 * 86_400), (10, 0));
+
+        let light = Frecency {
+            weight: 0.5,
+            ..params
+        };
+        assert_eq!(history(&light, 4, now), (10, 20));
+        let off = Frecency {
+            weight: 0.0,
+            ..light
+        };
+        assert_eq!(history(&off, 4, now), (0, 0));
+    }
+}
@@ -0,0 +1,257 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use anyhow::{Result, anyhow};
+use objc2_core_foundation::{CGPoint, CGRect, CGSize};
+use rhai::{AST, Array, Dynamic, Engine, Map, Scope};
+
+use crate::config;
+use crate::crash::log;
+use crate::windows::{App, Manager, Window};
+
+const SCRIPT_FILE: &str = "init.rhai";
+/// Keeps a runaway script from hanging the picker, for an action or a whole listing
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// What a script asked for, done by the caller once the script returns.
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Focus(u32),
+    Move(u32, CGRect),
+    Close(u32),
+}
+
+/// User scripts from `~/.config/switcheroo/init.rhai`. They can define
+/// - `fn rank(window)`, an int added to every window's rank
+/// - `fn filter(window)`, false leaves the window out
+/// - functions without parameters, run from the picker with `> run <name>`
+///
+/// Windows are maps with `id`, `app`, `bundle_id`, `title` and `pid`, and scripts get
+/// `list()`, `focus(id)`, `move(id, x, y, w, h)` and `close(id)` to work with them.
+pub struct Scripts {
+    engine: Engine,
+    ast: Option<AST>,
+    /// Script file mtime as of the last (re)load
+    modified: Option<SystemTime>,
+    /// What `list()` returns, see [`Scripts::set_windows`]
+    windows: Rc<RefCell<Array>>,
+    /// Asked for by the running script
+    ops: Rc<RefCell<Vec<Op>>>,
+    has_rank: bool,
+    has_filter: bool,
+    /// What `filter` and `rank` made of each window, see [`Scripts::verdict`]
+    verdicts: RefCell<HashMap<u32, Option<i64>>>,
+    /// Operations left for the current pass, see [`Scripts::begin_pass`]
+    budget: Rc<Cell<u64>>,
+    /// Operations the running call has used so far
+    used: Rc<Cell<u64>>,
+    /// Set by the first failed `filter` or `rank`, which are off until the script changes
+    failed: Cell<bool>,
+    /// That failure, until [`Scripts::take_error`] hands it out
+    error: RefCell<Option<String>>,
+}
+
+impl Scripts {
+    pub fn load() -> Self {
+        let mut scripts = Self::new();
+        scripts.reload_if_changed();
+        scripts
+    }
+
+    /// No script loaded yet, see [`Scripts::reload_if_changed`].
+    pub fn new() -> Self {
+        let windows = Rc::new(RefCell::new(Array::new()));
+        let ops = Rc::new(RefCell::new(Vec::new()));
+
+        let budget = Rc::new(Cell::new(MAX_OPERATIONS));
+        let used = Rc::new(Cell::new(0));
+
+        let mut engine = Engine::new();
+        let (left, count) = (budget.clone(), used.clone());
+        engine.on_progress(move |ops| {
+            count.set(ops);
+            (ops > left.get()).then(|| Dynamic::from("out of operations"))
+        });
+        let list = windows.clone();
+        engine.register_fn("list", move || list.borrow().clone());
+        let queued = ops.clone();
+        engine.register_fn("focus", move |id: i64| {
+            queued.borrow_mut().push(Op::Focus(id as u32));
+        });
+        let queued = ops.clone();
+        engine.register_fn("move", move |id: i64, x: i64, y: i64, w: i64, h: i64| {
+            let frame = CGRect::new(
+                CGPoint::new(x as f64, y as f64),
+                CGSize::new(w as f64, h as f64),
+            );
+            queued.borrow_mut().push(Op::Move(id as u32, frame));
+        });
+        let queued = ops.clone();
+        engine.register_fn("close", move |id: i64| {
+            queued.borrow_mut().push(Op::Close(id as u32));
+        });
+
+        Self {
+            engine,
+            ast: None,
+            modified: None,
+            windows,
+            ops,
+            has_rank: false,
+            has_filter: false,
+            verdicts: RefCell::default(),
+            budget,
+            used,
+            failed: Cell::new(false),
+            error: RefCell::default(),
+        }
+    }
+
+    /// Picks up edits to the script. A broken edit is logged and the previous script kept.
+    pub fn reload_if_changed(&mut self) {
+        let path = script_path();
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.forget_windows();
+        self.failed.set(false);
+        if modified.is_none() {
+            self.ast = None;
+            self.has_rank = false;
+            self.has_filter = false;
+            return;
+        }
+
+        let ast = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| self.engine.compile(source).map_err(|e| anyhow!("{e}")));
+        let ast = match ast {
+            Ok(ast) => ast,
+            Err(e) => {
+                log!("[warn] not applying {}: {e}", path.display());
+                return;
+            }
+        };
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == 1)
+        };
+        self.has_rank = defines("rank");
+        self.has_filter = defines("filter");
+        self.ast = Some(ast);
+        log!("loaded {}", path.display());
+    }
+
+    /// Updates what `list()` returns, before running an action.
+    pub fn set_windows(&self, manager: &Manager) {
+        let windows = manager
+            .app_map()
+            .values()
+            .flat_map(|app| {
+                app.windows
+                    .iter()
+                    .map(move |w| Dynamic::from(window_map(app, w)))
+            })
+            .collect();
+        *self.windows.borrow_mut() = windows;
+    }
+
+    /// Drops what `filter` and `rank` said, for when windows came, went or were renamed.
+    pub fn forget_windows(&self) {
+        self.verdicts.borrow_mut().clear();
+    }
+
+    /// Starts a listing, which all of its `filter` and `rank` calls share one budget for.
+    pub fn begin_pass(&self) {
+        self.budget.set(MAX_OPERATIONS);
+    }
+
+    /// None if the script's `filter(window)` leaves the window out, otherwise its
+    /// `rank(window)` (0 without one). Remembered until [`Scripts::forget_windows`].
+    pub fn verdict(&self, app: &App, window: &Window) -> Option<i64> {
+        if !(self.has_rank || self.has_filter) || self.failed.get() {
+            return Some(0);
+        }
+        if let Some(&verdict) = self.verdicts.borrow().get(&window.id) {
+            return verdict;
+        }
+        let keep = !self.has_filter || self.call("filter", app, window).unwrap_or(true);
+        let rank = if keep && self.has_rank {
+            self.call("rank", app, window).unwrap_or_default()
+        } else {
+            0
+        };
+        if self.failed.get() {
+            return Some(0);
+        }
+        let verdict = keep.then_some(rank);
+        self.verdicts.borrow_mut().insert(window.id, verdict);
+        verdict
+    }
+
+    /// The first failure since it was last asked for, to show to the user.
+    pub fn take_error(&self) -> Option<String> {
+        self.error.borrow_mut().take()
+    }
+
+    /// Calls `filter` or `rank`. A failure, running out of budget included, turns both off.
+    fn call<T: rhai::Variant + Clone>(&self, name: &str, app: &App, window: &Window) -> Option<T> {
+        let ast = self.ast.as_ref()?;
+        self.used.set(0);
+        let result = self
+            .engine
+            .call_fn(&mut Scope::new(), ast, name, (window_map(app, window),));
+        self.budget
+            .set(self.budget.get().saturating_sub(self.used.get()));
+        // Only actions get to change windows
+        self.ops.borrow_mut().clear();
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let message = format!("Script {name} failed, turned it off: {e}");
+                log!("[warn] {message}");
+                self.failed.set(true);
+                self.forget_windows();
+                *self.error.borrow_mut() = Some(message);
+                None
+            }
+        }
+    }
+
+    /// Runs a script function without parameters, returning what it asked for.
+    pub fn run(&self, name: &str) -> Result<Vec<Op>> {
+        let ast = self
+            .ast
+            .as_ref()
+            .ok_or_else(|| anyhow!("No {SCRIPT_FILE} loaded"))?;
+        self.begin_pass();
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, name, ());
+        let ops = std::mem::take(&mut *self.ops.borrow_mut());
+        result.map_err(|e| anyhow!("Script {name:?} failed: {e}"))?;
+        Ok(ops)
+    }
+}
+
+fn window_map(app: &App, window: &Window) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), Dynamic::from(i64::from(window.id)));
+    map.insert("app".into(), Dynamic::from(app.name.clone()));
+    map.insert(
+        "bundle_id".into(),
+        Dynamic::from(app.bundle_id.clone().unwrap_or_default()),
+    );
+    map.insert("title".into(), Dynamic::from(window.title.clone()));
+    map.insert("pid".into(), Dynamic::from(i64::from(app.pid)));
+    map
+}
+
+fn script_path() -> PathBuf {
+    config::config_dir().join(SCRIPT_FILE)
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::app_key;
+use crate::windows::{App, Manager, Window};
+use crate::{config, macos};
+
+const SESSION_FILE: &str = "session.json";
+
+/// The window last in front, focused again when switcheroo next starts with `restore_focus`.
+/// Saved whenever focus moves, so it's there however switcheroo went away at logout.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// [`app_key`] of the window's app, None once it's been restored
+    pub app: Option<String>,
+    pub title: String,
+}
+
+impl Session {
+    pub fn load() -> Self {
+        config::load_data(SESSION_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(SESSION_FILE, self)
+    }
+
+    pub fn of(app: &App, window: &Window) -> Self {
+        Self {
+            app: Some(app_key(app)),
+            title: window.title.clone(),
+        }
+    }
+
+    /// The app that just came to the front with its focused window, asked through AX rather
+    /// than refreshing. None for apps the last refresh didn't list.
+    pub fn front(manager: &Manager, pid: i32) -> Option<Self> {
+        let app = manager.app_map().get(&pid)?;
+        Some(Self {
+            app: Some(app_key(app)),
+            title: macos::focused_window_title(pid).unwrap_or_default(),
+        })
+    }
+
+    /// The recorded window if it's back with the same title, otherwise any window of its app.
+    pub fn resolve(&self, manager: &Manager) -> Option<u32> {
+        let app = self.app.as_deref()?;
+        let windows: Vec<&Window> = manager
+            .app_map()
+            .values()
+            .filter(|a| app_key(a) == app)
+            .flat_map(|a| &a.windows)
+            .collect();
+        windows
+            .iter()
+            .find(|w| w.title == self.title)
+            .or_else(|| windows.first())
+            .map(|w| w.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::two_displays;
+
+    #[test]
+    fn resolves_by_title_then_app() {
+        let mut manager = Manager::default();
+        manager.refresh_from(&two_displays()).unwrap();
+        let session = |app: &str, title: &str| Session {
+            app: Some(app.to_string()),
+            title: title.to_string(),
+        };
+
+        assert_eq!(
+            session("com.apple.Safari", "Keynote stream").resolve(&manager),
+            Some(106)
+        );
+        // Closed since, so the app's first window
+        assert_eq!(
+            session("com.apple.Safari", "Old tab").resolve(&manager),
+            Some(101)
+        );
+        assert_eq!(session("com.apple.mail", "Inbox").resolve(&manager), None);
+        assert_eq!(Session::default().resolve(&manager), None);
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const SLOW_APPS_FILE: &str = "slow_apps.json";
+/// Timed out lookups in a row before an app stops being resolved on refresh
+const STRIKES: u32 = 3;
+/// How long an app stays slow after its last timeout before it gets another chance
+const EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// Apps whose AX lookups keep running out of time. Their windows are resolved when
+/// something needs them instead of on every refresh, so one hung app doesn't slow
+/// down the picker. An hour after their last timeout they get another chance.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlowApps {
+    /// Timeouts in a row, by app key
+    strikes: BTreeMap<String, u32>,
+    /// When each app last timed out
+    last_strike: BTreeMap<String, SystemTime>,
+}
+
+impl SlowApps {
+    pub fn load() -> Self {
+        config::load_data(SLOW_APPS_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(SLOW_APPS_FILE, self)
+    }
+
+    pub fn is_slow(&self, app: &str) -> bool {
+        let recent = self
+            .last_strike
+            .get(app)
+            .is_some_and(|at| at.elapsed().is_ok_and(|age| age < EXPIRY));
+        recent && self.strikes.get(app).is_some_and(|&n| n >= STRIKES)
+    }
+
+    /// Records how a lookup for `app` went, true if anything changed.
+    pub fn record(&mut self, app: &str, timed_out: bool) -> bool {
+        if timed_out {
+            *self.strikes.entry(app.to_string()).or_default() += 1;
+            self.last_strike.insert(app.to_string(), SystemTime::now());
+            true
+        } else {
+            self.last_strike.remove(app);
+            self.strikes.remove(app).is_some()
+        }
+    }
+}
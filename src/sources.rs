@@ -0,0 +1,161 @@
+use std::process::Command;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::{Config, QuickCommand};
+use crate::crash::log;
+use crate::hooks;
+
+/// A row contributed by an [`ItemSource`], listed after the windows.
+pub struct SourceItem {
+    /// Stable within its source, e.g. a path
+    pub id: String,
+    pub title: String,
+    pub subtitle: String,
+    /// File whose icon the row shows, drawn once the row comes into view
+    pub icon: Option<String>,
+}
+
+/// Something besides windows that has rows to offer the picker: apps to launch, browser
+/// tabs, tmux sessions... Enabled by id with `sources` in the config.
+pub trait ItemSource {
+    fn id(&self) -> &'static str;
+    /// Picks up changes, called when the picker opens and the items are a minute old
+    fn refresh(&mut self) {}
+    fn items(&self) -> &[SourceItem];
+    /// Does whatever Enter on the item should do
+    fn activate(&self, item: &SourceItem) -> Result<()>;
+}
+
+fn builtin() -> Vec<Box<dyn ItemSource>> {
+    vec![Box::new(Apps::default())]
+}
+
+/// The config's quick commands, then the sources it names in that order.
+pub fn enabled(config: &Config) -> Vec<Box<dyn ItemSource>> {
+    let mut available = builtin();
+    let mut sources: Vec<Box<dyn ItemSource>> = Vec::new();
+    if !config.quick_commands.is_empty() {
+        sources.push(Box::new(QuickCommands::new(&config.quick_commands)));
+    }
+    for id in &config.sources {
+        match available.iter().position(|source| source.id() == id) {
+            Some(idx) => sources.push(available.remove(idx)),
+            None => log!("[warn] no source called {id:?}"),
+        }
+    }
+    sources
+}
+
+struct QuickCommands {
+    commands: Vec<QuickCommand>,
+    items: Vec<SourceItem>,
+}
+
+impl QuickCommands {
+    fn new(commands: &[QuickCommand]) -> Self {
+        let items = commands
+            .iter()
+            .enumerate()
+            .map(|(idx, command)| SourceItem {
+                id: idx.to_string(),
+                title: command.name.clone(),
+                subtitle: command
+                    .command
+                    .clone()
+                    .or_else(|| command.url.clone())
+                    .unwrap_or_default(),
+                icon: command.icon.clone(),
+            })
+            .collect();
+        Self {
+            commands: commands.to_vec(),
+            items,
+        }
+    }
+}
+
+impl ItemSource for QuickCommands {
+    fn id(&self) -> &'static str {
+        "quick-commands"
+    }
+
+    fn items(&self) -> &[SourceItem] {
+        &self.items
+    }
+
+    fn activate(&self, item: &SourceItem) -> Result<()> {
+        let command = item
+            .id
+            .parse::<usize>()
+            .ok()
+            .and_then(|idx| self.commands.get(idx))
+            .context("Unknown quick command")?;
+        match (&command.command, &command.url) {
+            (Some(shell), _) => hooks::run(shell, None),
+            (None, Some(url)) => {
+                Command::new("open")
+                    .arg(url)
+                    .spawn()
+                    .with_context(|| format!("Could not open {url}"))?;
+            }
+            (None, None) => return Err(anyhow!("{:?} has no command or url", command.name)),
+        }
+        Ok(())
+    }
+}
+
+const APP_DIRS: &[&str] = &[
+    "/Applications",
+    "/Applications/Utilities",
+    "/System/Applications",
+    "/System/Applications/Utilities",
+];
+/// Installed apps, opened (or brought forward if running) on Enter.
+#[derive(Default)]
+struct Apps {
+    items: Vec<SourceItem>,
+}
+
+impl ItemSource for Apps {
+    fn id(&self) -> &'static str {
+        "apps"
+    }
+
+    fn refresh(&mut self) {
+        self.items.clear();
+        for dir in APP_DIRS {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_none_or(|ext| ext != "app") {
+                    continue;
+                }
+                let id = path.to_string_lossy().into_owned();
+                self.items.push(SourceItem {
+                    title: path
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    subtitle: dir.to_string(),
+                    icon: Some(id.clone()),
+                    id,
+                });
+            }
+        }
+    }
+
+    fn items(&self) -> &[SourceItem] {
+        &self.items
+    }
+
+    fn activate(&self, item: &SourceItem) -> Result<()> {
+        Command::new("open")
+            .arg(&item.id)
+            .spawn()
+            .with_context(|| format!("Could not open {}", item.id))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::windows::{App, Window};
+
+const STATS_FILE: &str = "stats.json";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u64,
+    /// Unix timestamp in seconds
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowUsage {
+    pub app: String,
+    pub title: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// Persisted switch counts, used for the stats view and for ranking.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub apps: HashMap<String, Usage>,
+    pub windows: HashMap<String, WindowUsage>,
+    pub sessions: u64,
+    pub session_secs: f64,
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        config::load_data(STATS_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(STATS_FILE, self)
+    }
+
+    pub fn record_switch(&mut self, app: &App, window: &Window) {
+        let now = unix_now();
+        let app_usage = self.apps.entry(app_key(app)).or_default();
+        app_usage.count += 1;
+        app_usage.last_used = now;
+
+        let window_usage = self
+            .windows
+            .entry(window_key(app, window))
+            .or_insert_with(|| WindowUsage {
+                app: app.name.clone(),
+                title: window.title.clone(),
+                usage: Usage::default(),
+            });
+        window_usage.usage.count += 1;
+        window_usage.usage.last_used = now;
+    }
+
+    pub fn record_session(&mut self, duration: Duration) {
+        self.sessions += 1;
+        self.session_secs += duration.as_secs_f64();
+    }
+
+    pub fn average_session(&self) -> Option<Duration> {
+        (self.sessions > 0)
+            .then(|| Duration::from_secs_f64(self.session_secs / self.sessions as f64))
+    }
+
+    pub fn window_usage(&self, app: &App, window: &Window) -> Option<Usage> {
+        self.windows.get(&window_key(app, window)).map(|w| w.usage)
+    }
+
+    pub fn top_windows(&self, n: usize) -> Vec<&WindowUsage> {
+        let mut windows: Vec<&WindowUsage> = self.windows.values().collect();
+        windows.sort_by(|a, b| b.usage.count.cmp(&a.usage.count));
+        windows.truncate(n);
+        windows
+    }
+}
+
+/// Window ids don't survive restarts, so usage is keyed by app and title instead.
+pub fn window_key(app: &App, window: &Window) -> String {
+    format!("{}\u{1f}{}", app_key(app), window.title)
+}
+
+pub fn app_key(app: &App) -> String {
+    app.bundle_id.clone().unwrap_or_else(|| app.name.clone())
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,50 +1,528 @@
-use std::collections::HashSet;
+use std::cell::{OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use global_hotkey::{GlobalHotKeyEvent, HotKeyState};
-use iced::keyboard::{self, Key, key::Named};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use iced::keyboard;
+use iced::widget::text::Wrapping;
 use iced::widget::{
-    center, column, container, image, rich_text, row, scrollable, span, text_input,
+    button, center, column, container, image, mouse_area, rich_text, row, scrollable, span, stack,
+    text, text_input, tooltip,
 };
-use iced::widget::text::Wrapping;
 use iced::window;
 use iced::{Element, Length, Subscription, Task, Theme, color};
-use nucleo_matcher::{Config, Matcher, Utf32String};
 
-use crate::windows;
+use crate::bookmarks::Bookmarks;
+use crate::commands::{self, Command};
+use crate::config::{
+    AltAction, ColumnKind, Ellipsis, IconStyle, Sort, SpaceAccents, Trigger, WhileSharing,
+};
+use crate::crash::log;
+use crate::deeplink::Link;
+use crate::history::History;
+use crate::hotkeys::{ModifierTap, Picker};
+use crate::keymap::{self, BINDINGS};
+use crate::labels::Labels;
+use crate::metrics::{Invocation, Metric, Metrics};
+use crate::query::{self, Query};
+use crate::rank::Score;
+use crate::scripting::{Op, Scripts};
+use crate::session::Session;
+use crate::sources::{self, ItemSource};
+use crate::stats::{Stats, app_key};
+use crate::workspaces::Workspaces;
+use crate::{clipboard, config, corner, hotkeys, matching, windows};
 
 const SEARCH_INPUT_ID: &str = "search_input";
+const RESULTS_ID: &str = "results";
+/// Scroll distance (in pixels, a wheel line counts as one step) that moves the selection by one
+const SCROLL_STEP: f32 = 20.0;
+/// Horizontal two-finger travel that switches to the next saved search
+const SWIPE_DISTANCE: f32 = 120.0;
+/// Rows built beyond the visible ones on either side, so scrolling doesn't show gaps
+const OVERSCAN: usize = 10;
+/// How long a toast stays up
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(6);
+/// How many of the most recently used apps get their icons uploaded before the first frame
+const PREWARM_ICONS: usize = 12;
+/// Window snapshots are scaled down to this many pixels wide
+const SNAPSHOT_WIDTH: u32 = 240;
+/// Same for the Quick Look preview
+const PREVIEW_WIDTH: u32 = 1200;
+/// How long a snapshot is shown before taking a new one
+const SNAPSHOT_TTL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Source icons get scaled down to the configured size when drawn
+const SOURCE_ICON_PIXELS: f64 = 64.0;
+/// How long sources' items (installed apps...) are good for before a refresh reads them again
+const SOURCES_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+/// How long title changes are collected before the matcher picks them up, a terminal running
+/// a build can retitle its window many times a second
+const TITLE_BATCH: std::time::Duration = std::time::Duration::from_millis(150);
+/// How long the selection has to stay on a window before its snapshot shows
+const DWELL: std::time::Duration = std::time::Duration::from_millis(600);
+const PICKER_WIDTH: f32 = 640.0;
+/// Fits "59m ago", so the last-active column is as wide on every row
+const LAST_ACTIVE_WIDTH: f32 = 56.0;
+/// How big the picker starts out (and ends up when closing) relative to its full size
+const ANIMATION_SCALE: f32 = 0.96;
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    ShowPicker,
+    /// With when the event came in
+    HotKey(u32, HotKeyState, std::time::Instant),
     HidePicker,
     QueryChanged(String),
     SelectNext,
     SelectPrev,
+    PageDown,
+    PageUp,
     Confirm,
+    /// Confirm with the configured [`AltAction`] for windows
+    ConfirmAlt,
+    FocusWindow(u32),
+    RevealInFinder,
+    CopyMetadata(CopyField),
+    ToggleKeepOpen,
+    SetBookmark(u8),
+    ToggleHelp,
+    ToggleQuickLook,
+    /// The Space key, after whatever the search field made of it
+    SpacePressed,
+    /// Starts labeling the selected window, in the search field
+    EditLabel,
+    LabelChanged(String),
+    /// Chip number as shown, counting from 1, 0 turns them all off
+    ToggleChip(u8),
+    ToggleDebugScores,
+    TogglePrivacy,
+    SavedSearchKey(String),
+    SetPickerHotkey(String),
+    CheckConfig,
+    CheckPermissions,
+    ShowPermissions,
+    /// System Settings pane, e.g. "Privacy_Accessibility"
+    OpenPrivacySettings(&'static str),
+    PollModifierTap,
+    PollHotCorner,
+    PollClipboard,
+    Paste,
+    Scrolled(iced::mouse::ScrollDelta),
+    ResultsScrolled(scrollable::Viewport),
+    MatcherTick,
+    Revalidate,
+    /// The windows read by `Revalidate`, None if that fell over
+    Revalidated(Option<Arc<windows::Snapshot>>),
+    /// Periodic refresh while the picker stays open
+    RefreshTick,
+    /// Shows that a reload is on the way, [`Message::ReloadNow`] does it a frame later
+    Reload,
+    ReloadNow,
+    DismissToast(std::time::Instant),
+    AppActivated(i32),
+    AppTerminated(i32),
+    /// A `switcheroo://` URL another app opened
+    OpenUrl(String),
+    TitleChanged(u32, String),
+    /// Time to hand the titles changed since the first [`Message::TitleChanged`] to the matcher
+    TitlesChanged,
+    RowHovered(u32),
+    AnimationFrame(std::time::Instant),
+    Dwelled(u32),
+    SnapshotTaken(u32, Option<image::Handle>),
+    /// The query narrowed things down to this window at that moment, see `auto_confirm_ms`
+    UniqueMatch(u32, std::time::Instant),
+    IconAllocated(Option<image::Allocation>),
+    RestoreWorkspace(String),
+    /// Tries focusing the window from before logout, see `restore_focus`. Counts attempts
+    RestoreFocus(u8),
     WindowClosed(window::Id),
-    NoOp,
+    /// Another app or window got focus while the picker was up
+    PickerUnfocused,
+    /// The picker window exists but is still hidden, see `show_picker`
+    PickerOpened(window::Id),
+    /// The picker window drew its first frame, see [`Invocation`]
+    PickerShown,
+    /// The picker moved to a display with another backing scale
+    Rescaled(f32),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CopyField {
+    Title,
+    AppName,
+    BundleId,
+    WindowId,
+    DeepLink,
+}
+
+/// Picker colors, adjusted for the system Reduce Transparency / Increase Contrast settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Palette {
+    background: iced::Color,
+    selection: iced::Color,
+    text: iced::Color,
+    text_selected: iced::Color,
+    highlight: iced::Color,
+    highlight_selected: iced::Color,
+}
+
+impl Palette {
+    fn current() -> Self {
+        let contrast = crate::macos::increase_contrast();
+        let opaque = contrast || crate::macos::reduce_transparency();
+
+        Self {
+            background: iced::Color {
+                a: if opaque { 1.0 } else { 0.93 },
+                ..color!(0x1a1a1a)
+            },
+            selection: if contrast {
+                color!(0x0a4fc9)
+            } else {
+                color!(0x2d6de0)
+            },
+            text: if contrast {
+                color!(0xffffff)
+            } else {
+                color!(0xcccccc)
+            },
+            text_selected: color!(0xffffff),
+            highlight: if contrast {
+                color!(0x9be0ff)
+            } else {
+                color!(0x64c8ff)
+            },
+            highlight_selected: if contrast {
+                color!(0xffff00)
+            } else {
+                color!(0xffff96)
+            },
+        }
+    }
+}
+
+/// The picker fading and growing in, or shrinking away before its window closes.
+#[derive(Debug, Clone, Copy)]
+struct Animation {
+    started: std::time::Instant,
+    duration: std::time::Duration,
+    /// Window to close once faded out, None while opening
+    closing: Option<window::Id>,
+}
+
+impl Animation {
+    /// How far along the picker is to being fully shown, eased
+    fn shown(&self) -> f32 {
+        let t = (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        let eased = 1.0 - (1.0 - t).powi(3);
+        if self.closing.is_some() {
+            1.0 - eased
+        } else {
+            eased
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+}
+
+/// A short-lived notice under the results, e.g. for a window that wouldn't focus.
+#[derive(Debug, Clone)]
+struct Toast {
+    message: String,
+    /// Window to try focusing again
+    retry: Option<u32>,
+    /// Also tells it apart from later toasts when its timer runs out
+    shown_at: std::time::Instant,
+}
+
+/// A label being typed for a window, in place of the query.
+#[derive(Debug, Clone)]
+struct Labeling {
+    wid: u32,
+    draft: String,
+}
+
+/// What the area below the search field shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Results,
+    Stats,
+    Help,
+    /// Picker hotkey status, with alternatives if it couldn't be registered
+    Hotkey,
+    Permissions,
+    /// Large snapshot of the selected window, Quick Look style
+    QuickLook,
+}
+
+/// Checked every second while the picker is open and one is missing, there's no notification
+/// for either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Permissions {
+    accessibility: bool,
+    screen_recording: bool,
+}
+
+impl Permissions {
+    fn current() -> Self {
+        Self {
+            accessibility: crate::macos::accessibility_granted(),
+            screen_recording: crate::macos::screen_recording_granted(),
+        }
+    }
+
+    const fn all(self) -> bool {
+        self.accessibility && self.screen_recording
+    }
+}
+
+/// Filters toggled below the search field, on top of the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Chip {
+    CurrentSpace,
+    CurrentDisplay,
+    Minimized,
+    Hidden,
+}
+
+/// How many filter chips there are, Cmd+1..CHIPS toggle them
+pub const CHIPS: u8 = Chip::ALL.len() as u8;
+
+impl Chip {
+    /// In the order shown, also what Cmd+1.. toggle
+    const ALL: [Chip; 4] = [
+        Chip::CurrentSpace,
+        Chip::CurrentDisplay,
+        Chip::Minimized,
+        Chip::Hidden,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Chip::CurrentSpace => "Current Space",
+            Chip::CurrentDisplay => "Current Display",
+            Chip::Minimized => "Minimized",
+            Chip::Hidden => "Hidden",
+        }
+    }
+
+    fn matches(self, manager: &windows::Manager, window: &windows::Window) -> bool {
+        match self {
+            Chip::CurrentSpace => manager.on_active_space(window),
+            Chip::CurrentDisplay => manager.on_cursor_display(window),
+            Chip::Minimized => window.state.minimized,
+            Chip::Hidden => window.state.hidden,
+        }
+    }
 }
 
 pub struct Switcheroo {
     query: String,
     selected: Option<usize>,
     filtered_count: usize,
+    /// How many of the first items make up the "Recent" section, 0 without one
+    recent_count: usize,
     manager: windows::Manager,
+    engine: matching::Engine,
+    /// Result rows, recomputed when the query or the matches change and shared by update and view
+    items: Vec<Item>,
+    sources: Vec<Box<dyn ItemSource>>,
+    /// Matching source items, listed after `items`
+    source_rows: Vec<SourceRow>,
+    /// By file, None for those without one. Drawn as rows come into view, see
+    /// [`load_source_icons`]
+    source_icons: HashMap<String, Option<image::Handle>>,
+    /// When the sources were last refreshed, see [`SOURCES_TTL`]
+    sources_refreshed: Option<std::time::Instant>,
+    clipboard: clipboard::History,
+    /// Clipboard history entries matching `> clip`, best first
+    clip_rows: Vec<usize>,
+    /// Reused for highlighting rows as they're shown, view only gets `&self`
+    matcher: RefCell<nucleo::Matcher>,
+    /// Scratch space reused by [`update_results`] rather than allocated on every change
+    scores: HashMap<u32, u32>,
+    /// Icon handles by pid and whether the row is selected. Drawing the same handle every
+    /// frame is what lets iced keep the texture rather than upload it again
+    icons: HashMap<(i32, bool), image::Handle>,
+    /// Icons uploaded ahead of the first frame, held while the picker is open
+    prewarmed: Vec<image::Allocation>,
     picker_window: Option<window::Id>,
+    keep_open: bool,
+    peek_origin: Option<u32>,
+    /// Raised by `peek` and not put back yet, see [`unpeek`]
+    peeked: Option<u32>,
+    palette: Palette,
+    panel: Panel,
+    stats: Stats,
+    metrics: Metrics,
+    scripts: Scripts,
+    history: History,
+    bookmarks: Bookmarks,
+    labels: Labels,
+    labeling: Option<Labeling>,
+    workspaces: Workspaces,
+    /// Last saved for `restore_focus`
+    session: Session,
+    shown_at: Option<std::time::Instant>,
+    invocation: Option<Invocation>,
+    /// Hotkey currently held down, when triggering on press
+    held_hotkey: Option<u32>,
+    hotkey_manager: GlobalHotKeyManager,
+    /// None while no picker hotkey could be registered
+    picker: Option<Picker>,
+    modifier_tap: ModifierTap,
+    hot_corner: corner::Detector,
+    /// Scrolling not yet turned into selection moves or a swipe
+    scrolled: (f32, f32),
+    /// Scroll offset and height of the result list, as last reported
+    results_viewport: (f32, f32),
+    hotkey_error: Option<String>,
+    permissions: Permissions,
+    /// The screen was shared or mirrored when the picker opened, see `while_sharing`
+    screen_shared: bool,
+    /// Privacy mode turned on with its hotkey, on top of `privacy` in the config
+    privacy: bool,
+    /// Bundle ids of the Dock's apps, read when the picker opens with `sort = "dock"`
+    dock_order: Vec<String>,
+    /// Backing scale of the display the picker is on
+    scale: f64,
+    /// `config.theme` snapped to that display's pixels
+    theme: config::Theme,
+    toast: Option<Toast>,
+    /// Keeps titles current while the picker is open
+    title_watch: Option<crate::macos::TitleWatch>,
+    /// By window id, with when they were taken. Dropped when the picker closes
+    snapshots: HashMap<u32, (image::Handle, std::time::Instant)>,
+    /// Selected window the selection has rested on long enough to show its snapshot
+    dwelled: Option<u32>,
+    /// The only window matching the query, and since when
+    unique_match: Option<(u32, std::time::Instant)>,
+    /// A title changed and the matcher still has the old one, see [`TITLE_BATCH`]
+    titles_stale: bool,
+    animation: Option<Animation>,
+    /// Shown by [`Panel::QuickLook`], by window id
+    quick_look: Option<(u32, image::Handle)>,
+    /// Toggled filter chips, none means all windows
+    chips: HashSet<Chip>,
+    /// A forced reload is under way
+    reloading: bool,
+    /// The matches coming in are for windows that changed underneath the user, not for
+    /// what they typed, so they don't get to move the selection
+    keep_selection: bool,
+    /// Window ids in the order they were listed when the selection was first moved, kept
+    /// until the query changes with `stable_order`
+    frozen_order: Option<HashMap<u32, usize>>,
+    /// A space was held back from the query, it toggles the preview if the Space key typed it
+    held_space: bool,
+    config: config::Config,
+    /// Config file mtime as of the last (re)load
+    config_modified: Option<std::time::SystemTime>,
+    /// Why the last config edit wasn't applied, shown once the picker is up
+    config_error: Option<String>,
 }
 
-pub fn boot() -> (Switcheroo, Task<Message>) {
-    (
-        Switcheroo {
-            query: String::new(),
-            selected: None,
-            filtered_count: 0,
-            manager: windows::Manager::new().unwrap_or_default(),
-            picker_window: None,
-        },
-        Task::none(),
-    )
+pub fn boot(config: config::Config) -> (Switcheroo, Task<Message>) {
+    // The URL switcheroo was opened with comes in as soon as the event loop runs
+    crate::macos::install_url_handler();
+    let scale = crate::macos::backing_scale();
+    let mut manager = windows::Manager::new(&config);
+    if let Err(e) = manager.refresh() {
+        log!("Failed to refresh windows: {e}");
+    }
+    let labels = Labels::load();
+    let mut engine = matching::Engine::new();
+    engine.set_app_matchers(&config.matchers);
+    engine.set_labels(&labels);
+    engine.set_windows(&manager);
+
+    let hotkey_manager = hotkeys::register(&config);
+    let picker = hotkeys::picker(&config);
+    let hotkey_error = hotkeys::swap_picker(&hotkey_manager, None, picker)
+        .err()
+        .map(|e| format!("Could not register {}: {e}", picker.label()));
+
+    let mut state = Switcheroo {
+        query: String::new(),
+        selected: None,
+        filtered_count: 0,
+        recent_count: 0,
+        manager,
+        engine,
+        items: Vec::new(),
+        sources: sources::enabled(&config),
+        source_rows: Vec::new(),
+        source_icons: HashMap::new(),
+        sources_refreshed: None,
+        clipboard: clipboard::History::default(),
+        clip_rows: Vec::new(),
+        matcher: RefCell::new(nucleo::Matcher::new(nucleo::Config::DEFAULT)),
+        scores: HashMap::new(),
+        icons: HashMap::new(),
+        prewarmed: Vec::new(),
+        picker_window: None,
+        keep_open: false,
+        peek_origin: None,
+        peeked: None,
+        palette: Palette::current(),
+        panel: Panel::Results,
+        stats: Stats::load(),
+        metrics: Metrics::load(),
+        scripts: Scripts::load(),
+        history: History::default(),
+        bookmarks: Bookmarks::load(),
+        labels,
+        labeling: None,
+        workspaces: Workspaces::load(),
+        session: Session::default(),
+        shown_at: None,
+        invocation: None,
+        held_hotkey: None,
+        picker: hotkey_error.is_none().then_some(picker),
+        modifier_tap: ModifierTap::default(),
+        hot_corner: corner::Detector::default(),
+        scrolled: (0.0, 0.0),
+        results_viewport: (0.0, 0.0),
+        hotkey_error,
+        permissions: Permissions::current(),
+        screen_shared: false,
+        privacy: false,
+        dock_order: Vec::new(),
+        scale,
+        theme: config.theme.snapped(scale),
+        toast: None,
+        title_watch: None,
+        snapshots: HashMap::new(),
+        dwelled: None,
+        unique_match: None,
+        titles_stale: false,
+        animation: None,
+        quick_look: None,
+        chips: HashSet::new(),
+        reloading: false,
+        keep_selection: false,
+        frozen_order: None,
+        held_space: false,
+        hotkey_manager,
+        config,
+        config_modified: config::modified(),
+        config_error: None,
+    };
+
+    let restore = if state.config.restore_focus {
+        Task::done(Message::RestoreFocus(0))
+    } else {
+        Task::none()
+    };
+    // Without the picker hotkey there'd be no way in, so say so right away
+    if let Some(e) = &state.hotkey_error {
+        log!("[warn] {e}");
+        let task = show_picker(&mut state, None);
+        state.panel = Panel::Hotkey;
+        return (state, Task::batch([restore, task]));
+    }
+    (state, restore)
 }
 
 pub fn title(_state: &Switcheroo, _window: window::Id) -> String {
@@ -52,85 +530,116 @@ pub fn title(_state: &Switcheroo, _window: window::Id) -> String {
 }
 
 pub fn update(state: &mut Switcheroo, message: Message) -> Task<Message> {
+    let mut task = handle(state, message);
+    // Scripts fail while listing windows, where there's no task to show a toast with
+    if let Some(e) = state.scripts.take_error() {
+        task = Task::batch([task, show_toast(state, e, None)]);
+    }
+    // Config edits are picked up while opening, before there's a picker to show them on
+    if state.picker_window.is_some()
+        && let Some(e) = state.config_error.take()
+    {
+        task = Task::batch([task, show_toast(state, e, None)]);
+    }
+    task
+}
+
+fn handle(state: &mut Switcheroo, message: Message) -> Task<Message> {
     match message {
-        Message::ShowPicker => {
-            if state.picker_window.is_some() {
+        Message::HotKey(id, edge, received) => {
+            // On press, key repeat sends more presses and the release still follows,
+            // so only the first press of a held combo counts
+            let fire = match (state.config.hotkey_trigger, edge) {
+                (Trigger::Press, HotKeyState::Pressed) => state.held_hotkey.replace(id) != Some(id),
+                (Trigger::Press, HotKeyState::Released) => {
+                    state.held_hotkey = None;
+                    false
+                }
+                (Trigger::Release, HotKeyState::Released) => true,
+                (Trigger::Release, HotKeyState::Pressed) => false,
+            };
+            if !fire {
                 return Task::none();
             }
-
-            const WINDOW_W: f32 = 640.0;
-            const WINDOW_H: f32 = 380.0;
-
-            let position = match crate::macos::active_display_frame_at_cursor() {
-                Some((sx, sy, sw, sh)) => window::Position::Specific(iced::Point::new(
-                    sx + (sw - WINDOW_W) / 2.0,
-                    sy + (sh - WINDOW_H) / 2.0,
-                )),
-                None => window::Position::Centered,
-            };
-
-            crate::macos::activate_application();
-
-            if let Err(e) = state.manager.refresh() {
-                eprintln!("Failed to refresh windows: {e}");
+            if matches!(state.picker, Some(Picker::Hotkey(h)) if h.id() == id) {
+                let task = show_picker(state, None);
+                if let Some(invocation) = &mut state.invocation {
+                    invocation.triggered_at(received);
+                }
+                return task;
             }
-            state.query.clear();
-            state.filtered_count = get_filtered_items(state).len();
-            state.selected = if state.filtered_count > 0 {
-                Some(0)
-            } else {
-                None
+            let matches = |hotkey: &Option<String>| {
+                hotkey.as_deref().and_then(hotkeys::parse).map(|h| h.id()) == Some(id)
             };
-
-            let (id, open_task) = window::open(window::Settings {
-                size: iced::Size::new(WINDOW_W, WINDOW_H),
-                position,
-                decorations: false,
-                transparent: true,
-                level: window::Level::AlwaysOnTop,
-                exit_on_close_request: false,
-                ..Default::default()
+            if state.config.bookmarks
+                && let Some(slot) =
+                    (1..=9).find(|&slot| hotkeys::bookmark_hotkey(slot).map(|h| h.id()) == Some(id))
+            {
+                focus_bookmark(state, slot);
+                return Task::none();
+            }
+            if let Some(idx) = state
+                .config
+                .scratchpads
+                .iter()
+                .position(|pad| hotkeys::parse(&pad.hotkey).map(|h| h.id()) == Some(id))
+            {
+                toggle_scratchpad(state, idx);
+                return Task::none();
+            }
+            if matches(&state.config.hotkeys.back) {
+                navigate_history(state, Direction::Back);
+                return Task::none();
+            }
+            if matches(&state.config.hotkeys.forward) {
+                navigate_history(state, Direction::Forward);
+                return Task::none();
+            }
+            if matches(&state.config.hotkeys.privacy) {
+                return handle(state, Message::TogglePrivacy);
+            }
+            let search = state.config.saved_searches.iter().find(|search| {
+                search
+                    .hotkey
+                    .as_deref()
+                    .and_then(hotkeys::parse)
+                    .map(|h| h.id())
+                    == Some(id)
             });
-            state.picker_window = Some(id);
-
-            open_task.then(|id| {
-                Task::batch([
-                    window::gain_focus(id),
-                    iced::widget::operation::focus_next(),
-                ])
-            })
+            match search.map(|search| search.query.clone()) {
+                Some(query) if state.picker_window.is_some() => apply_query(state, query),
+                Some(query) => show_picker(state, Some(query)),
+                None => Task::none(),
+            }
         }
         Message::HidePicker => {
-            if let Some(id) = state.picker_window.take() {
-                state.query.clear();
-                state.selected = None;
-                crate::macos::hide_application();
-                window::close(id)
-            } else {
-                Task::none()
+            // Escape only cancels labeling
+            if state.labeling.take().is_some() {
+                return Task::none();
             }
+            // Same for the preview
+            if state.panel == Panel::QuickLook {
+                state.panel = Panel::Results;
+                return Task::none();
+            }
+            // Cancelled, which puts back whatever peeking raised
+            hide_picker(state)
         }
-        Message::QueryChanged(query) => {
-            state.query = query;
-            state.filtered_count = get_filtered_items(state).len();
-            state.selected = if state.filtered_count > 0 {
-                Some(0)
-            } else {
-                None
-            };
-            Task::none()
-        }
+        Message::QueryChanged(query) => apply_query(state, query),
         Message::SelectNext => {
             if state.filtered_count == 0 {
                 return Task::none();
             }
+            freeze_order(state);
             state.selected = Some(match state.selected {
                 Some(idx) => (idx + 1).min(state.filtered_count - 1),
                 None => 0,
             });
-            Task::none()
+            peek_selected(state);
+            Task::batch([scroll_to_selected(state), dwell(state)])
         }
         Message::SelectPrev => {
+            freeze_order(state);
             if state.filtered_count == 0 {
                 state.selected = None;
             } else {
@@ -139,235 +648,3381 @@ pub fn update(state: &mut Switcheroo, message: Message) -> Task<Message> {
                     _ => Some(0),
                 };
             }
-            Task::none()
+            peek_selected(state);
+            Task::batch([scroll_to_selected(state), dwell(state)])
         }
-        Message::Confirm => {
-            let items = get_filtered_items(state);
-            if let Some(idx) = state.selected
-                && let Some((_, app, window, _, _)) = items.get(idx)
-            {
-                let _ = window.focus(&app.app);
+        Message::PageDown | Message::PageUp => {
+            if state.filtered_count == 0 {
+                return Task::none();
             }
-            if let Some(id) = state.picker_window.take() {
-                state.query.clear();
-                state.selected = None;
-                crate::macos::hide_application();
-                window::close(id)
+            freeze_order(state);
+            let page = page_size(state);
+            let current = state.selected.unwrap_or(0) / page;
+            let target = if matches!(message, Message::PageDown) {
+                current + 1
             } else {
-                Task::none()
-            }
+                current.saturating_sub(1)
+            };
+            let first = (target * page).min(state.filtered_count - 1);
+            state.selected = Some(first);
+            peek_selected(state);
+            // Line the page up with the top of the list
+            let theme = &state.theme;
+            let y = item_row(state, first) as f32 * (theme.row_height + theme.row_spacing);
+            Task::batch([
+                iced::widget::operation::scroll_to(
+                    RESULTS_ID,
+                    scrollable::AbsoluteOffset { x: 0.0, y },
+                ),
+                dwell(state),
+            ])
         }
-        Message::WindowClosed(id) => {
-            if state.picker_window == Some(id) {
-                state.picker_window = None;
+        Message::Confirm | Message::ConfirmAlt => {
+            if let Some(labeling) = state.labeling.take() {
+                save_label(state, labeling);
+                return Task::none();
+            }
+            if let Some(invocation) = &mut state.invocation {
+                invocation.confirmed();
+            }
+            if let Some(&entry) = state.selected.and_then(|idx| state.clip_rows.get(idx))
+                && clip_query(state).is_some()
+            {
+                crate::macos::set_clipboard(&state.clipboard.entries()[entry]);
+                // Once the app we came from is back in front
+                let paste = Task::perform(
+                    tokio::time::sleep(std::time::Duration::from_millis(150)),
+                    |()| Message::Paste,
+                );
+                return Task::batch([hide_picker(state), paste]);
+            }
+            if let Some(command) = selected_command(state) {
+                return run_command(state, command);
             }
-            Task::none()
-        }
-        Message::NoOp => Task::none(),
-    }
-}
-
-pub fn view(state: &Switcheroo, _window_id: window::Id) -> Element<'_, Message> {
-    let items = get_filtered_items(state);
-
-    let search = text_input("Search windows...", &state.query)
-        .id(SEARCH_INPUT_ID)
-        .on_input(Message::QueryChanged)
-        .on_submit(Message::Confirm)
-        .padding([8, 6])
-        .size(15);
-
-    let mut result_rows: Vec<Element<'_, Message>> = Vec::new();
 
-    for (idx, (pid, app, window, _, indices)) in items.iter().enumerate() {
-        let is_selected = state.selected == Some(idx);
-        let indices_set: HashSet<usize> = indices.iter().map(|&i| i as usize).collect();
+            if let Some(row) = selected_source_row(state) {
+                let source = &state.sources[row.source];
+                if let Err(e) = source.activate(&source.items()[row.item]) {
+                    log!("[warn] {} source: {e:#}", source.id());
+                }
+                return hide_picker(state);
+            }
 
-        let normal_color = if is_selected {
-            color!(0xffffff)
-        } else {
-            color!(0xcccccc)
-        };
-        let highlight_color = if is_selected {
-            color!(0xffff96)
-        } else {
-            color!(0x64c8ff)
-        };
+            let alt = matches!(message, Message::ConfirmAlt).then_some(state.config.alt_action);
+            if let Some(wid) = selected_window_id(state)
+                && let Err(e) = focus_window_as(state, wid, alt)
+            {
+                log!("Failed to focus window {wid}: {e}");
+                return show_toast(state, format!("Couldn't focus window: {e}"), Some(wid));
+            }
 
-        // App icon
-        let icon_elem: Element<'_, Message> = if let Some(icon_data) = state.manager.get_icon(*pid)
-        {
-            image(image::Handle::from_rgba(
-                icon_data.width,
-                icon_data.height,
-                icon_data.rgba.clone(),
-            ))
-            .width(20)
-            .height(20)
-            .into()
-        } else {
-            iced::widget::Space::new().width(20).height(20).into()
-        };
+            if state.keep_open
+                && let Some(id) = state.picker_window
+            {
+                refresh_windows(state);
+                state.engine.tick(10);
+                sync_results(state);
 
-        // App name with highlighted spans
-        let mut app_name_spans: Vec<iced::widget::text::Span<'_>> = Vec::new();
-        for (i, ch) in app.name.chars().enumerate() {
-            let c = if indices_set.contains(&i) { highlight_color } else { normal_color };
-            app_name_spans.push(span(ch.to_string()).color(c));
+                // Focusing the target made it key, so pull the picker back in front
+                crate::macos::activate_application();
+                return window::gain_focus(id);
+            }
+            hide_picker(state)
         }
-
-        // Window title with highlighted spans (truncate to avoid multi-line rows)
-        let max_title_chars = 80;
-        let title_offset = app.name.len() + 1;
-        let mut title_spans: Vec<iced::widget::text::Span<'_>> = Vec::new();
-        let title_len = window.title.chars().count();
-        for (i, ch) in window.title.chars().take(max_title_chars).enumerate() {
-            let c = if indices_set.contains(&(i + title_offset)) { highlight_color } else { normal_color };
-            title_spans.push(span(ch.to_string()).color(c));
+        Message::FocusWindow(wid) => match focus_window(state, wid) {
+            Ok(()) => hide_picker(state),
+            Err(e) => {
+                log!("Failed to focus window {wid}: {e}");
+                show_toast(state, format!("Couldn't focus window: {e}"), Some(wid))
+            }
+        },
+        Message::TitleChanged(wid, title) => {
+            if !state.manager.set_title(wid, title) {
+                return Task::none();
+            }
+            // nucleo can't edit items, so the haystacks get rebuilt, at most once per batch
+            if state.titles_stale {
+                return Task::none();
+            }
+            state.titles_stale = true;
+            Task::perform(tokio::time::sleep(TITLE_BATCH), |()| Message::TitlesChanged)
         }
-        if title_len > max_title_chars {
-            title_spans.push(span("…").color(normal_color));
+        Message::TitlesChanged => {
+            if state.titles_stale {
+                state.titles_stale = false;
+                state.engine.set_windows(&state.manager);
+                state.scripts.forget_windows();
+                resync_keeping_selection(state);
+            }
+            Task::none()
         }
-
-        let row_content = row![
-            icon_elem,
-            container(rich_text(app_name_spans).size(13).wrapping(Wrapping::None)).width(150),
-            container(rich_text(title_spans).size(13).wrapping(Wrapping::None)).width(Length::Fill),
-        ]
-        .spacing(8)
-        .align_y(iced::Alignment::Center);
-
-        let bg_color = if is_selected {
-            color!(0x2d6de0)
-        } else {
-            iced::Color::TRANSPARENT
-        };
-
-        let row_container = container(row_content)
-            .padding([4, 8])
-            .width(Length::Fill)
-            .style(move |_: &Theme| container::Style {
-                background: Some(iced::Background::Color(bg_color)),
-                border: iced::Border {
-                    radius: 5.0.into(),
-                    ..Default::default()
-                },
-                ..Default::default()
-            });
-
-        result_rows.push(row_container.into());
-    }
-
-    let results = scrollable(column(result_rows).spacing(1)).height(Length::Fill);
-
-    let separator = container(iced::widget::Space::new().width(Length::Fill).height(0))
-        .width(Length::Fill)
-        .height(1)
-        .style(|_: &Theme| container::Style {
-            background: Some(iced::Background::Color(iced::Color {
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-                a: 0.08,
-            })),
-            ..Default::default()
-        });
-
-    let content = column![search, separator, results]
-        .spacing(8)
-        .padding([12, 14]);
-
-    let main_container = container(content)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .style(|_: &Theme| container::Style {
-            background: Some(iced::Background::Color(iced::Color {
-                r: 0.10,
-                g: 0.10,
-                b: 0.10,
-                a: 0.93,
-            })),
-            border: iced::Border {
-                radius: 12.0.into(),
-                ..Default::default()
-            },
-            ..Default::default()
-        });
-
-    center(main_container).into()
-}
-
-pub fn subscription(state: &Switcheroo) -> Subscription<Message> {
-    let mut subs = vec![
-        iced::time::every(iced::time::Duration::from_millis(16)).map(check_hotkey),
-        window::close_events().map(Message::WindowClosed),
-    ];
-
-    if state.picker_window.is_some() {
-        subs.push(iced::event::listen_with(
-            |event, status, _window| match event {
-                iced::Event::Keyboard(keyboard::Event::KeyPressed {
-                    key: Key::Named(Named::Escape),
-                    ..
-                }) => Some(Message::HidePicker),
-                iced::Event::Keyboard(keyboard::Event::KeyPressed {
-                    key: Key::Named(Named::ArrowDown),
-                    ..
-                }) if status == iced::event::Status::Ignored => Some(Message::SelectNext),
-                iced::Event::Keyboard(keyboard::Event::KeyPressed {
-                    key: Key::Named(Named::ArrowUp),
-                    ..
-                }) if status == iced::event::Status::Ignored => Some(Message::SelectPrev),
-                _ => None,
-            },
-        ));
-    }
-
-    Subscription::batch(subs)
-}
-
-fn check_hotkey(_instant: std::time::Instant) -> Message {
-    let receiver = GlobalHotKeyEvent::receiver();
-    match receiver.try_recv() {
-        Ok(event) if event.state() == HotKeyState::Released => Message::ShowPicker,
-        _ => Message::NoOp,
-    }
-}
-
-fn get_filtered_items(
-    state: &Switcheroo,
-) -> Vec<(i32, &windows::App, &windows::Window, u32, Vec<u32>)> {
-    let mut matcher = Matcher::new(Config::DEFAULT);
-    let mut items: Vec<(i32, &windows::App, &windows::Window, u32, Vec<u32>)> = Vec::new();
-
-    let app_map = state.manager.app_map();
-    if state.query.is_empty() {
-        for (pid, app) in app_map {
-            for win in &app.windows {
-                items.push((*pid, app, win, 0, vec![]));
+        Message::AnimationFrame(_) => {
+            let Some(animation) = state.animation else {
+                return Task::none();
+            };
+            crate::macos::set_window_alpha(f64::from(animation.shown()));
+            if !animation.done() {
+                return Task::none();
+            }
+            state.animation = None;
+            match animation.closing {
+                Some(id) => {
+                    // Unless it was opened again in the meantime
+                    if state.picker_window.is_none() {
+                        crate::macos::hide_application();
+                    }
+                    window::close(id)
+                }
+                None => Task::none(),
             }
         }
-    } else {
-        let needle = Utf32String::from(state.query.as_str());
-        for (pid, app) in app_map {
-            for win in &app.windows {
-                let search_text = format!("{} {}", app.name, win.title);
-                let haystack = Utf32String::from(search_text.as_str());
-                let mut indices = Vec::new();
-                if let Some(score) =
-                    matcher.fuzzy_indices(haystack.slice(..), needle.slice(..), &mut indices)
-                {
-                    items.push((*pid, app, win, score as u32, indices));
+        Message::RowHovered(wid) => take_snapshot(state, wid),
+        Message::Dwelled(wid) => {
+            if selected_window_id(state) != Some(wid) {
+                return Task::none();
+            }
+            state.dwelled = Some(wid);
+            take_snapshot(state, wid)
+        }
+        Message::SnapshotTaken(wid, snapshot) => {
+            // Snapshots are dropped with the picker
+            if state.picker_window.is_none() {
+                return Task::none();
+            }
+            match snapshot {
+                Some(handle) => {
+                    state
+                        .snapshots
+                        .insert(wid, (handle, std::time::Instant::now()));
                 }
+                None => log!("[warn] could not take a snapshot of window {wid}"),
             }
+            Task::none()
         }
-    }
-
-    items.sort_by(|a, b| {
-        b.3.cmp(&a.3)
-            .then_with(|| a.1.name.cmp(&b.1.name))
-            .then_with(|| a.2.title.cmp(&b.2.title))
-    });
-
-    items
+        Message::UniqueMatch(wid, since) => {
+            // Only if nothing else matched in the meantime, not even briefly
+            if state.unique_match == Some((wid, since)) && unique_match(state) == Some(wid) {
+                log!("auto-confirming the only match, window {wid}");
+                return handle(state, Message::Confirm);
+            }
+            Task::none()
+        }
+        Message::AppActivated(pid) => {
+            state.manager.app_activated(pid);
+            // The picker coming up activates switcheroo itself
+            if pid != std::process::id() as i32
+                && let Some(session) = Session::front(&state.manager, pid)
+            {
+                remember_session(state, session);
+            }
+            Task::none()
+        }
+        Message::AppTerminated(pid) => {
+            state.manager.app_terminated(pid);
+            Task::none()
+        }
+        Message::OpenUrl(url) => {
+            open_link(state, &url);
+            Task::none()
+        }
+        Message::CheckPermissions => {
+            let permissions = Permissions::current();
+            if permissions == state.permissions {
+                return Task::none();
+            }
+            log!(
+                "permissions changed: accessibility {}, screen recording {}",
+                permissions.accessibility,
+                permissions.screen_recording
+            );
+            state.permissions = permissions;
+            // Windows listed app-level only get their AX elements, titles show up
+            refresh_windows(state);
+            resync_keeping_selection(state);
+            Task::none()
+        }
+        Message::PickerOpened(id) => {
+            // AppKit, so here on the main thread rather than in the task that opened it
+            crate::macos::show_on_all_spaces();
+            // Before its first frame gets shown at full opacity
+            if let Some(animation) = state.animation {
+                crate::macos::set_window_alpha(f64::from(animation.shown()));
+            }
+            if let Some(invocation) = &mut state.invocation {
+                invocation.opened();
+            }
+            Task::batch([
+                window::set_mode(id, window::Mode::Windowed),
+                window::gain_focus(id),
+                iced::widget::operation::focus_next(),
+                iced::widget::operation::select_all(SEARCH_INPUT_ID),
+            ])
+        }
+        Message::PickerShown => {
+            if let Some(invocation) = &mut state.invocation {
+                invocation.shown(&mut state.metrics);
+            }
+            Task::none()
+        }
+        Message::Rescaled(scale) => {
+            if rescale(state, f64::from(scale)) {
+                return Task::done(Message::Revalidate);
+            }
+            Task::none()
+        }
+        Message::ShowPermissions => {
+            state.panel = Panel::Permissions;
+            Task::none()
+        }
+        Message::OpenPrivacySettings(pane) => {
+            crate::macos::open_privacy_settings(pane);
+            Task::none()
+        }
+        Message::DismissToast(shown_at) => {
+            if state.toast.as_ref().is_some_and(|t| t.shown_at == shown_at) {
+                state.toast = None;
+            }
+            Task::none()
+        }
+        Message::RevealInFinder => {
+            if let Some(wid) = selected_window_id(state)
+                && let Some((app, window)) = state.manager.window(wid)
+                && let Err(e) = window.reveal_in_finder(&app.app)
+            {
+                log!("Failed to reveal in Finder: {e}");
+            }
+            hide_picker(state)
+        }
+        Message::CopyMetadata(field) => {
+            if let Some(wid) = selected_window_id(state)
+                && let Some((app, window)) = state.manager.window(wid)
+            {
+                let text = match field {
+                    CopyField::Title => window.title.clone(),
+                    CopyField::AppName => app.name.clone(),
+                    CopyField::BundleId => app.bundle_id.clone().unwrap_or_default(),
+                    CopyField::WindowId => window.id.to_string(),
+                    CopyField::DeepLink => Link::to_window(app, window).url(),
+                };
+                crate::macos::set_clipboard(&text);
+            }
+            hide_picker(state)
+        }
+        Message::ToggleHelp => {
+            state.panel = if state.panel == Panel::Help {
+                Panel::Results
+            } else {
+                Panel::Help
+            };
+            Task::none()
+        }
+        Message::ToggleChip(n) => {
+            match usize::from(n).checked_sub(1).map(|idx| Chip::ALL.get(idx)) {
+                None => state.chips.clear(),
+                Some(Some(chip)) => {
+                    if !state.chips.remove(chip) {
+                        state.chips.insert(*chip);
+                    }
+                }
+                Some(None) => return Task::none(),
+            }
+            state.frozen_order = None;
+            update_results(state);
+            state.selected = if state.filtered_count > 0 {
+                Some(0)
+            } else {
+                None
+            };
+            Task::none()
+        }
+        Message::EditLabel => {
+            let Some((app, window)) =
+                selected_window_id(state).and_then(|wid| state.manager.window(wid))
+            else {
+                return Task::none();
+            };
+            state.labeling = Some(Labeling {
+                wid: window.id,
+                draft: state
+                    .labels
+                    .get(app, window)
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+            iced::widget::operation::select_all(SEARCH_INPUT_ID)
+        }
+        Message::LabelChanged(draft) => {
+            if let Some(labeling) = &mut state.labeling {
+                labeling.draft = draft;
+            }
+            Task::none()
+        }
+        Message::SpacePressed => {
+            // Not for a pasted space, there's no key press for that
+            if std::mem::take(&mut state.held_space) {
+                toggle_quick_look(state);
+            }
+            Task::none()
+        }
+        Message::ToggleQuickLook => {
+            toggle_quick_look(state);
+            Task::none()
+        }
+        Message::SetPickerHotkey(combo) => {
+            if set_picker_hotkey(state, &combo) {
+                state.panel = Panel::Results;
+            }
+            Task::none()
+        }
+        Message::PollModifierTap => {
+            if let Some(Picker::Tap(modifiers)) = state.picker
+                && state.modifier_tap.poll(modifiers)
+            {
+                return show_picker(state, None);
+            }
+            Task::none()
+        }
+        Message::Scrolled(delta) => {
+            let (x, y) = match delta {
+                iced::mouse::ScrollDelta::Lines { x, y } => (x * SCROLL_STEP, y * SCROLL_STEP),
+                iced::mouse::ScrollDelta::Pixels { x, y } => (x, y),
+            };
+            state.scrolled.0 += x;
+            state.scrolled.1 += y;
+
+            if state.scrolled.0.abs() >= SWIPE_DISTANCE {
+                let forward = state.scrolled.0 < 0.0;
+                state.scrolled = (0.0, 0.0);
+                return cycle_saved_search(state, forward);
+            }
+
+            let steps = (state.scrolled.1 / SCROLL_STEP) as i32;
+            if steps == 0 || state.filtered_count == 0 {
+                return Task::none();
+            }
+            state.scrolled.1 -= steps as f32 * SCROLL_STEP;
+            freeze_order(state);
+            // Scrolling up (positive) goes towards the top of the list
+            let last = state.filtered_count as i32 - 1;
+            let idx = (state.selected.unwrap_or(0) as i32 - steps).clamp(0, last);
+            state.selected = Some(idx as usize);
+            peek_selected(state);
+            scroll_to_selected(state)
+        }
+        Message::ResultsScrolled(viewport) => {
+            state.results_viewport = (viewport.absolute_offset().y, viewport.bounds().height);
+            load_source_icons(state);
+            Task::none()
+        }
+        Message::IconAllocated(allocation) => {
+            if state.picker_window.is_some() {
+                state.prewarmed.extend(allocation);
+            }
+            Task::none()
+        }
+        Message::Revalidate => {
+            if state.picker_window.is_none() {
+                return Task::none();
+            }
+            // Reading the window server can take a while, the UI keeps going meanwhile
+            let private_apis = state.manager.private_apis();
+            Task::perform(
+                tokio::task::spawn_blocking(move || windows::Snapshot::take(private_apis)),
+                |snapshot| Message::Revalidated(snapshot.ok().map(Arc::new)),
+            )
+        }
+        Message::Revalidated(snapshot) => {
+            if state.picker_window.is_none() {
+                return Task::none();
+            }
+            match snapshot {
+                Some(snapshot) => refresh_windows_from(state, &snapshot),
+                None => log!("[warn] couldn't read the window list"),
+            }
+            if state
+                .sources_refreshed
+                .is_none_or(|at| at.elapsed() > SOURCES_TTL)
+            {
+                refresh_sources(state);
+            }
+            state.peek_origin = state.manager.front_window();
+            resync_keeping_selection(state);
+            scroll_to_selected(state)
+        }
+        Message::Reload => {
+            if state.reloading {
+                return Task::none();
+            }
+            state.reloading = true;
+            // Like opening, give the indicator a frame to show before the UI blocks
+            Task::perform(
+                tokio::time::sleep(std::time::Duration::from_millis(16)),
+                |()| Message::ReloadNow,
+            )
+        }
+        Message::ReloadNow => {
+            state.reloading = false;
+            if state.picker_window.is_none() {
+                return Task::none();
+            }
+            state.manager.forget_ax_elements();
+            refresh_windows(state);
+            refresh_sources(state);
+            resync_keeping_selection(state);
+            scroll_to_selected(state)
+        }
+        Message::RefreshTick => {
+            // Typing or a reload under way would only get held up by it
+            if state.picker_window.is_none() || state.reloading || state.engine.running() {
+                return Task::none();
+            }
+            refresh_windows(state);
+            resync_keeping_selection(state);
+            Task::none()
+        }
+        Message::MatcherTick => {
+            let selected = state
+                .keep_selection
+                .then(|| selected_window_id(state))
+                .flatten();
+            state.engine.tick(0);
+            sync_results(state);
+            reselect(state, selected);
+            state.keep_selection &= state.engine.running();
+            watch_unique_match(state)
+        }
+        Message::PollClipboard => {
+            state.clipboard.poll(state.config.clipboard_history);
+            Task::none()
+        }
+        Message::Paste => {
+            crate::macos::paste();
+            Task::none()
+        }
+        Message::PollHotCorner => {
+            if let Some(corner) = &state.config.hot_corner
+                && state.hot_corner.poll(corner)
+            {
+                return show_picker(state, None);
+            }
+            Task::none()
+        }
+        Message::CheckConfig => {
+            check_config(state);
+            Task::none()
+        }
+        Message::ToggleDebugScores => {
+            state.config.debug_scores = !state.config.debug_scores;
+            Task::none()
+        }
+        Message::TogglePrivacy => {
+            // Not in the config, so a reload doesn't turn it back off
+            state.privacy = !state.privacy;
+            let mode = match (state.privacy, state.config.privacy) {
+                (true, _) => "on",
+                (false, true) => "still on, privacy is set in the config",
+                (false, false) => "off",
+            };
+            log!("privacy mode {mode}");
+            Task::none()
+        }
+        Message::ToggleKeepOpen => {
+            state.keep_open = !state.keep_open;
+            Task::none()
+        }
+        Message::SetBookmark(slot) => {
+            if let Some(wid) = selected_window_id(state)
+                && let Some((app, window)) = state.manager.window(wid)
+            {
+                state.bookmarks.set(slot, app, window);
+                if let Err(e) = state.bookmarks.save() {
+                    log!("[warn] could not save bookmarks: {e:#}");
+                }
+            }
+            Task::none()
+        }
+        Message::SavedSearchKey(key) => {
+            match state
+                .config
+                .saved_searches
+                .iter()
+                .find(|search| search.key.as_deref() == Some(key.as_str()))
+            {
+                Some(search) => {
+                    let query = search.query.clone();
+                    Task::batch([
+                        apply_query(state, query),
+                        iced::widget::operation::move_cursor_to_end(SEARCH_INPUT_ID),
+                    ])
+                }
+                None => Task::none(),
+            }
+        }
+        Message::RestoreWorkspace(name) => {
+            // Second pass for apps launched by the first one
+            refresh_windows(state);
+            if let Err(e) = state.workspaces.restore(&name, &state.manager, false) {
+                log!("Failed to restore workspace: {e}");
+            }
+            Task::none()
+        }
+        Message::RestoreFocus(attempt) => restore_focus(state, attempt),
+        Message::PickerUnfocused => {
+            unpeek(state);
+            Task::none()
+        }
+        Message::WindowClosed(id) => {
+            if state.picker_window == Some(id) {
+                state.picker_window = None;
+            }
+            Task::none()
+        }
+    }
+}
+
+fn show_picker(state: &mut Switcheroo, query: Option<String>) -> Task<Message> {
+    if state.picker_window.is_some() {
+        return Task::none();
+    }
+    state.screen_shared =
+        state.config.while_sharing != WhileSharing::Show && crate::macos::screen_shared();
+    if state.screen_shared && state.config.while_sharing == WhileSharing::Hide {
+        log!("not opening the picker, the screen is shared");
+        return Task::none();
+    }
+    // Still fading out the last one
+    let closing = match state
+        .animation
+        .take()
+        .and_then(|animation| animation.closing)
+    {
+        Some(id) => window::close(id),
+        None => Task::none(),
+    };
+
+    let window_h = state.theme.height;
+
+    let position = match crate::macos::active_display_frame_at_cursor() {
+        Some((sx, sy, sw, sh)) => window::Position::Specific(iced::Point::new(
+            sx + (sw - PICKER_WIDTH) / 2.0,
+            sy + (sh - window_h) / 2.0,
+        )),
+        None => window::Position::Centered,
+    };
+
+    crate::macos::activate_application();
+
+    // The config file is only watched while the picker is open, catch up on edits made since
+    check_config(state);
+    let palette = Palette::current();
+    if palette != state.palette {
+        // Tinted icons are drawn in the highlight color
+        state.icons.clear();
+        state.palette = palette;
+        update_icons(state);
+    }
+    // The refresh in `Revalidate` picks up whatever was granted since
+    state.permissions = Permissions::current();
+    if state.config.sort == Sort::Dock {
+        state.dock_order = crate::macos::dock_apps();
+    }
+    // Icons come back with the refresh in `Revalidate`
+    rescale(state, crate::macos::backing_scale_at_cursor());
+    // Opens on the windows from the last refresh, the fresh list follows in `Revalidate`
+    match query {
+        Some(query) => state.query = query,
+        None if !state.config.preserve_query => state.query.clear(),
+        None => {}
+    }
+    state.panel = Panel::Results;
+    match_query(state);
+    update_results(state);
+    state.selected = if state.filtered_count > 0 {
+        Some(0)
+    } else {
+        None
+    };
+    state.peek_origin = state.manager.front_window();
+    state.shown_at = Some(std::time::Instant::now());
+    state.invocation = Some(Invocation::new());
+    if let Some(hook) = &state.config.hooks.on_show {
+        let front = state.peek_origin.and_then(|wid| state.manager.window(wid));
+        crate::hooks::run(hook, front);
+    }
+
+    let (id, open_task) = window::open(window::Settings {
+        size: iced::Size::new(PICKER_WIDTH, window_h),
+        position,
+        decorations: false,
+        transparent: true,
+        level: window::Level::AlwaysOnTop,
+        // Shown once it's been told to join whatever space is active
+        visible: false,
+        exit_on_close_request: false,
+        ..Default::default()
+    });
+    state.picker_window = Some(id);
+    state.animation = animation_duration(state).map(|duration| Animation {
+        started: std::time::Instant::now(),
+        duration,
+        closing: None,
+    });
+
+    let open_task = open_task.map(Message::PickerOpened);
+    // A frame later, so the cached list gets painted before refreshing holds up the UI
+    let revalidate = Task::perform(
+        tokio::time::sleep(std::time::Duration::from_millis(16)),
+        |()| Message::Revalidate,
+    );
+    Task::batch([closing, open_task, prewarm_icons(state), revalidate])
+}
+
+/// None when the picker should just appear and disappear.
+fn animation_duration(state: &Switcheroo) -> Option<std::time::Duration> {
+    (state.config.animation_ms > 0 && !crate::macos::reduce_motion())
+        .then(|| std::time::Duration::from_millis(state.config.animation_ms))
+}
+
+/// Uploads the icons of the most recently used apps up front, so the first frame of the
+/// picker doesn't have to upload a dozen at once.
+fn prewarm_icons(state: &Switcheroo) -> Task<Message> {
+    let mut pids = Vec::new();
+    for (app, _) in state.manager.recent_windows(usize::MAX) {
+        if !pids.contains(&app.pid) {
+            pids.push(app.pid);
+        }
+        if pids.len() == PREWARM_ICONS {
+            break;
+        }
+    }
+    Task::batch(
+        pids.into_iter()
+            .filter_map(|pid| state.icons.get(&(pid, false)).cloned())
+            .map(|handle| {
+                image::allocate(handle).map(|result| Message::IconAllocated(result.ok()))
+            }),
+    )
+}
+
+fn apply_query(state: &mut Switcheroo, query: String) -> Task<Message> {
+    // `?` typed into an empty search opens the help instead of searching for it
+    if query == "?" {
+        state.panel = Panel::Help;
+        return Task::none();
+    }
+    // Same for Space, which previews the selected window (and closes the preview again). The
+    // search field hands the space over before the key press comes in, see `SpacePressed`
+    state.held_space =
+        query == " " || (state.panel == Panel::QuickLook && query == format!("{} ", state.query));
+    if state.held_space {
+        return Task::none();
+    }
+    if query != state.query {
+        state.frozen_order = None;
+    }
+    state.query = query;
+    // Results for this query start from the top, whatever was being held on to
+    state.keep_selection = false;
+    state.panel = Panel::Results;
+    let start = std::time::Instant::now();
+    match_query(state);
+    update_results(state);
+    state.metrics.record(Metric::Match, start.elapsed());
+    state.selected = if state.filtered_count > 0 {
+        Some(0)
+    } else {
+        None
+    };
+    watch_unique_match(state)
+}
+
+/// The window that's the only row for a typed query, with nothing still being matched.
+fn unique_match(state: &Switcheroo) -> Option<u32> {
+    let searching = !state.query.trim().is_empty()
+        && commands::command_query(&state.query).is_none()
+        && state.panel == Panel::Results
+        && state.labeling.is_none();
+    if !searching || state.engine.running() || state.filtered_count != 1 {
+        return None;
+    }
+    state.items.first().map(|item| item.wid)
+}
+
+/// Starts the `auto_confirm_ms` countdown when a single window is left, or calls it off.
+fn watch_unique_match(state: &mut Switcheroo) -> Task<Message> {
+    if state.config.auto_confirm_ms == 0 {
+        return Task::none();
+    }
+    let Some(wid) = unique_match(state) else {
+        // Still matching keeps the countdown going, the results aren't in yet
+        if !state.engine.running() {
+            state.unique_match = None;
+        }
+        return Task::none();
+    };
+    if state.unique_match.is_some_and(|(unique, _)| unique == wid) {
+        return Task::none();
+    }
+    let since = std::time::Instant::now();
+    state.unique_match = Some((wid, since));
+    let delay = std::time::Duration::from_millis(state.config.auto_confirm_ms);
+    Task::perform(tokio::time::sleep(delay), move |()| Message::UniqueMatch(wid, since))
+}
+
+/// Opens the Quick Look panel on the selected window, or closes it.
+fn toggle_quick_look(state: &mut Switcheroo) {
+    if state.panel == Panel::QuickLook {
+        state.panel = Panel::Results;
+        return;
+    }
+    let Some(wid) = selected_window_id(state).filter(|_| state.panel == Panel::Results) else {
+        return;
+    };
+    if titles_hidden(state) {
+        return;
+    }
+    match crate::macos::window_snapshot(wid, PREVIEW_WIDTH, state.scale) {
+        Some(snapshot) => {
+            let handle = image::Handle::from_rgba(snapshot.width, snapshot.height, snapshot.rgba);
+            state.quick_look = Some((wid, handle));
+            state.panel = Panel::QuickLook;
+        }
+        None => log!("[warn] could not take a snapshot of window {wid}"),
+    }
+}
+
+/// Drops icons and snapshots rendered for another backing scale, e.g. when the picker opens
+/// on or gets moved to another display. True if the icons need the next `Revalidate`.
+fn rescale(state: &mut Switcheroo, scale: f64) -> bool {
+    if scale == state.scale {
+        return false;
+    }
+    log!("backing scale {} -> {scale}", state.scale);
+    state.scale = scale;
+    state.theme = state.config.theme.snapped(scale);
+    state.snapshots.clear();
+    let icons = state.manager.set_backing_scale(scale);
+    if icons {
+        state.icons.clear();
+    }
+    icons
+}
+
+/// Re-reads the window list and hands it to the matcher.
+fn refresh_windows(state: &mut Switcheroo) {
+    let start = std::time::Instant::now();
+    let result = state.manager.refresh();
+    refreshed(state, result, start);
+}
+
+/// [`refresh_windows`] from a snapshot taken off the main thread.
+fn refresh_windows_from(state: &mut Switcheroo, snapshot: &windows::Snapshot) {
+    let start = std::time::Instant::now();
+    let result = state.manager.apply(snapshot);
+    refreshed(state, result, start);
+}
+
+fn refreshed(state: &mut Switcheroo, result: anyhow::Result<()>, start: std::time::Instant) {
+    match result {
+        Ok(()) => state.metrics.record(Metric::Refresh, start.elapsed()),
+        Err(e) => log!("Failed to refresh windows: {e}"),
+    }
+    state.engine.set_windows(&state.manager);
+    state.scripts.forget_windows();
+    update_icons(state);
+    if state.picker_window.is_some() {
+        state.title_watch = Some(state.manager.watch_titles());
+    }
+}
+
+/// Builds handles for icons that don't have one yet and drops those of apps that are gone.
+/// They stay PNG until iced decodes them for their first frame, grayscale and tinted ones too
+/// once converted.
+fn update_icons(state: &mut Switcheroo) {
+    let apps = state.manager.app_map();
+    state.icons.retain(|(pid, _), _| apps.contains_key(pid));
+    for &pid in apps.keys() {
+        if state.icons.contains_key(&(pid, false)) {
+            continue;
+        }
+        let Some(png) = state.manager.get_icon(pid) else {
+            continue;
+        };
+        let tinted = |tint: Option<iced::Color>| {
+            let icon = crate::macos::IconData::from_png(png)?;
+            let converted = crate::macos::IconData {
+                rgba: monochrome(&icon.rgba, tint),
+                ..icon
+            };
+            converted.to_png().map(image::Handle::from_bytes)
+        };
+        let (normal, selected) = match state.config.icon_style {
+            IconStyle::Color => {
+                let handle = image::Handle::from_bytes(png.to_vec());
+                (handle.clone(), handle)
+            }
+            IconStyle::Grayscale => {
+                let Some(handle) = tinted(None) else {
+                    continue;
+                };
+                (handle.clone(), handle)
+            }
+            IconStyle::Tinted => {
+                let (Some(normal), Some(selected)) = (
+                    tinted(Some(state.palette.highlight)),
+                    tinted(Some(state.palette.highlight_selected)),
+                ) else {
+                    continue;
+                };
+                (normal, selected)
+            }
+        };
+        state.icons.insert((pid, false), normal);
+        state.icons.insert((pid, true), selected);
+    }
+}
+
+fn refresh_sources(state: &mut Switcheroo) {
+    state.sources_refreshed = Some(std::time::Instant::now());
+    for source in &mut state.sources {
+        source.refresh();
+    }
+}
+
+/// Draws the icons of source rows in view, the others wait until they're scrolled to.
+fn load_source_icons(state: &mut Switcheroo) {
+    for row in shown_rows(state) {
+        let Some(source_row) = row_item(state, row)
+            .and_then(|idx| idx.checked_sub(state.items.len()))
+            .and_then(|i| state.source_rows.get(i))
+        else {
+            continue;
+        };
+        let item = &state.sources[source_row.source].items()[source_row.item];
+        let Some(path) = &item.icon else {
+            continue;
+        };
+        if !state.source_icons.contains_key(path) {
+            let handle = crate::macos::file_icon(path, SOURCE_ICON_PIXELS)
+                .map(|icon| image::Handle::from_rgba(icon.width, icon.height, icon.rgba));
+            state.source_icons.insert(path.clone(), handle);
+        }
+    }
+}
+
+/// Hands the query's free text to the matcher, giving it a moment so results usually
+/// show up in the same frame. Anything slower arrives through [`Message::MatcherTick`].
+fn match_query(state: &mut Switcheroo) {
+    if commands::command_query(&state.query).is_none() {
+        state.engine.set_pattern(&Query::parse(&state.query).text);
+    }
+    state.engine.tick(10);
+}
+
+/// Picks up the matcher's latest results. A selection on the top row stays there as better
+/// matches come in, otherwise it follows the selected window to wherever it ended up.
+fn sync_results(state: &mut Switcheroo) {
+    let selected = selected_window_id(state).filter(|_| state.selected != Some(0));
+    update_results(state);
+    let count = state.filtered_count;
+    let moved = selected.and_then(|wid| state.items.iter().position(|item| item.wid == wid));
+    state.selected = match state.selected {
+        _ if count == 0 => None,
+        _ if moved.is_some() => moved,
+        Some(idx) => Some(idx.min(count - 1)),
+        None => Some(0),
+    };
+}
+
+/// Pins the rows to their current order, see `stable_order`.
+fn freeze_order(state: &mut Switcheroo) {
+    if state.config.stable_order && state.frozen_order.is_none() {
+        let order = state
+            .items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| (item.wid, idx));
+        state.frozen_order = Some(order.collect());
+    }
+}
+
+/// Re-lists the rows after windows changed underneath the user (a refresh, a new title),
+/// keeping the selection on its window, first row included: a new window showing up right
+/// as Enter is pressed mustn't be what gets focused. Matches still coming in after this keep
+/// it there too, until the user types.
+fn resync_keeping_selection(state: &mut Switcheroo) {
+    let selected = selected_window_id(state);
+    state.engine.tick(10);
+    sync_results(state);
+    reselect(state, selected);
+    state.keep_selection = state.engine.running();
+}
+
+/// Puts the selection back on `wid` wherever it ended up, even off the first row (which
+/// [`sync_results`] leaves alone).
+fn reselect(state: &mut Switcheroo, wid: Option<u32>) {
+    if let Some(idx) = wid.and_then(|wid| state.items.iter().position(|item| item.wid == wid)) {
+        state.selected = Some(idx);
+    }
+}
+
+fn selected_command(state: &Switcheroo) -> Option<Command> {
+    let query = commands::command_query(&state.query)?;
+    commands::filter(query, state.manager.private_apis())
+        .get(state.selected?)
+        .copied()
+}
+
+/// The search text after `> clip`, while in clipboard history mode.
+fn clip_query(state: &Switcheroo) -> Option<&str> {
+    let (command, text) = commands::split(commands::command_query(&state.query)?);
+    (command == Command::Clip.name()).then_some(text)
+}
+
+fn selected_source_row(state: &Switcheroo) -> Option<&SourceRow> {
+    let idx = state.selected?.checked_sub(state.items.len())?;
+    state.source_rows.get(idx)
+}
+
+fn selected_window_id(state: &Switcheroo) -> Option<u32> {
+    state.items.get(state.selected?).map(|item| item.wid)
+}
+
+/// Shows the selected window's snapshot if the selection is still on it after [`DWELL`].
+fn dwell(state: &mut Switcheroo) -> Task<Message> {
+    state.dwelled = None;
+    match selected_window_id(state).filter(|_| state.config.window_snapshots) {
+        Some(wid) => Task::perform(tokio::time::sleep(DWELL), move |()| Message::Dwelled(wid)),
+        None => Task::none(),
+    }
+}
+
+/// Captures the window off the main thread unless there's a recent enough snapshot of it
+/// already, see [`Message::SnapshotTaken`].
+fn take_snapshot(state: &Switcheroo, wid: u32) -> Task<Message> {
+    if !state.config.window_snapshots
+        || state
+            .snapshots
+            .get(&wid)
+            .is_some_and(|(_, taken)| taken.elapsed() < SNAPSHOT_TTL)
+    {
+        return Task::none();
+    }
+    let scale = state.scale;
+    Task::perform(
+        tokio::task::spawn_blocking(move || {
+            crate::macos::window_snapshot(wid, SNAPSHOT_WIDTH, scale)
+        }),
+        move |snapshot| {
+            let handle = snapshot.ok().flatten().map(|snapshot| {
+                image::Handle::from_rgba(snapshot.width, snapshot.height, snapshot.rgba)
+            });
+            Message::SnapshotTaken(wid, handle)
+        },
+    )
+}
+
+/// Focuses a listed window and records the switch in the usage stats.
+fn focus_window(state: &mut Switcheroo, wid: u32) -> anyhow::Result<()> {
+    focus_window_as(state, wid, None)
+}
+
+/// [`focus_window`], the `alt` way if given.
+fn focus_window_as(state: &mut Switcheroo, wid: u32, alt: Option<AltAction>) -> anyhow::Result<()> {
+    // Peeking raised it already, anything else it raised goes back first
+    if state.peeked == Some(wid) {
+        state.peeked = None;
+    } else {
+        unpeek(state);
+    }
+    let (app, window) = state
+        .manager
+        .window(wid)
+        .ok_or_else(|| anyhow::anyhow!("Window {wid} is gone"))?;
+    let start = std::time::Instant::now();
+    match alt {
+        None => window.focus(&app.app)?,
+        Some(AltAction::BringHere) => window.bring_here(&app.app)?,
+        Some(AltAction::FocusWithoutMouseWarp) => window.focus_in_place(&app.app)?,
+        Some(AltAction::FocusAndMaximize) => window.focus_and_maximize(&app.app)?,
+    }
+    state.history.push(state.manager.front_window(), wid);
+    focused(state, wid, start);
+    Ok(())
+}
+
+/// Everything that follows focusing a window but the history, which going back and forward
+/// through it mustn't add to.
+fn focused(state: &mut Switcheroo, wid: u32, start: std::time::Instant) {
+    state.metrics.record(Metric::Focus, start.elapsed());
+    if let Some(invocation) = &mut state.invocation {
+        invocation.switched(&mut state.metrics);
+    }
+    let Some((app, window)) = state.manager.window(wid) else {
+        return;
+    };
+    state.stats.record_switch(app, window);
+    let session = Session::of(app, window);
+    state.manager.mark_active(wid);
+    if let Some(hook) = &state.config.hooks.on_focus
+        && let Some(target) = state.manager.window(wid)
+    {
+        crate::hooks::run(hook, Some(target));
+    }
+    remember_session(state, session);
+}
+
+/// Saves the window in front for `restore_focus`, whenever it's another one than last time.
+fn remember_session(state: &mut Switcheroo, session: Session) {
+    if !state.config.restore_focus || session == state.session {
+        return;
+    }
+    if let Err(e) = session.save() {
+        log!("[warn] could not save the focused window: {e:#}");
+    }
+    state.session = session;
+}
+
+/// Does what a script action asked for, in order, returning the first thing that failed.
+fn apply_script_ops(state: &mut Switcheroo, ops: Vec<Op>) -> anyhow::Result<()> {
+    let mut failed = None;
+    for op in ops {
+        let result = match op {
+            Op::Focus(wid) => focus_window(state, wid),
+            Op::Move(wid, frame) => state
+                .manager
+                .window(wid)
+                .ok_or_else(|| anyhow::anyhow!("Window {wid} is gone"))
+                .and_then(|(_, window)| window.set_frame(frame)),
+            Op::Close(wid) => state
+                .manager
+                .window(wid)
+                .ok_or_else(|| anyhow::anyhow!("Window {wid} is gone"))
+                .and_then(|(_, window)| window.close()),
+        };
+        if let Err(e) = result {
+            log!("[warn] script {op:?} failed: {e:#}");
+            failed.get_or_insert(e.context(format!("Script {op:?} failed")));
+        }
+    }
+    failed.map_or(Ok(()), Err)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Back,
+    Forward,
+}
+
+fn focus_bookmark(state: &mut Switcheroo, slot: u8) {
+    refresh_windows(state);
+    let Some(wid) = state.bookmarks.resolve(slot, &state.manager) else {
+        log!("[warn] bookmark {slot} doesn't match any open window");
+        return;
+    };
+    if let Err(e) = focus_window(state, wid) {
+        log!("Failed to focus window {wid}: {e}");
+    }
+}
+
+fn save_label(state: &mut Switcheroo, labeling: Labeling) {
+    let Some((app, window)) = state.manager.window(labeling.wid) else {
+        return;
+    };
+    state.labels.set(app, window, labeling.draft.trim());
+    if let Err(e) = state.labels.save() {
+        log!("[warn] could not save labels: {e:#}");
+    }
+    state.engine.set_labels(&state.labels);
+    state.engine.set_windows(&state.manager);
+    resync_keeping_selection(state);
+}
+
+/// How often the window from before logout is looked for, apps opened at login take a while
+/// to bring their windows back.
+const RESTORE_ATTEMPTS: u8 = 5;
+const RESTORE_RETRY: std::time::Duration = std::time::Duration::from_secs(2);
+
+fn restore_focus(state: &mut Switcheroo, attempt: u8) -> Task<Message> {
+    let session = Session::load();
+    let Some(app) = session.app.as_deref() else {
+        return Task::none();
+    };
+    refresh_windows(state);
+    let found = session.resolve(&state.manager);
+    if found.is_some() || attempt + 1 >= RESTORE_ATTEMPTS {
+        // Just once, not again if switcheroo gets restarted later on
+        if let Err(e) = Session::default().save() {
+            log!("[warn] could not clear the focused window: {e:#}");
+        }
+    }
+    let Some(wid) = found else {
+        if attempt + 1 >= RESTORE_ATTEMPTS {
+            log!("[warn] gave up refocusing {app}, none of its windows showed up");
+            return Task::none();
+        }
+        let running = state.manager.app_map().values().any(|a| app_key(a) == app);
+        if attempt == 0 && !running {
+            crate::macos::launch_app(app);
+        }
+        return Task::perform(tokio::time::sleep(RESTORE_RETRY), move |()| {
+            Message::RestoreFocus(attempt + 1)
+        });
+    };
+    if let Err(e) = focus_window(state, wid) {
+        log!("Failed to focus window {wid}: {e}");
+    }
+    Task::none()
+}
+
+fn open_link(state: &mut Switcheroo, url: &str) {
+    let link = match Link::parse(url) {
+        Ok(link) => link,
+        Err(e) => {
+            log!("[warn] {e:#}");
+            return;
+        }
+    };
+    refresh_windows(state);
+    let Some(wid) = link.resolve(&state.manager) else {
+        log!("[warn] {url} doesn't match any open window");
+        return;
+    };
+    if let Err(e) = focus_window(state, wid) {
+        log!("Failed to focus window {wid}: {e}");
+    }
+}
+
+fn toggle_scratchpad(state: &mut Switcheroo, idx: usize) {
+    refresh_windows(state);
+    let pad = &state.config.scratchpads[idx];
+    let target = state.manager.app_map().values().find_map(|app| {
+        if app.bundle_id.as_deref() != Some(pad.app.as_str()) && app.name != pad.app {
+            return None;
+        }
+        let window = app.windows.iter().find(|w| {
+            pad.title
+                .as_deref()
+                .is_none_or(|title| w.title.contains(title))
+        })?;
+        Some((app, window))
+    });
+
+    let Some((app, window)) = target else {
+        log!("[warn] scratchpad {:?} has no matching window", pad.app);
+        return;
+    };
+    if state.manager.front_window() == Some(window.id) {
+        app.app.hide();
+    } else if let Err(e) = window.bring_here(&app.app) {
+        log!("Failed to bring scratchpad {:?} here: {e}", pad.app);
+    }
+}
+
+fn navigate_history(state: &mut Switcheroo, direction: Direction) {
+    refresh_windows(state);
+    let manager = &state.manager;
+    let exists = |wid| manager.window(wid).is_some();
+    let target = match direction {
+        Direction::Back => state.history.back(exists),
+        Direction::Forward => state.history.forward(exists),
+    };
+
+    if let Some(wid) = target
+        && let Some((app, window)) = state.manager.window(wid)
+    {
+        let start = std::time::Instant::now();
+        match window.focus(&app.app) {
+            Ok(()) => focused(state, wid, start),
+            Err(e) => log!("Failed to focus window {wid}: {e}"),
+        }
+    }
+}
+
+fn run_command(state: &mut Switcheroo, command: Command) -> Task<Message> {
+    if command.private() && !state.manager.private_apis() {
+        log!("[warn] {} needs private_apis", command.name());
+        return hide_picker(state);
+    }
+    match command {
+        Command::Back => {
+            navigate_history(state, Direction::Back);
+            hide_picker(state)
+        }
+        Command::Forward => {
+            navigate_history(state, Direction::Forward);
+            hide_picker(state)
+        }
+        Command::Stats => {
+            state.panel = Panel::Stats;
+            Task::none()
+        }
+        Command::Snapshot => {
+            let name = workspace_name(state);
+            state.workspaces.snapshot(&name, &state.manager);
+            if let Err(e) = state.workspaces.save() {
+                log!("[warn] could not save workspaces: {e:#}");
+            }
+            hide_picker(state)
+        }
+        Command::Restore => {
+            let name = workspace_name(state);
+            let relaunched = match state.workspaces.restore(&name, &state.manager, true) {
+                Ok(launched) => launched.then(|| {
+                    Task::perform(
+                        tokio::time::sleep(std::time::Duration::from_secs(3)),
+                        move |()| Message::RestoreWorkspace(name),
+                    )
+                }),
+                Err(e) => {
+                    log!("Failed to restore workspace: {e}");
+                    None
+                }
+            };
+            Task::batch([hide_picker(state), relaunched.unwrap_or_else(Task::none)])
+        }
+        Command::Space => {
+            let (number, display) = commands::split(command_argument(state));
+            match number.parse::<usize>() {
+                Ok(number) => {
+                    let display = (!display.is_empty()).then_some(display);
+                    if let Err(e) = state.manager.switch_to_space(number, display) {
+                        log!("Failed to switch space: {e}");
+                    }
+                }
+                Err(_) => log!("[warn] space needs a number, e.g. `space 2`"),
+            }
+            hide_picker(state)
+        }
+        Command::Hotkey => {
+            let combo = command_argument(state).to_string();
+            if !combo.is_empty() && set_picker_hotkey(state, &combo) {
+                return hide_picker(state);
+            }
+            state.panel = Panel::Hotkey;
+            Task::none()
+        }
+        Command::Run => {
+            state.scripts.set_windows(&state.manager);
+            let result = state
+                .scripts
+                .run(command_argument(state))
+                .and_then(|ops| apply_script_ops(state, ops));
+            match result {
+                Ok(()) => hide_picker(state),
+                Err(e) => {
+                    log!("[warn] {e:#}");
+                    show_toast(state, format!("{e:#}"), None)
+                }
+            }
+        }
+        Command::Permissions => {
+            state.panel = Panel::Permissions;
+            Task::none()
+        }
+        Command::Clip => apply_query(state, format!("{}clip ", commands::PREFIX)),
+        Command::Link => {
+            let text = command_argument(state).to_lowercase();
+            let target = if text.is_empty() {
+                state.peek_origin.and_then(|wid| state.manager.window(wid))
+            } else {
+                state
+                    .manager
+                    .recent_windows(usize::MAX)
+                    .into_iter()
+                    .find(|(app, window)| {
+                        format!("{} {}", app.name, window.title)
+                            .to_lowercase()
+                            .contains(&text)
+                    })
+            };
+            match target.map(|(app, window)| Link::to_window(app, window).url()) {
+                Some(url) => {
+                    crate::macos::set_clipboard(&url);
+                    hide_picker(state)
+                }
+                None => show_toast(state, format!("No window matching {text:?}"), None),
+            }
+        }
+        Command::MissionControl => {
+            let task = hide_picker(state);
+            crate::macos::mission_control();
+            task
+        }
+        Command::Expose => {
+            let name = command_argument(state).to_lowercase();
+            let target = if name.is_empty() {
+                state.peek_origin.and_then(|wid| state.manager.window(wid))
+            } else {
+                state
+                    .manager
+                    .recent_windows(usize::MAX)
+                    .into_iter()
+                    .find(|(app, _)| {
+                        app.name.to_lowercase().contains(&name)
+                            || app
+                                .bundle_id
+                                .as_deref()
+                                .is_some_and(|b| b.to_lowercase().contains(&name))
+                    })
+            };
+            // Exposé works on the frontmost app, so bring it forward first
+            let focused = match target {
+                Some((app, window)) => match window.focus(&app.app) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log!("Failed to focus window: {e}");
+                        false
+                    }
+                },
+                None => {
+                    log!("[warn] no app to show App Exposé for");
+                    false
+                }
+            };
+            let task = hide_picker(state);
+            if focused {
+                crate::macos::app_expose();
+            }
+            task
+        }
+    }
+}
+
+/// Shows `message` under the results for a while, pulling the picker back in front in case
+/// whatever failed got as far as activating another app.
+fn show_toast(state: &mut Switcheroo, message: String, retry: Option<u32>) -> Task<Message> {
+    let Some(id) = state.picker_window else {
+        return Task::none();
+    };
+    let shown_at = std::time::Instant::now();
+    state.toast = Some(Toast {
+        message,
+        retry,
+        shown_at,
+    });
+    crate::macos::activate_application();
+    Task::batch([
+        window::gain_focus(id),
+        Task::perform(tokio::time::sleep(TOAST_DURATION), move |()| {
+            Message::DismissToast(shown_at)
+        }),
+    ])
+}
+
+/// Swaps in a new picker hotkey, keeping the old one (and recording why) if that fails.
+fn set_picker_hotkey(state: &mut Switcheroo, combo: &str) -> bool {
+    // Unless the managed one couldn't be registered, there'd be no way in otherwise
+    if state.config.is_managed("hotkeys.picker") && state.picker.is_some() {
+        state.hotkey_error =
+            Some(String::from("The picker hotkey is set by a configuration profile"));
+        return false;
+    }
+    let Some(picker) = hotkeys::parse_picker(combo) else {
+        state.hotkey_error = Some(format!("{combo:?} is not a valid hotkey"));
+        return false;
+    };
+    match hotkeys::swap_picker(&state.hotkey_manager, state.picker, picker) {
+        Ok(()) => {
+            log!("picker hotkey is now {combo}, set picker = {combo:?} under [hotkeys] to keep it");
+            state.picker = Some(picker);
+            state.hotkey_error = None;
+            true
+        }
+        Err(e) => {
+            state.hotkey_error = Some(format!("Could not register {combo}: {e}"));
+            false
+        }
+    }
+}
+
+fn check_config(state: &mut Switcheroo) {
+    state.scripts.reload_if_changed();
+    let modified = config::modified();
+    if modified != state.config_modified {
+        state.config_modified = modified;
+        reload_config(state);
+    }
+}
+
+/// Applies an edited config file. Hotkeys are swapped live; if the new ones can't be
+/// registered the old ones (and the old config) stay, and the picker says why.
+fn reload_config(state: &mut Switcheroo) {
+    let config = match config::Config::try_load() {
+        Ok(config) => config,
+        Err(e) => {
+            log!("[warn] not applying config change: {e:#}");
+            state.config_error = Some(format!("Config not applied: {e:#}"));
+            return;
+        }
+    };
+    if let Err(e) = hotkeys::reregister(&state.hotkey_manager, &state.config, &config) {
+        log!("[warn] not applying config change, keeping the previous hotkeys: {e:#}");
+        state.config_error = Some(format!("Config not applied: {e:#}"));
+        return;
+    }
+
+    let picker = hotkeys::picker(&config);
+    if let Err(e) = hotkeys::swap_picker(&state.hotkey_manager, state.picker, picker) {
+        let e = format!("Could not register {}: {e}", picker.label());
+        log!("[warn] not applying config change, keeping the previous hotkeys: {e}");
+        if let Err(e) = hotkeys::reregister(&state.hotkey_manager, &config, &state.config) {
+            log!("[warn] could not put the previous hotkeys back: {e:#}");
+        }
+        state.config_error = Some(format!("Config not applied: {e}"));
+        return;
+    }
+    state.picker = Some(picker);
+    state.hotkey_error = None;
+    state.config_error = None;
+
+    state.manager.configure(&config);
+    if config.sources != state.config.sources
+        || config.quick_commands != state.config.quick_commands
+    {
+        state.sources = sources::enabled(&config);
+        refresh_sources(state);
+    }
+    if config.matchers != state.config.matchers {
+        state.engine.set_app_matchers(&config.matchers);
+        state.engine.set_windows(&state.manager);
+    }
+    state.config = config;
+    state.theme = state.config.theme.snapped(state.scale);
+    // The icon size or style may have changed
+    state.icons.clear();
+    update_icons(state);
+    sync_results(state);
+    log!("reloaded config");
+}
+
+/// Scrolls the result list just enough to bring the selected row into view.
+fn scroll_to_selected(state: &Switcheroo) -> Task<Message> {
+    let Some(idx) = state.selected else {
+        return Task::none();
+    };
+    let theme = &state.theme;
+    // The top row scrolls all the way up, so the "Recent" heading stays in view
+    let row = if idx == 0 { 0 } else { item_row(state, idx) };
+    let top = row as f32 * (theme.row_height + theme.row_spacing);
+    let offset = state.results_viewport.0;
+    let height = results_height(state);
+
+    let y = if top < offset {
+        top
+    } else if top + theme.row_height > offset + height {
+        top + theme.row_height - height
+    } else {
+        return Task::none();
+    };
+    iced::widget::operation::scroll_to(RESULTS_ID, scrollable::AbsoluteOffset { x: 0.0, y })
+}
+
+fn results_height(state: &Switcheroo) -> f32 {
+    match state.results_viewport.1 {
+        // Not scrolled yet, so no viewport reported; roughly the window minus the search field
+        height if height <= 0.0 => state.theme.height - 60.0,
+        height => height,
+    }
+}
+
+/// Where result `idx` sits in the list, counting the section headings above it.
+fn item_row(state: &Switcheroo, idx: usize) -> usize {
+    match state.recent_count {
+        0 => idx,
+        n if idx < n => idx + 1,
+        _ => idx + 2,
+    }
+}
+
+/// The result shown in list row `row`, None for a section heading.
+fn row_item(state: &Switcheroo, row: usize) -> Option<usize> {
+    match state.recent_count {
+        0 => Some(row),
+        n if row == 0 || row == n + 1 => None,
+        n if row <= n => Some(row - 1),
+        _ => Some(row - 2),
+    }
+}
+
+/// Rows in (or near) view, the only ones the list builds.
+fn shown_rows(state: &Switcheroo) -> std::ops::Range<usize> {
+    let theme = &state.theme;
+    let stride = theme.row_height + theme.row_spacing;
+    let rows = row_count(state);
+    let first = ((state.results_viewport.0 / stride) as usize).saturating_sub(OVERSCAN);
+    let first = first.min(rows);
+    first..(first + page_size(state) + 2 * OVERSCAN).min(rows)
+}
+
+/// Rows in the result list, section headings included.
+fn row_count(state: &Switcheroo) -> usize {
+    state.items.len()
+        + state.source_rows.len()
+        + match state.recent_count {
+            0 => 0,
+            n if n == state.items.len() => 1,
+            _ => 2,
+        }
+}
+
+/// How many rows fit in the result list.
+fn page_size(state: &Switcheroo) -> usize {
+    let theme = &state.theme;
+    ((results_height(state) + theme.row_spacing) / (theme.row_height + theme.row_spacing)).max(1.0)
+        as usize
+}
+
+/// "1–30 of 124" for the rows currently scrolled into view, None if they all fit.
+fn page_indicator(state: &Switcheroo) -> Option<String> {
+    let count = state.filtered_count;
+    let page = page_size(state);
+    if count <= page {
+        return None;
+    }
+    let theme = &state.theme;
+    let first =
+        (state.results_viewport.0 / (theme.row_height + theme.row_spacing)).round() as usize;
+    let first = first.min(count - 1);
+    let last = (first + page).min(count);
+    Some(format!("{}–{last} of {count}", first + 1))
+}
+
+/// Steps through "all windows" and the saved searches, for horizontal swipes.
+fn cycle_saved_search(state: &mut Switcheroo, forward: bool) -> Task<Message> {
+    let queries: Vec<String> = std::iter::once(String::new())
+        .chain(state.config.saved_searches.iter().map(|s| s.query.clone()))
+        .collect();
+    if queries.len() < 2 {
+        return Task::none();
+    }
+    let current = queries.iter().position(|q| *q == state.query).unwrap_or(0);
+    let next = if forward {
+        (current + 1) % queries.len()
+    } else {
+        (current + queries.len() - 1) % queries.len()
+    };
+    apply_query(state, queries[next].clone())
+}
+
+/// Whatever follows the command word in command mode.
+fn command_argument(state: &Switcheroo) -> &str {
+    commands::command_query(&state.query)
+        .map(|query| commands::split(query).1)
+        .unwrap_or_default()
+}
+
+/// The argument to a workspace command, `default` if none was given.
+fn workspace_name(state: &Switcheroo) -> String {
+    let argument = command_argument(state);
+    if argument.is_empty() {
+        String::from("default")
+    } else {
+        argument.to_string()
+    }
+}
+
+/// Raises the selected window to have a look at it, after putting back the one before.
+fn peek_selected(state: &mut Switcheroo) {
+    if !state.config.peek {
+        return;
+    }
+    unpeek(state);
+    if let Some(wid) = selected_window_id(state)
+        && let Some((_, window)) = state.manager.window(wid)
+    {
+        window.raise();
+        state.peeked = Some(wid);
+    }
+}
+
+/// Puts the window peeking raised back where it was, by raising the windows of its space
+/// that were in front of it again, back to front.
+fn unpeek(state: &mut Switcheroo) {
+    let Some(peeked) = state.peeked.take() else {
+        return;
+    };
+    let Some((_, window)) = state.manager.window(peeked) else {
+        return;
+    };
+    let in_front: Vec<&windows::Window> = state
+        .manager
+        .recent_windows(usize::MAX)
+        .into_iter()
+        .map(|(_, w)| w)
+        .take_while(|w| w.id != peeked)
+        .filter(|w| w.space_id == window.space_id)
+        .collect();
+    for w in in_front.into_iter().rev() {
+        w.raise();
+    }
+}
+
+fn hide_picker(state: &mut Switcheroo) -> Task<Message> {
+    if let Some(id) = state.picker_window.take() {
+        unpeek(state);
+        if !state.config.preserve_query {
+            state.query.clear();
+            state.chips.clear();
+        }
+        state.frozen_order = None;
+        state.selected = None;
+        state.keep_open = false;
+        state.peek_origin = None;
+        state.panel = Panel::Results;
+        state.prewarmed.clear();
+        state.toast = None;
+        state.title_watch = None;
+        state.snapshots.clear();
+        state.dwelled = None;
+        state.quick_look = None;
+        state.labeling = None;
+        state.keep_selection = false;
+        state.unique_match = None;
+        if let Some(shown_at) = state.shown_at.take() {
+            state.stats.record_session(shown_at.elapsed());
+        }
+        if let Some(invocation) = state.invocation.take() {
+            log!("picker {}", invocation.summary());
+        }
+        if let Err(e) = state.stats.save() {
+            log!("[warn] could not save stats: {e:#}");
+        }
+        if let Err(e) = state.metrics.save() {
+            log!("[warn] could not save metrics: {e:#}");
+        }
+        if let Some(duration) = animation_duration(state) {
+            state.animation = Some(Animation {
+                started: std::time::Instant::now(),
+                duration,
+                closing: Some(id),
+            });
+            return Task::none();
+        }
+        crate::macos::hide_application();
+        window::close(id)
+    } else {
+        Task::none()
+    }
+}
+
+pub fn view(state: &Switcheroo, _window_id: window::Id) -> Element<'_, Message> {
+    let search = match &state.labeling {
+        Some(labeling) => text_input("Label, Enter to save, empty to remove", &labeling.draft)
+            .on_input(Message::LabelChanged),
+        None => text_input("Search windows, > for commands...", &state.query)
+            .on_input(Message::QueryChanged),
+    }
+    .id(SEARCH_INPUT_ID)
+    .padding([8, 6])
+    .size(15);
+
+    // Only rows in (or near) view get built, spacers stand in for the rest
+    let theme = &state.theme;
+    let stride = theme.row_height + theme.row_spacing;
+    let rows = row_count(state);
+    let shown = shown_rows(state);
+    let spacer =
+        |rows: usize| iced::widget::Space::new().height(rows as f32 * stride - theme.row_spacing);
+
+    let mut result_rows: Vec<Element<'_, Message>> = Vec::new();
+    if shown.start > 0 {
+        result_rows.push(spacer(shown.start).into());
+    }
+
+    let highlight = !Query::parse(&state.query).text.is_empty();
+    for row in shown.clone() {
+        if let Some(idx) = row_item(state, row)
+            && let Some(source_row) = idx
+                .checked_sub(state.items.len())
+                .and_then(|i| state.source_rows.get(i))
+        {
+            result_rows.push(source_row_view(
+                state,
+                source_row,
+                state.selected == Some(idx),
+            ));
+            continue;
+        }
+        let Some(idx) = row_item(state, row) else {
+            let heading = if row == 0 { "Recent" } else { "All windows" };
+            result_rows.push(
+                container(text(heading).size(11).color(color!(0x888888)))
+                    .height(theme.row_height)
+                    .padding([0.0, theme.row_padding])
+                    .align_y(iced::alignment::Vertical::Bottom)
+                    .into(),
+            );
+            continue;
+        };
+        let item = &state.items[idx];
+        // Closed since the results were last updated, gone with the next matcher tick
+        let Some((app, window)) = item.resolve(&state.manager) else {
+            result_rows.push(iced::widget::Space::new().height(theme.row_height).into());
+            continue;
+        };
+        let Item {
+            pid,
+            score,
+            duplicates,
+            ..
+        } = item;
+        let is_selected = state.selected == Some(idx);
+        let indices = item.indices.get_or_init(|| {
+            if !highlight {
+                return Vec::new();
+            }
+            state
+                .engine
+                .indices(&mut state.matcher.borrow_mut(), item.wid)
+        });
+        let indices_set: HashSet<usize> = indices.iter().map(|&i| i as usize).collect();
+
+        let palette = state.palette;
+        let normal_color = if is_selected {
+            palette.text_selected
+        } else {
+            palette.text
+        };
+        let highlight_color = if is_selected {
+            palette.highlight_selected
+        } else {
+            palette.highlight
+        };
+
+        let mut row_content = row![].spacing(8).align_y(iced::Alignment::Center);
+        let accent = match state.config.space_accents {
+            SpaceAccents::Off => None,
+            SpaceAccents::Badge | SpaceAccents::Border => {
+                state.manager.space_number(window).map(space_accent)
+            }
+        };
+        // Pushed on every row, transparent without a space, so the columns line up
+        if state.config.space_accents == SpaceAccents::Border {
+            let stripe = accent.unwrap_or(iced::Color::TRANSPARENT);
+            row_content = row_content.push(
+                container(iced::widget::Space::new())
+                    .width(3.0)
+                    .height(state.theme.row_height * 0.6)
+                    .style(move |_| container::Style {
+                        background: Some(stripe.into()),
+                        border: iced::Border {
+                            radius: 1.5.into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }),
+            );
+        }
+        for column in &state.config.columns {
+            let cell: Element<'_, Message> = match column.kind {
+                ColumnKind::Icon => {
+                    let icon_size = f32::from(state.config.icon_size());
+                    let icon: Element<'_, Message> =
+                        if let Some(handle) = state.icons.get(&(*pid, is_selected)) {
+                            image(handle.clone())
+                                .width(icon_size)
+                                .height(icon_size)
+                                .into()
+                        } else {
+                            iced::widget::Space::new()
+                                .width(icon_size)
+                                .height(icon_size)
+                                .into()
+                        };
+                    // How many windows the app has, so it's clear Enter isn't the only choice
+                    if state.config.window_count_badge && app.windows.len() > 1 {
+                        let count = container(
+                            text(app.windows.len().to_string())
+                                .size(8)
+                                .color(palette.text_selected),
+                        )
+                        .padding([0, 3])
+                        .style(move |_| container::Style {
+                            background: Some(palette.selection.into()),
+                            border: iced::Border {
+                                radius: 6.0.into(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        });
+                        stack![
+                            icon,
+                            container(count)
+                                .width(icon_size)
+                                .height(icon_size)
+                                .align_x(iced::alignment::Horizontal::Right)
+                                .align_y(iced::alignment::Vertical::Bottom),
+                        ]
+                        .into()
+                    } else {
+                        icon
+                    }
+                }
+                ColumnKind::App => {
+                    let chars: Vec<char> = app.name.chars().collect();
+                    let visible = (0..chars.len()).map(Some);
+                    let app_name_spans: Vec<iced::widget::text::Span<'_>> =
+                        highlight_runs(&chars, visible, 0, &indices_set)
+                            .into_iter()
+                            .map(|(run, matched)| {
+                                span(run).color(if matched {
+                                    highlight_color
+                                } else {
+                                    normal_color
+                                })
+                            })
+                            .collect();
+                    rich_text(app_name_spans)
+                        .size(13)
+                        .wrapping(Wrapping::None)
+                        .into()
+                }
+                ColumnKind::Title if titles_hidden(state) => continue,
+                ColumnKind::Title => {
+                    let title_offset = app.name.chars().count() + 1;
+                    let label = state.labels.get(app, window);
+                    // Labeled windows lead with the label, the real title dimmed after it
+                    let title = title_cell(
+                        state,
+                        window.id,
+                        &window.title,
+                        title_offset,
+                        &indices_set,
+                        if label.is_some() {
+                            color!(0x888888)
+                        } else {
+                            normal_color
+                        },
+                        highlight_color,
+                    );
+                    let mut cell = row![].spacing(6).align_y(iced::Alignment::Center);
+                    if let Some(label) = label {
+                        // The label comes last in the matched text, after the folder
+                        let offset = title_offset
+                            + window.title.chars().count()
+                            + 1
+                            + window.folder.as_ref().map_or(0, |f| f.chars().count() + 1);
+                        let chars: Vec<char> = label.chars().collect();
+                        let label_spans: Vec<iced::widget::text::Span<'_>> = highlight_runs(
+                            &chars,
+                            (0..chars.len()).map(Some),
+                            offset,
+                            &indices_set,
+                        )
+                        .into_iter()
+                        .map(|(run, matched)| {
+                            span(run).color(if matched {
+                                highlight_color
+                            } else {
+                                normal_color
+                            })
+                        })
+                        .collect();
+                        cell = cell.push(rich_text(label_spans).size(13).wrapping(Wrapping::None));
+                    }
+                    cell = cell.push(title);
+                    if let Some(folder) = &window.folder {
+                        cell = cell.push(
+                            text(folder)
+                                .size(11)
+                                .color(color!(0x888888))
+                                .wrapping(Wrapping::None),
+                        );
+                    }
+                    cell.into()
+                }
+                ColumnKind::Space => match state.manager.space_number(window) {
+                    Some(number) => match accent {
+                        Some(accent) => tinted_badge(
+                            format!("Space {number}"),
+                            normal_color,
+                            iced::Color { a: 0.45, ..accent },
+                        ),
+                        None => badge(format!("Space {number}"), normal_color, palette),
+                    },
+                    None => continue,
+                },
+                ColumnKind::Display => {
+                    if !state.manager.multiple_displays() {
+                        continue;
+                    }
+                    match state.manager.display_name(window) {
+                        Some(display) => badge(display.to_string(), normal_color, palette),
+                        None => continue,
+                    }
+                }
+                ColumnKind::BundleId => text(app.bundle_id.as_deref().unwrap_or_default())
+                    .size(12)
+                    .color(color!(0x888888))
+                    .wrapping(Wrapping::None)
+                    .into(),
+                ColumnKind::WindowId => text(window.id.to_string())
+                    .size(12)
+                    .color(color!(0x888888))
+                    .wrapping(Wrapping::None)
+                    .into(),
+                ColumnKind::LastActive => match state.manager.last_active(window.id) {
+                    Some(at) => text(ago(at))
+                        .size(12)
+                        .color(color!(0x888888))
+                        .wrapping(Wrapping::None)
+                        .into(),
+                    // Keeps whatever comes after lined up with the rows that have one
+                    None => iced::widget::Space::new().into(),
+                },
+            };
+            let width = match (column.width, column.kind) {
+                (Some(width), _) => Length::Fixed(width),
+                (None, ColumnKind::Title) => Length::Fill,
+                (None, ColumnKind::LastActive) => Length::Fixed(LAST_ACTIVE_WIDTH),
+                (None, _) => Length::Shrink,
+            };
+            row_content = row_content.push(container(cell).width(width));
+        }
+
+        if *duplicates > 0 {
+            row_content =
+                row_content.push(badge(format!("×{}", duplicates + 1), normal_color, palette));
+        }
+
+        if window.app_level_only {
+            row_content =
+                row_content.push(badge("app-level only".to_string(), normal_color, palette));
+        }
+
+        if state.config.debug_scores {
+            row_content = row_content.push(
+                text(format!(
+                    "fuzzy {} + frecency {} + recency {} + boost {} + script {} = {}",
+                    score.fuzzy,
+                    score.frecency,
+                    score.recency,
+                    score.boost,
+                    score.script,
+                    score.total()
+                ))
+                .size(10)
+                .color(color!(0x888888))
+                .wrapping(Wrapping::None),
+            );
+        }
+
+        let row = result_row(row_content, is_selected, palette, &state.theme);
+        if state.config.window_snapshots && !titles_hidden(state) {
+            // Hovering takes the snapshot, the tooltip with it shows once it's there
+            let row = mouse_area(row).on_enter(Message::RowHovered(window.id));
+            if state.snapshots.contains_key(&window.id) {
+                result_rows.push(
+                    tooltip(
+                        row,
+                        snapshot_card(state, window),
+                        tooltip::Position::FollowCursor,
+                    )
+                    .into(),
+                );
+            } else {
+                result_rows.push(row.into());
+            }
+        } else {
+            result_rows.push(row);
+        }
+    }
+    if shown.end < rows {
+        result_rows.push(spacer(rows - shown.end).into());
+    }
+
+    let mut results: Element<'_, Message> = if state.panel == Panel::Help {
+        help_panel(state)
+    } else if state.panel == Panel::QuickLook {
+        quick_look_panel(state)
+    } else if state.panel == Panel::Stats {
+        stats_panel(state)
+    } else if state.panel == Panel::Hotkey {
+        hotkey_panel(state)
+    } else if state.panel == Panel::Permissions {
+        permissions_panel(state)
+    } else if let Some(query) = commands::command_query(&state.query) {
+        command_list(state, query)
+    } else if state.items.is_empty() && state.source_rows.is_empty() {
+        empty_state(state)
+    } else {
+        scrollable(column(result_rows).spacing(state.theme.row_spacing))
+            .id(RESULTS_ID)
+            .on_scroll(Message::ResultsScrolled)
+            .height(Length::Fill)
+            .into()
+    };
+
+    // Kept to the corner, out of the way of the rows around the selection
+    if state.panel == Panel::Results
+        && !titles_hidden(state)
+        && let Some(wid) = state
+            .dwelled
+            .filter(|&wid| selected_window_id(state) == Some(wid))
+        && let Some((_, window)) = state.manager.window(wid)
+    {
+        results = stack![
+            results,
+            container(snapshot_card(state, window))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(iced::alignment::Horizontal::Right)
+                .align_y(iced::alignment::Vertical::Bottom),
+        ]
+        .into();
+    }
+
+    let separator = container(iced::widget::Space::new().width(Length::Fill).height(0))
+        .width(Length::Fill)
+        .height(1)
+        .style(|_: &Theme| container::Style {
+            background: Some(iced::Background::Color(iced::Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 0.08,
+            })),
+            ..Default::default()
+        });
+
+    let mut header = row![container(search).width(Length::Fill)]
+        .spacing(8)
+        .align_y(iced::Alignment::Center);
+    if state.panel == Panel::Results
+        && let Some(indicator) = page_indicator(state)
+    {
+        header = header.push(text(indicator).size(11).color(color!(0x888888)));
+    }
+    if state.reloading {
+        header = header.push(text("reloading…").size(11).color(color!(0x888888)));
+    }
+    if state.keep_open {
+        header = header.push(text("keep open").size(11).color(state.palette.highlight));
+    }
+    if !state.permissions.all() && state.panel != Panel::Permissions {
+        header = header.push(
+            button(
+                text("permissions missing")
+                    .size(11)
+                    .color(state.palette.highlight),
+            )
+            .on_press(Message::ShowPermissions)
+            .padding(0)
+            .style(button::text),
+        );
+    }
+
+    let mut content = column![header];
+    if state.panel == Panel::Results && commands::command_query(&state.query).is_none() {
+        content = content.push(chip_bar(state));
+    }
+    let mut content = content
+        .push(separator)
+        .push(results)
+        .spacing(8)
+        .padding([12, 14]);
+    if let Some(toast) = &state.toast {
+        content = content.push(toast_bar(state, toast));
+    }
+
+    let background = state.palette.background;
+    let main_container = container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(move |_: &Theme| container::Style {
+            background: Some(iced::Background::Color(background)),
+            border: iced::Border {
+                radius: 12.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+    // Grows from the middle, the window itself stays put
+    let shown = state.animation.map_or(1.0, |animation| animation.shown());
+    let shrink = (1.0 - ANIMATION_SCALE) * (1.0 - shown) / 2.0;
+    center(main_container)
+        .padding([shrink * state.theme.height, shrink * PICKER_WIDTH])
+        .into()
+}
+
+/// Window title with highlighted spans, truncated to avoid multi-line rows. `offset` is where
+/// the title starts in the matched "<app name> <title>" text.
+fn title_cell<'a>(
+    state: &Switcheroo,
+    wid: u32,
+    title: &'a str,
+    offset: usize,
+    indices: &HashSet<usize>,
+    normal_color: iced::Color,
+    highlight_color: iced::Color,
+) -> Element<'a, Message> {
+    let title_chars: Vec<char> = title.chars().collect();
+    let visible = truncate(
+        title_chars.len(),
+        state.config.max_title_chars,
+        state.config.title_ellipsis,
+    );
+    let truncated = visible.len() < title_chars.len();
+    let title_spans: Vec<iced::widget::text::Span<'a>> =
+        highlight_runs(&title_chars, visible, offset, indices)
+            .into_iter()
+            .map(|(run, matched)| {
+                span(run).color(if matched {
+                    highlight_color
+                } else {
+                    normal_color
+                })
+            })
+            .collect();
+    let title_elem: Element<'a, Message> = rich_text(title_spans)
+        .size(13)
+        .wrapping(Wrapping::None)
+        .into();
+
+    // Show the full title on hover when it was cut, snapshot tooltips have it already
+    if truncated && !(state.config.window_snapshots && state.snapshots.contains_key(&wid)) {
+        tooltip(
+            title_elem,
+            container(text(title).size(12))
+                .padding([4, 8])
+                .style(container::rounded_box),
+            tooltip::Position::Bottom,
+        )
+        .into()
+    } else {
+        title_elem
+    }
+}
+
+/// The `visible` chars (`None` being a "…") split into runs that are all matched or all not,
+/// `offset` being where `chars` start in the matched text.
+fn highlight_runs(
+    chars: &[char],
+    visible: impl IntoIterator<Item = Option<usize>>,
+    offset: usize,
+    indices: &HashSet<usize>,
+) -> Vec<(String, bool)> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for i in visible {
+        let (ch, matched) = match i {
+            Some(i) => (chars[i], indices.contains(&(i + offset))),
+            None => ('…', false),
+        };
+        match runs.last_mut() {
+            Some((run, run_matched)) if *run_matched == matched => run.push(ch),
+            _ => runs.push((ch.to_string(), matched)),
+        }
+    }
+    runs
+}
+
+/// Whether rows leave out window titles (and snapshots, labels), for an audience.
+fn titles_hidden(state: &Switcheroo) -> bool {
+    state.privacy
+        || state.config.privacy
+        || (state.screen_shared && state.config.while_sharing == WhileSharing::Minimal)
+}
+
+/// The window's snapshot, when there is one, above its full title.
+fn snapshot_card<'a>(state: &'a Switcheroo, window: &'a windows::Window) -> Element<'a, Message> {
+    let mut content = column![].spacing(4).max_width(SNAPSHOT_WIDTH as f32);
+    if let Some((handle, _)) = state.snapshots.get(&window.id) {
+        content = content.push(image(handle.clone()).width(Length::Fill));
+    }
+    content = content.push(text(&window.title).size(12));
+    container(content)
+        .padding([4, 8])
+        .style(container::rounded_box)
+        .into()
+}
+
+/// Desaturates RGBA pixels, optionally shading them in `tint` instead of gray.
+fn monochrome(rgba: &[u8], tint: Option<iced::Color>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rgba.len());
+    for px in rgba.chunks_exact(4) {
+        let luma = 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32;
+        match tint {
+            // Keep some of the shading so the icon doesn't turn into a flat blob
+            Some(tint) => {
+                let shade = 0.35 + 0.65 * luma / 255.0;
+                for channel in [tint.r, tint.g, tint.b] {
+                    out.push((channel * shade * px[3] as f32) as u8);
+                }
+            }
+            None => out.extend_from_slice(&[luma as u8; 3]),
+        }
+        out.push(px[3]);
+    }
+    out
+}
+
+fn source_row_view<'a>(
+    state: &'a Switcheroo,
+    row: &SourceRow,
+    is_selected: bool,
+) -> Element<'a, Message> {
+    let item = &state.sources[row.source].items()[row.item];
+    let icon_size = f32::from(state.config.icon_size());
+    let handle = item
+        .icon
+        .as_ref()
+        .and_then(|path| state.source_icons.get(path))
+        .and_then(Option::as_ref);
+    let icon: Element<'_, Message> = match handle {
+        Some(handle) => image(handle.clone())
+            .width(icon_size)
+            .height(icon_size)
+            .into(),
+        None => iced::widget::Space::new()
+            .width(icon_size)
+            .height(icon_size)
+            .into(),
+    };
+    let title_color = if is_selected {
+        state.palette.text_selected
+    } else {
+        state.palette.text
+    };
+    let title = if titles_hidden(state) {
+        ""
+    } else {
+        item.title.as_str()
+    };
+    let content = row![
+        icon,
+        text(title)
+            .size(13)
+            .color(title_color)
+            .wrapping(Wrapping::None),
+        text(&item.subtitle)
+            .size(12)
+            .color(color!(0x888888))
+            .wrapping(Wrapping::None),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+    result_row(content, is_selected, state.palette, &state.theme)
+}
+
+/// "All" and the [`Chip`]s, filled in the selection color while on.
+fn chip_bar(state: &Switcheroo) -> Element<'_, Message> {
+    let palette = state.palette;
+    let chip = |label: &'static str, on: bool, n: u8| {
+        let color = if on {
+            palette.text_selected
+        } else {
+            palette.text
+        };
+        button(text(label).size(11).color(color))
+            .on_press(Message::ToggleChip(n))
+            .padding([1, 8])
+            .style(move |_: &Theme, _| button::Style {
+                background: on.then_some(palette.selection.into()),
+                border: iced::Border {
+                    color: palette.selection,
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    };
+    let mut chips: Vec<Element<'_, Message>> = vec![chip("All", state.chips.is_empty(), 0)];
+    for (n, c) in (1..).zip(Chip::ALL) {
+        chips.push(chip(c.label(), state.chips.contains(&c), n));
+    }
+    row(chips).spacing(6).into()
+}
+
+/// A small pill shown at the end of a result row.
+fn badge<'a>(label: String, color: iced::Color, palette: Palette) -> Element<'a, Message> {
+    tinted_badge(label, color, palette.selection)
+}
+
+fn tinted_badge<'a>(
+    label: String,
+    color: iced::Color,
+    background: iced::Color,
+) -> Element<'a, Message> {
+    container(text(label).size(11).color(color).wrapping(Wrapping::None))
+        .padding([1, 6])
+        .style(move |_| container::Style {
+            background: Some(background.into()),
+            border: iced::Border {
+                radius: 8.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+/// Spaces' accent colors, repeating after the eighth.
+const SPACE_ACCENTS: [iced::Color; 8] = [
+    color!(0x0a84ff),
+    color!(0x30d158),
+    color!(0xff9f0a),
+    color!(0xbf5af2),
+    color!(0xff375f),
+    color!(0x64d2ff),
+    color!(0xffd60a),
+    color!(0xac8e68),
+];
+
+/// The accent of the nth space (1-based), the same on every display.
+fn space_accent(number: usize) -> iced::Color {
+    SPACE_ACCENTS[number.saturating_sub(1) % SPACE_ACCENTS.len()]
+}
+
+fn result_row<'a>(
+    content: impl Into<Element<'a, Message>>,
+    is_selected: bool,
+    palette: Palette,
+    theme: &config::Theme,
+) -> Element<'a, Message> {
+    let bg_color = if is_selected {
+        palette.selection
+    } else {
+        iced::Color::TRANSPARENT
+    };
+
+    container(content)
+        .padding([0.0, theme.row_padding])
+        .width(Length::Fill)
+        .height(theme.row_height)
+        .align_y(iced::Alignment::Center)
+        .style(move |_: &Theme| container::Style {
+            background: Some(iced::Background::Color(bg_color)),
+            border: iced::Border {
+                radius: 5.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+fn toast_bar<'a>(state: &Switcheroo, toast: &'a Toast) -> Element<'a, Message> {
+    let mut content = row![
+        text(&toast.message)
+            .size(12)
+            .color(state.palette.text_selected)
+            .width(Length::Fill)
+            .wrapping(Wrapping::None),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+    if let Some(wid) = toast.retry {
+        content = content.push(
+            button(text("Retry").size(12).color(state.palette.highlight))
+                .on_press(Message::FocusWindow(wid))
+                .padding([2, 6])
+                .style(button::text),
+        );
+    }
+    content = content.push(
+        button(text("×").size(12).color(state.palette.text))
+            .on_press(Message::DismissToast(toast.shown_at))
+            .padding([2, 6])
+            .style(button::text),
+    );
+
+    container(content)
+        .padding([4, 10])
+        .width(Length::Fill)
+        .style(|_: &Theme| container::Style {
+            background: Some(iced::Background::Color(color!(0x5c2020))),
+            border: iced::Border {
+                radius: 6.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+fn command_list<'a>(state: &'a Switcheroo, query: &str) -> Element<'a, Message> {
+    if clip_query(state).is_some() {
+        return clip_list(state);
+    }
+    let rows = commands::filter(query, state.manager.private_apis())
+        .into_iter()
+        .enumerate()
+        .map(|(idx, command)| {
+            let is_selected = state.selected == Some(idx);
+            let name_color = if is_selected {
+                state.palette.text_selected
+            } else {
+                state.palette.text
+            };
+            let content = row![
+                text(command.name()).size(13).color(name_color).width(150),
+                text(command.description())
+                    .size(13)
+                    .color(color!(0x888888))
+                    .wrapping(Wrapping::None),
+            ]
+            .spacing(8);
+            result_row(content, is_selected, state.palette, &state.theme)
+        });
+
+    scrollable(column(rows).spacing(state.theme.row_spacing))
+        .id(RESULTS_ID)
+        .on_scroll(Message::ResultsScrolled)
+        .height(Length::Fill)
+        .into()
+}
+
+/// First line of each matching clipboard entry, cut to fit.
+fn clip_list(state: &Switcheroo) -> Element<'_, Message> {
+    const MAX_CHARS: usize = 120;
+    if state.clip_rows.is_empty() {
+        let hint = if state.config.clipboard_history == 0 {
+            "Set clipboard_history in the config to remember what you copy"
+        } else {
+            "Nothing copied matches"
+        };
+        return container(text(hint).size(13).color(color!(0x888888)))
+            .padding([8, 4])
+            .into();
+    }
+    let hidden = titles_hidden(state);
+    let rows = state.clip_rows.iter().enumerate().map(|(idx, &entry)| {
+        let is_selected = state.selected == Some(idx);
+        let entry = &state.clipboard.entries()[entry];
+        let mut line: String = entry
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_CHARS)
+            .collect();
+        if line.len() < entry.trim_end().len() {
+            line.push('…');
+        }
+        // Still there to pick, just not readable
+        if hidden {
+            line = "•".repeat(line.chars().count().min(12));
+        }
+        let color = if is_selected {
+            state.palette.text_selected
+        } else {
+            state.palette.text
+        };
+        let content = text(line).size(13).color(color).wrapping(Wrapping::None);
+        result_row(content, is_selected, state.palette, &state.theme)
+    });
+
+    scrollable(column(rows).spacing(state.theme.row_spacing))
+        .id(RESULTS_ID)
+        .on_scroll(Message::ResultsScrolled)
+        .height(Length::Fill)
+        .into()
+}
+
+fn quick_look_panel(state: &Switcheroo) -> Element<'_, Message> {
+    let Some((wid, handle)) = &state.quick_look else {
+        return column![].into();
+    };
+    let title = state
+        .manager
+        .window(*wid)
+        .map(|(app, window)| {
+            if titles_hidden(state) {
+                app.name.clone()
+            } else {
+                format!("{} — {}", app.name, window.title)
+            }
+        })
+        .unwrap_or_default();
+    column![
+        center(image(handle.clone()).content_fit(iced::ContentFit::Contain))
+            .width(Length::Fill)
+            .height(Length::Fill),
+        text(title)
+            .size(12)
+            .color(state.palette.text)
+            .wrapping(Wrapping::None),
+        text("Space or Esc to close")
+            .size(11)
+            .color(color!(0x888888)),
+    ]
+    .spacing(6)
+    .align_x(iced::Alignment::Center)
+    .into()
+}
+
+fn help_panel(state: &Switcheroo) -> Element<'_, Message> {
+    let dim = color!(0x888888);
+    let entry = |keys: String, description: &'static str| {
+        row![
+            text(keys)
+                .size(13)
+                .color(state.palette.highlight)
+                .width(150),
+            text(description).size(13).color(state.palette.text),
+        ]
+        .spacing(8)
+    };
+
+    let mut content = column![text("Keys").size(12).color(dim)].spacing(4);
+    for binding in BINDINGS {
+        content = content.push(entry(binding.label(), binding.description));
+    }
+
+    content = content.push(text("Query operators").size(12).color(dim));
+    for (operator, description) in query::OPERATORS {
+        content = content.push(entry(operator.to_string(), *description));
+    }
+
+    content = content.push(text("Commands").size(12).color(dim));
+    for command in Command::available(state.manager.private_apis()) {
+        content = content.push(entry(
+            format!("{}{}", commands::PREFIX, command.name()),
+            command.description(),
+        ));
+    }
+
+    scrollable(content).height(Length::Fill).into()
+}
+
+fn hotkey_panel(state: &Switcheroo) -> Element<'_, Message> {
+    let dim = color!(0x888888);
+
+    let status = match (&state.hotkey_error, state.picker) {
+        (Some(error), _) => error.clone(),
+        (None, Some(picker)) => format!("The picker opens with {}", picker.label()),
+        (None, None) => String::from("No picker hotkey registered"),
+    };
+
+    let mut content = column![
+        text(status).size(13).color(state.palette.text),
+        text("Taken by macOS or another app? Pick one of these, or type >hotkey <combo>")
+            .size(12)
+            .color(dim),
+    ]
+    .spacing(4);
+
+    for combo in hotkeys::PICKER_ALTERNATIVES {
+        content = content.push(
+            button(text(*combo).size(13).color(state.palette.highlight))
+                .on_press(Message::SetPickerHotkey(combo.to_string()))
+                .padding([2, 0])
+                .style(button::text),
+        );
+    }
+
+    scrollable(content).height(Length::Fill).into()
+}
+
+fn permissions_panel(state: &Switcheroo) -> Element<'_, Message> {
+    let dim = color!(0x888888);
+
+    let mut content = column![
+        text("Checked every second, no restart needed once Accessibility is granted")
+            .size(12)
+            .color(dim),
+    ]
+    .spacing(4);
+
+    let permissions = [
+        (
+            "Accessibility",
+            state.permissions.accessibility,
+            "Privacy_Accessibility",
+            "focusing, moving and closing windows; without it picking a window activates its app",
+        ),
+        (
+            "Screen Recording",
+            state.permissions.screen_recording,
+            "Privacy_ScreenCapture",
+            "window titles and snapshots; macOS may want switcheroo restarted after granting it",
+        ),
+    ];
+    for (name, granted, pane, needed_for) in permissions {
+        let (status, color) = if granted {
+            ("granted", state.palette.text)
+        } else {
+            ("missing", color!(0xe06c6c))
+        };
+        let mut line = row![
+            text(name).size(13).color(state.palette.text).width(150),
+            text(status).size(13).color(color).width(Length::Fill),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center);
+        if !granted {
+            line = line.push(
+                button(
+                    text("Open System Settings")
+                        .size(12)
+                        .color(state.palette.highlight),
+                )
+                .on_press(Message::OpenPrivacySettings(pane))
+                .padding([2, 0])
+                .style(button::text),
+            );
+        }
+        content = content
+            .push(line)
+            .push(text(needed_for).size(12).color(dim));
+    }
+
+    scrollable(content).height(Length::Fill).into()
+}
+
+fn stats_panel(state: &Switcheroo) -> Element<'_, Message> {
+    let dim = color!(0x888888);
+
+    let sessions = match state.stats.average_session() {
+        Some(avg) => format!(
+            "{} picker sessions, {:.1}s on average",
+            state.stats.sessions,
+            avg.as_secs_f64()
+        ),
+        None => String::from("No picker sessions recorded yet"),
+    };
+
+    let mut content = column![
+        text(sessions).size(13).color(state.palette.text),
+        text("Most switched-to windows").size(12).color(dim),
+    ]
+    .spacing(4);
+
+    for usage in state.stats.top_windows(10) {
+        let title = if titles_hidden(state) {
+            ""
+        } else {
+            usage.title.as_str()
+        };
+        content = content.push(
+            row![
+                text(usage.usage.count.to_string())
+                    .size(13)
+                    .color(state.palette.highlight)
+                    .width(40),
+                text(&usage.app)
+                    .size(13)
+                    .color(state.palette.text)
+                    .width(150),
+                text(title).size(13).color(dim).wrapping(Wrapping::None),
+            ]
+            .spacing(8),
+        );
+    }
+
+    scrollable(content).height(Length::Fill).into()
+}
+
+fn empty_state(state: &Switcheroo) -> Element<'_, Message> {
+    let dim = color!(0x888888);
+
+    let reason = if !state.manager.app_map().is_empty() {
+        "No windows match your query"
+    } else if !crate::macos::accessibility_granted() {
+        "No windows found: Accessibility permission is missing"
+    } else if !crate::macos::screen_recording_granted() {
+        "No windows found: Screen Recording permission is missing"
+    } else {
+        "No windows open"
+    };
+
+    let mut content = column![
+        text(reason).size(14).color(state.palette.text),
+        text("Tip: narrow by app with app:<name>, e.g. app:safari docs")
+            .size(12)
+            .color(dim),
+    ]
+    .spacing(6);
+
+    let recent = state.manager.recent_windows(5);
+    if !recent.is_empty() {
+        content = content.push(text("Recent windows").size(12).color(dim));
+        for (app, window) in recent {
+            let title = if titles_hidden(state) {
+                ""
+            } else {
+                window.title.as_str()
+            };
+            let label = row![
+                text(&app.name)
+                    .size(13)
+                    .color(state.palette.text)
+                    .width(150),
+                text(title).size(13).color(dim).wrapping(Wrapping::None),
+            ]
+            .spacing(8);
+            content = content.push(
+                button(label)
+                    .on_press(Message::FocusWindow(window.id))
+                    .padding([4, 8])
+                    .width(Length::Fill)
+                    .style(button::text),
+            );
+        }
+    }
+
+    container(content)
+        .padding([8, 4])
+        .height(Length::Fill)
+        .into()
+}
+
+pub fn subscription(state: &Switcheroo) -> Subscription<Message> {
+    // Nothing in here ticks while the picker is hidden (unless a tap trigger or hot corner
+    // needs polling), so the process sits idle between invocations
+    let mut subs = vec![
+        Subscription::run(hotkey_events),
+        Subscription::run(workspace_events),
+        Subscription::run(url_events),
+        window::close_events().map(Message::WindowClosed),
+    ];
+
+    if state.animation.is_some() {
+        subs.push(window::frames().map(Message::AnimationFrame));
+    }
+    // Shown means drawn on screen, not just opened
+    if state
+        .invocation
+        .as_ref()
+        .is_some_and(Invocation::awaiting_frame)
+    {
+        subs.push(window::frames().map(|_| Message::PickerShown));
+    }
+
+    if state.engine.running() {
+        subs.push(
+            iced::time::every(iced::time::Duration::from_millis(16)).map(|_| Message::MatcherTick),
+        );
+    }
+
+    if let Some(Picker::Tap(_)) = state.picker {
+        subs.push(
+            iced::time::every(iced::time::Duration::from_millis(16))
+                .map(|_| Message::PollModifierTap),
+        );
+    }
+
+    // Change counts are all NSPasteboard offers, there's no notification for copies
+    if state.config.clipboard_history > 0 {
+        subs.push(
+            iced::time::every(iced::time::Duration::from_millis(500))
+                .map(|_| Message::PollClipboard),
+        );
+    }
+
+    if state.config.hot_corner.is_some() && state.picker_window.is_none() {
+        subs.push(
+            iced::time::every(iced::time::Duration::from_millis(16))
+                .map(|_| Message::PollHotCorner),
+        );
+    }
+
+    if state.picker_window.is_some() {
+        subs.push(
+            iced::time::every(iced::time::Duration::from_secs(2)).map(|_| Message::CheckConfig),
+        );
+        if !state.permissions.all() || state.panel == Panel::Permissions {
+            subs.push(
+                iced::time::every(iced::time::Duration::from_secs(1))
+                    .map(|_| Message::CheckPermissions),
+            );
+        }
+        subs.push(Subscription::run(title_events));
+        if state.config.refresh_secs > 0 {
+            subs.push(
+                iced::time::every(iced::time::Duration::from_secs(state.config.refresh_secs))
+                    .map(|_| Message::RefreshTick),
+            );
+        }
+        subs.push(iced::event::listen_with(
+            |event, status, _window| match event {
+                iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    keymap::lookup(&key, modifiers, status == iced::event::Status::Captured)
+                }
+                iced::Event::Mouse(iced::mouse::Event::WheelScrolled { delta }) => {
+                    Some(Message::Scrolled(delta))
+                }
+                iced::Event::Window(window::Event::Rescaled(scale)) => {
+                    Some(Message::Rescaled(scale))
+                }
+                iced::Event::Window(window::Event::Unfocused) => Some(Message::PickerUnfocused),
+                _ => None,
+            },
+        ));
+    }
+
+    Subscription::batch(subs)
+}
+
+/// Hotkey events pushed from global-hotkey's handler, rather than polling its receiver.
+fn hotkey_events() -> iced::futures::channel::mpsc::UnboundedReceiver<Message> {
+    let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+    GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+        let received = std::time::Instant::now();
+        let _ = sender.unbounded_send(Message::HotKey(event.id(), event.state(), received));
+    }));
+    receiver
+}
+
+/// Apps coming to the front and quitting, from NSWorkspace's notifications.
+fn workspace_events() -> iced::futures::channel::mpsc::UnboundedReceiver<Message> {
+    let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+    let terminated = sender.clone();
+    crate::macos::watch_activations(move |pid| {
+        let _ = sender.unbounded_send(Message::AppActivated(pid));
+    });
+    crate::macos::watch_terminations(move |pid| {
+        let _ = terminated.unbounded_send(Message::AppTerminated(pid));
+    });
+    receiver
+}
+
+fn url_events() -> iced::futures::channel::mpsc::UnboundedReceiver<Message> {
+    let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+    crate::macos::set_url_handler(move |url| {
+        let _ = sender.unbounded_send(Message::OpenUrl(url));
+    });
+    receiver
+}
+
+fn title_events() -> iced::futures::channel::mpsc::UnboundedReceiver<Message> {
+    let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+    crate::macos::set_title_handler(move |wid, title| {
+        let _ = sender.unbounded_send(Message::TitleChanged(wid, title));
+    });
+    receiver
+}
+
+/// "now", "2m ago", "3h ago" or "5d ago".
+fn ago(at: std::time::SystemTime) -> String {
+    let secs = at.elapsed().unwrap_or_default().as_secs();
+    match secs {
+        0..60 => String::from("now"),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// Which title chars to show for `max` visible chars, `None` marks the "…".
+fn truncate(len: usize, max: usize, ellipsis: Ellipsis) -> Vec<Option<usize>> {
+    if len <= max || max == 0 {
+        return (0..len).map(Some).collect();
+    }
+    let keep = max - 1;
+    match ellipsis {
+        Ellipsis::Start => std::iter::once(None)
+            .chain((len - keep..len).map(Some))
+            .collect(),
+        Ellipsis::Middle => {
+            let head = keep.div_ceil(2);
+            let tail = keep - head;
+            (0..head)
+                .map(Some)
+                .chain(std::iter::once(None))
+                .chain((len - tail..len).map(Some))
+                .collect()
+        }
+        Ellipsis::End => (0..keep).map(Some).chain(std::iter::once(None)).collect(),
+    }
+}
+
+/// A window row in the results, by id so it can outlive the borrow of the window list.
+struct Item {
+    pid: i32,
+    wid: u32,
+    score: Score,
+    /// Matched char indices into "<app name> <title>", worked out once the row is shown
+    indices: OnceCell<Vec<u32>>,
+    /// Other windows merged into this row (`merge_duplicates`)
+    duplicates: usize,
+}
+
+impl Item {
+    fn resolve<'a>(
+        &self,
+        manager: &'a windows::Manager,
+    ) -> Option<(&'a windows::App, &'a windows::Window)> {
+        let app = manager.app_map().get(&self.pid)?;
+        let window = app.windows.iter().find(|w| w.id == self.wid)?;
+        Some((app, window))
+    }
+}
+
+struct SourceRow {
+    source: usize,
+    item: usize,
+    score: u32,
+}
+
+/// Fuzzy matches the clipboard history, keeping it newest first without a search.
+fn match_clipboard(state: &mut Switcheroo, text: &str) {
+    let entries = state.clipboard.entries();
+    if text.is_empty() {
+        state.clip_rows.extend(0..entries.len());
+        return;
+    }
+    let pattern = nucleo::pattern::Pattern::parse(
+        text,
+        nucleo::pattern::CaseMatching::Smart,
+        nucleo::pattern::Normalization::Smart,
+    );
+    let matcher = state.matcher.get_mut();
+    let mut buf = Vec::new();
+    let mut scored: Vec<(u32, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, entry)| {
+            let score = pattern.score(nucleo::Utf32Str::new(entry, &mut buf), matcher)?;
+            Some((score, idx))
+        })
+        .collect();
+    // Stable, so equally good matches stay newest first
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    state
+        .clip_rows
+        .extend(scored.into_iter().map(|(_, idx)| idx));
+}
+
+/// Fuzzy matches the sources' items, best first. Only while searching, so they never
+/// share the list with the "Recent" section.
+fn match_sources(state: &mut Switcheroo, text: &str) {
+    if text.is_empty() || state.sources.is_empty() {
+        return;
+    }
+    let pattern = nucleo::pattern::Pattern::parse(
+        text,
+        nucleo::pattern::CaseMatching::Smart,
+        nucleo::pattern::Normalization::Smart,
+    );
+    let matcher = state.matcher.get_mut();
+    let mut buf = Vec::new();
+    for (s, source) in state.sources.iter().enumerate() {
+        for (i, item) in source.items().iter().enumerate() {
+            let haystack = nucleo::Utf32Str::new(&item.title, &mut buf);
+            if let Some(score) = pattern.score(haystack, matcher) {
+                state.source_rows.push(SourceRow {
+                    source: s,
+                    item: i,
+                    score,
+                });
+            }
+        }
+    }
+    state.source_rows.sort_by(|a, b| b.score.cmp(&a.score));
+}
+
+/// Recomputes the result rows from the matcher's latest scores.
+fn update_results(state: &mut Switcheroo) {
+    state.items.clear();
+    state.source_rows.clear();
+    state.clip_rows.clear();
+    state.recent_count = 0;
+    if let Some(text) = clip_query(state).map(str::to_string) {
+        match_clipboard(state, &text);
+        state.filtered_count = state.clip_rows.len();
+        return;
+    }
+    if let Some(query) = commands::command_query(&state.query) {
+        state.filtered_count = commands::filter(query, state.manager.private_apis()).len();
+        return;
+    }
+
+    let query = Query::parse(&state.query);
+    state.engine.scores(&mut state.scores);
+    state.scripts.begin_pass();
+    let filter = Filter {
+        query: &state.query,
+        manager: &state.manager,
+        scores: &state.scores,
+        chips: &state.chips,
+        frozen_order: state.frozen_order.as_ref(),
+        stats: &state.stats,
+        scripts: &state.scripts,
+        config: &state.config,
+        dock: &state.dock_order,
+    };
+    (state.items, state.recent_count) = filter.items(&query);
+    match_sources(state, &query.text);
+    state.filtered_count = state.items.len() + state.source_rows.len();
+    load_source_icons(state);
+}
+
+/// What goes into listing windows, borrowed from [`Switcheroo`] so tests can make one up.
+struct Filter<'a> {
+    /// As typed, operators and all
+    query: &'a str,
+    manager: &'a windows::Manager,
+    /// Fuzzy scores by window id, windows without one don't match
+    scores: &'a HashMap<u32, u32>,
+    chips: &'a HashSet<Chip>,
+    frozen_order: Option<&'a HashMap<u32, usize>>,
+    stats: &'a Stats,
+    scripts: &'a Scripts,
+    config: &'a config::Config,
+    /// Bundle ids in Dock order, for [`Sort::Dock`]
+    dock: &'a [String],
+}
+
+impl Filter<'_> {
+    /// Window rows in the order they're listed, and how many of the first are recent windows.
+    fn items(&self, query: &Query) -> (Vec<Item>, usize) {
+        let mut items = Vec::new();
+        let mut recent_count = 0;
+        let mut rows: Vec<(&windows::App, &windows::Window, Item)> = Vec::new();
+        for (pid, app) in self.manager.app_map() {
+            if !query.matches_app(app) {
+                continue;
+            }
+            for win in &app.windows {
+                if self.config.hide_untitled && win.title.trim().is_empty() {
+                    continue;
+                }
+                if !query.matches_display(self.manager.display_name(win))
+                    || !query.matches_state(win.state)
+                    || !self
+                        .chips
+                        .iter()
+                        .all(|chip| chip.matches(self.manager, win))
+                {
+                    continue;
+                }
+                let Some(&fuzzy) = self.scores.get(&win.id) else {
+                    continue;
+                };
+                let Some(script) = self.scripts.verdict(app, win) else {
+                    continue;
+                };
+                let mut score = Score::new(fuzzy, self.stats, self.config, app, win);
+                score.script = script;
+                let item = Item {
+                    pid: *pid,
+                    wid: win.id,
+                    score,
+                    indices: OnceCell::new(),
+                    duplicates: 0,
+                };
+                rows.push((app, win, item));
+            }
+        }
+
+        let manager = self.manager;
+        // Looked up once per app rather than for every comparison
+        let app_order: HashMap<i32, (f64, &str)> = match self.config.sort {
+            Sort::Dock => manager
+                .app_map()
+                .iter()
+                .map(|(pid, app)| {
+                    let key = app_key(app);
+                    let position = self.dock.iter().position(|id| *id == key);
+                    (
+                        *pid,
+                        (position.map_or(f64::MAX, |p| p as f64), app.name.as_str()),
+                    )
+                })
+                .collect(),
+            Sort::Launched => manager
+                .app_map()
+                .iter()
+                .map(|(pid, app)| (*pid, (app.launched().unwrap_or(f64::MAX), "")))
+                .collect(),
+            Sort::Rank | Sort::LastActive => HashMap::new(),
+        };
+        rows.sort_by(|(a_app, a_win, a), (b_app, b_win, b)| {
+            let order = match self.config.sort {
+                Sort::Rank => std::cmp::Ordering::Equal,
+                Sort::LastActive => manager.last_active(b.wid).cmp(&manager.last_active(a.wid)),
+                Sort::Dock | Sort::Launched => {
+                    let (a_order, a_name) = app_order[&a.pid];
+                    let (b_order, b_name) = app_order[&b.pid];
+                    a_order
+                        .total_cmp(&b_order)
+                        .then_with(|| a_name.cmp(b_name))
+                        .then_with(|| a.pid.cmp(&b.pid))
+                }
+            };
+            order
+                .then_with(|| b.score.total().cmp(&a.score.total()))
+                .then_with(|| a_app.name.cmp(&b_app.name))
+                .then_with(|| a_win.title.cmp(&b_win.title))
+        });
+        // Windows that were listed stay put, new matches go after them in rank order
+        if let Some(order) = self.frozen_order {
+            rows.sort_by_key(|(_, _, item)| order.get(&item.wid).copied().unwrap_or(usize::MAX));
+        }
+
+        // Nothing typed yet, so the last used windows go first and Enter flips back to one
+        if self.query.trim().is_empty() && self.config.recent > 0 {
+            let current = manager.front_window();
+            let mut recent: Vec<(std::time::SystemTime, u32)> = rows
+                .iter()
+                .filter(|(_, _, item)| Some(item.wid) != current)
+                .filter_map(|(_, _, item)| Some((manager.last_active(item.wid)?, item.wid)))
+                .collect();
+            recent.sort_unstable_by(|a, b| b.cmp(a));
+            recent.truncate(self.config.recent);
+            for (_, wid) in &recent {
+                if let Some(pos) = rows.iter().position(|(_, _, item)| item.wid == *wid) {
+                    items.push(rows.remove(pos).2);
+                }
+            }
+            recent_count = items.len();
+        }
+
+        if self.config.merge_duplicates {
+            // Sorted by score, so the best-ranked window of each (app, title) is the one kept
+            let mut seen: HashMap<(i32, &str), usize> = HashMap::new();
+            for (_, win, item) in rows {
+                let key = (item.pid, win.title.as_str());
+                match seen.get(&key) {
+                    Some(&idx) => items[idx].duplicates += 1,
+                    None => {
+                        seen.insert(key, items.len());
+                        items.push(item);
+                    }
+                }
+            }
+        } else {
+            items.extend(rows.into_iter().map(|(_, _, item)| item));
+        }
+        (items, recent_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    /// What the picker lists for `query` over the two-display fixture, a row per line with the
+    /// matched runs of app name and title in brackets.
+    fn listing(query: &str, chips: &[Chip], setup: impl FnOnce(&mut config::Config)) -> String {
+        listing_with(query, chips, setup, |_| {}, &[])
+    }
+
+    /// [`listing`] with the refreshed manager handed to `prepare`, and `dock` as the Dock's
+    /// bundle ids.
+    fn listing_with(
+        query: &str,
+        chips: &[Chip],
+        setup: impl FnOnce(&mut config::Config),
+        prepare: impl FnOnce(&mut windows::Manager),
+        dock: &[String],
+    ) -> String {
+        let mut config = config::Config::default();
+        setup(&mut config);
+        let mut manager = windows::Manager::default();
+        manager.refresh_from(&fixture::two_displays()).unwrap();
+        prepare(&mut manager);
+
+        let parsed = Query::parse(query);
+        let mut engine = matching::Engine::new();
+        engine.set_app_matchers(&config.matchers);
+        engine.set_windows(&manager);
+        engine.set_pattern(&parsed.text);
+        while engine.tick(10) {}
+        let mut scores = HashMap::new();
+        engine.scores(&mut scores);
+
+        let chips = chips.iter().copied().collect();
+        let stats = Stats::default();
+        let scripts = Scripts::new();
+        let filter = Filter {
+            query,
+            manager: &manager,
+            scores: &scores,
+            chips: &chips,
+            frozen_order: None,
+            stats: &stats,
+            scripts: &scripts,
+            config: &config,
+            dock,
+        };
+        let (items, recent_count) = filter.items(&parsed);
+
+        let marked = |runs: Vec<(String, bool)>| -> String {
+            runs.into_iter()
+                .map(|(run, matched)| if matched { format!("[{run}]") } else { run })
+                .collect()
+        };
+        let mut matcher = nucleo::Matcher::new(nucleo::Config::DEFAULT);
+        let mut lines = String::new();
+        for (row, item) in items.iter().enumerate() {
+            let (app, window) = item.resolve(&manager).unwrap();
+            let indices: HashSet<usize> = engine
+                .indices(&mut matcher, item.wid)
+                .into_iter()
+                .map(|i| i as usize)
+                .collect();
+            let name: Vec<char> = app.name.chars().collect();
+            let title: Vec<char> = window.title.chars().collect();
+            let visible = truncate(title.len(), config.max_title_chars, config.title_ellipsis);
+            lines += &format!(
+                "{}{} \"{}\"\n",
+                if row < recent_count { "recent " } else { "" },
+                marked(highlight_runs(
+                    &name,
+                    (0..name.len()).map(Some),
+                    0,
+                    &indices
+                )),
+                marked(highlight_runs(&title, visible, name.len() + 1, &indices)),
+            );
+        }
+        lines
+    }
+
+    #[test]
+    fn empty_query_lists_everything_by_name() {
+        assert_eq!(
+            listing("", &[], |_| {}),
+            "Finder \"code\"\n\
+             Notes \"Groceries\"\n\
+             Safari \"\"\n\
+             Safari \"Keynote stream\"\n\
+             Safari \"Release notes\"\n\
+             Terminal \"zsh\"\n"
+        );
+    }
+
+    #[test]
+    fn fuzzy_matches_span_app_and_title() {
+        assert_eq!(
+            listing("notes", &[], |_| {}),
+            "[Notes] \"Groceries\"\n\
+             Safari \"Release [notes]\"\n\
+             Safari \"Key[note] [s]tream\"\n"
+        );
+    }
+
+    #[test]
+    fn every_token_has_to_match() {
+        assert_eq!(
+            listing("saf rel", &[], |_| {}),
+            "[Saf]ari \"[Rel]ease notes\"\n"
+        );
+    }
+
+    #[test]
+    fn case_sensitive_apps_need_the_exact_case() {
+        let listed = listing("notes", &[], |config| {
+            let matcher = config::AppMatcher {
+                case_sensitive: true,
+                ..Default::default()
+            };
+            config
+                .matchers
+                .insert("com.apple.Notes".to_string(), matcher);
+        });
+        assert_eq!(
+            listed,
+            "Safari \"Release [notes]\"\n\
+             Safari \"Key[note] [s]tream\"\n"
+        );
+    }
+
+    #[test]
+    fn boosts_outrank_better_matches() {
+        let listed = listing("notes", &[], |config| {
+            config.boosts.insert("com.apple.Safari".to_string(), 1000);
+        });
+        assert_eq!(
+            listed,
+            "Safari \"Release [notes]\"\n\
+             Safari \"Key[note] [s]tream\"\n\
+             [Notes] \"Groceries\"\n"
+        );
+    }
+
+    #[test]
+    fn operators_and_chips_filter() {
+        assert_eq!(listing("is:hidden", &[], |_| {}), "Terminal \"zsh\"\n");
+        assert_eq!(
+            listing("", &[Chip::Minimized], |_| {}),
+            "Notes \"Groceries\"\n"
+        );
+        assert_eq!(
+            listing("", &[Chip::CurrentDisplay], |_| {}),
+            "Notes \"Groceries\"\n\
+             Terminal \"zsh\"\n"
+        );
+    }
+
+    #[test]
+    fn each_sort_mode_has_its_own_order() {
+        let sorted = |sort, prepare: fn(&mut windows::Manager), dock: &[String]| {
+            let setup = |config: &mut config::Config| {
+                config.sort = sort;
+                config.recent = 0;
+            };
+            listing_with("", &[], setup, prepare, dock)
+        };
+
+        // Nothing typed, so the rank ties and names decide
+        assert_eq!(
+            sorted(Sort::Rank, |_| {}, &[]),
+            "Finder \"code\"\n\
+             Notes \"Groceries\"\n\
+             Safari \"\"\n\
+             Safari \"Keynote stream\"\n\
+             Safari \"Release notes\"\n\
+             Terminal \"zsh\"\n"
+        );
+
+        // The refresh marks the front window (Release notes), these come after it
+        let focused = |manager: &mut windows::Manager| {
+            for wid in [202, 105] {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                manager.mark_active(wid);
+            }
+        };
+        assert_eq!(
+            sorted(Sort::LastActive, focused, &[]),
+            "Finder \"code\"\n\
+             Notes \"Groceries\"\n\
+             Safari \"Release notes\"\n\
+             Safari \"\"\n\
+             Safari \"Keynote stream\"\n\
+             Terminal \"zsh\"\n"
+        );
+
+        let dock = ["com.apple.Terminal", "com.apple.Safari"].map(String::from);
+        assert_eq!(
+            sorted(Sort::Dock, |_| {}, &dock),
+            "Terminal \"zsh\"\n\
+             Safari \"\"\n\
+             Safari \"Keynote stream\"\n\
+             Safari \"Release notes\"\n\
+             Finder \"code\"\n\
+             Notes \"Groceries\"\n"
+        );
+
+        // Every fixture app is the test process, launched at the same time, so by pid
+        assert_eq!(
+            sorted(Sort::Launched, |_| {}, &[]),
+            "Safari \"\"\n\
+             Safari \"Keynote stream\"\n\
+             Safari \"Release notes\"\n\
+             Notes \"Groceries\"\n\
+             Finder \"code\"\n\
+             Terminal \"zsh\"\n"
+        );
+    }
+
+    #[test]
+    fn highlights_survive_truncation() {
+        let listed = listing("notes", &[], |config| {
+            config.max_title_chars = 8;
+            config.title_ellipsis = Ellipsis::Middle;
+        });
+        assert_eq!(
+            listed,
+            "[Notes] \"Groc…ies\"\n\
+             Safari \"Rele…[tes]\"\n\
+             Safari \"Key[n]…eam\"\n"
+        );
+    }
 }
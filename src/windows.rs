@@ -1,65 +1,649 @@
+use crate::config::{Config, FocusMode, Layers};
+use crate::crash::log;
 use crate::macos::{self, _SLPSSetFrontProcessWithOptions, ProcessSerialNumber, make_key_window};
+use crate::slow_apps::SlowApps;
+use crate::stats::app_key;
+use std::cell::{Cell, OnceCell};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result, anyhow};
 
 use objc2::rc::Retained;
-use objc2_app_kit::{NSApplicationActivationPolicy, NSRunningApplication, NSWorkspace};
-#[allow(deprecated)]
-use objc2_application_services::{AXUIElement, GetProcessForPID};
-use objc2_core_foundation::{CFString, CGPoint, CGRect};
+use objc2_app_kit::{
+    NSApplicationActivationOptions, NSApplicationActivationPolicy, NSRunningApplication,
+    NSWorkspace,
+};
+use objc2_application_services::{AXError, AXUIElement};
+use objc2_core_foundation::{CFArray, CFRetained, CFString, CGPoint, CGRect, CGSize};
 use objc2_core_graphics::{CGError, CGWarpMouseCursorPosition};
+use objc2_foundation::{NSString, NSURL};
+
+/// What a refresh needs to know about a running app.
+pub struct RunningApp {
+    pub handle: Retained<NSRunningApplication>,
+    pub pid: i32,
+    pub name: String,
+    pub bundle_id: Option<String>,
+    pub policy: NSApplicationActivationPolicy,
+    pub hidden: bool,
+}
+
+/// Where [`Manager::refresh_from`] gets the window server's and the apps' state: [`Live`]
+/// asks macOS, [`Public`] does without private APIs, tests replay a recorded
+/// [`crate::fixture::Fixture`].
+pub trait System {
+    /// SLSCopyManagedDisplaySpaces
+    fn display_spaces(&self) -> CFRetained<CFArray<macos::CFDict>>;
+    fn spans_displays(&self) -> bool;
+    fn windows_on_space(&self, space_id: u64) -> Vec<u32>;
+    /// CGWindowListCopyWindowInfo
+    fn window_list(&self) -> Option<CFRetained<CFArray<macos::CFDict>>>;
+    fn running_apps(&self) -> Vec<RunningApp>;
+    fn display_names(&self) -> HashMap<String, String>;
+    /// Global top-left-origin frame of each display, by UUID
+    fn display_frames(&self) -> Vec<(String, CGRect)>;
+    fn active_space(&self) -> u64;
+    fn cursor_display(&self) -> Option<String>;
+    /// See [`macos::resolve_ax_for_pid`]
+    fn resolve_windows(
+        &self,
+        pid: i32,
+        wids: &HashSet<u32>,
+        budget: Duration,
+    ) -> (HashMap<u32, Retained<AXUIElement>>, bool);
+    /// Minimized and fullscreen, hidden is up to the app
+    fn window_state(&self, wid: u32, element: &AXUIElement) -> WindowState;
+    /// AXDocument, a file URL
+    fn document(&self, wid: u32, element: &AXUIElement) -> Option<String>;
+    /// Whether Accessibility is granted. Without it no AX element turns up, so windows get
+    /// listed app-level only
+    fn ax_trusted(&self) -> bool;
+}
+
+pub struct Live;
+
+impl System for Live {
+    fn display_spaces(&self) -> CFRetained<CFArray<macos::CFDict>> {
+        macos::copy_managed_display_spaces()
+    }
+
+    fn spans_displays(&self) -> bool {
+        macos::displays_span_spaces()
+    }
+
+    fn windows_on_space(&self, space_id: u64) -> Vec<u32> {
+        macos::windows_on_space(space_id)
+    }
+
+    fn window_list(&self) -> Option<CFRetained<CFArray<macos::CFDict>>> {
+        macos::copy_window_list()
+    }
+
+    fn running_apps(&self) -> Vec<RunningApp> {
+        NSWorkspace::sharedWorkspace()
+            .runningApplications()
+            .iter()
+            .filter(|app| !app.isTerminated())
+            .map(|app| RunningApp {
+                pid: app.processIdentifier(),
+                name: app
+                    .localizedName()
+                    .map(|n| n.to_string())
+                    .unwrap_or_default(),
+                bundle_id: app.bundleIdentifier().map(|b| b.to_string()),
+                policy: app.activationPolicy(),
+                hidden: app.isHidden(),
+                handle: app,
+            })
+            .collect()
+    }
+
+    fn display_names(&self) -> HashMap<String, String> {
+        macos::display_names()
+    }
+
+    fn display_frames(&self) -> Vec<(String, CGRect)> {
+        macos::display_frames()
+    }
+
+    fn active_space(&self) -> u64 {
+        unsafe { macos::SLSGetActiveSpace(macos::SLSMainConnectionID()) }
+    }
+
+    fn cursor_display(&self) -> Option<String> {
+        macos::display_uuid_at_cursor()
+    }
+
+    fn resolve_windows(
+        &self,
+        pid: i32,
+        wids: &HashSet<u32>,
+        budget: Duration,
+    ) -> (HashMap<u32, Retained<AXUIElement>>, bool) {
+        macos::resolve_ax_for_pid(pid, wids, budget)
+    }
+
+    fn window_state(&self, _wid: u32, element: &AXUIElement) -> WindowState {
+        WindowState {
+            minimized: macos::ax_flag(element, "AXMinimized"),
+            fullscreen: macos::ax_flag(element, "AXFullScreen"),
+            hidden: false,
+        }
+    }
+
+    fn document(&self, _wid: u32, element: &AXUIElement) -> Option<String> {
+        let value = macos::get_attribute(element, "AXDocument")?;
+        value.downcast::<CFString>().ok().map(|s| s.to_string())
+    }
+
+    fn ax_trusted(&self) -> bool {
+        macos::accessibility_granted()
+    }
+}
+
+/// What [`Live`] gets from the window server, read up front so it can be done off the main
+/// thread. Apps, display names and AX are still asked when it's applied.
+pub struct LiveSnapshot {
+    display_spaces: CFRetained<CFArray<macos::CFDict>>,
+    spans_displays: bool,
+    space_windows: HashMap<u64, Vec<u32>>,
+    window_list: Option<CFRetained<CFArray<macos::CFDict>>>,
+    active_space: u64,
+    cursor_display: Option<String>,
+}
+
+impl LiveSnapshot {
+    fn take() -> Self {
+        let display_spaces = Live.display_spaces();
+        let spans_displays = Live.spans_displays();
+        let space_windows = macos::parse_display_spaces(&display_spaces, spans_displays)
+            .into_iter()
+            .flat_map(|(_, spaces)| spaces)
+            .map(|space| (space.id, Live.windows_on_space(space.id)))
+            .collect();
+        Self {
+            display_spaces,
+            spans_displays,
+            space_windows,
+            window_list: Live.window_list(),
+            active_space: Live.active_space(),
+            cursor_display: Live.cursor_display(),
+        }
+    }
+}
+
+impl System for LiveSnapshot {
+    fn display_spaces(&self) -> CFRetained<CFArray<macos::CFDict>> {
+        self.display_spaces.clone()
+    }
+
+    fn spans_displays(&self) -> bool {
+        self.spans_displays
+    }
+
+    fn windows_on_space(&self, space_id: u64) -> Vec<u32> {
+        self.space_windows
+            .get(&space_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn window_list(&self) -> Option<CFRetained<CFArray<macos::CFDict>>> {
+        self.window_list.clone()
+    }
+
+    fn running_apps(&self) -> Vec<RunningApp> {
+        Live.running_apps()
+    }
+
+    fn display_names(&self) -> HashMap<String, String> {
+        Live.display_names()
+    }
+
+    fn display_frames(&self) -> Vec<(String, CGRect)> {
+        Live.display_frames()
+    }
+
+    fn active_space(&self) -> u64 {
+        self.active_space
+    }
+
+    fn cursor_display(&self) -> Option<String> {
+        self.cursor_display.clone()
+    }
+
+    fn resolve_windows(
+        &self,
+        pid: i32,
+        wids: &HashSet<u32>,
+        budget: Duration,
+    ) -> (HashMap<u32, Retained<AXUIElement>>, bool) {
+        Live.resolve_windows(pid, wids, budget)
+    }
+
+    fn window_state(&self, wid: u32, element: &AXUIElement) -> WindowState {
+        Live.window_state(wid, element)
+    }
+
+    fn document(&self, wid: u32, element: &AXUIElement) -> Option<String> {
+        Live.document(wid, element)
+    }
+
+    fn ax_trusted(&self) -> bool {
+        Live.ax_trusted()
+    }
+}
+
+/// What can be found out with public APIs alone (window list, AX, CoreGraphics displays),
+/// for the hardened runtime and App Sandbox. There are no spaces to go by, so each display
+/// gets one made-up space for its windows on screen and one for the rest (other spaces,
+/// minimized). Window ids come from matching AX frames against the window list.
+pub struct Public {
+    window_list: Option<CFRetained<CFArray<macos::CFDict>>>,
+    /// Display UUID with its made-up on-screen and off-screen space ids
+    displays: Vec<(String, [u64; 2])>,
+    space_windows: HashMap<u64, Vec<u32>>,
+    bounds: HashMap<u32, CGRect>,
+    cursor_display: Option<String>,
+    ax_trusted: bool,
+}
+
+impl Public {
+    pub fn snapshot() -> Self {
+        let window_list = macos::copy_window_list();
+        let frames = macos::display_frames();
+        let ax_trusted = macos::accessibility_granted();
+        let displays: Vec<(String, [u64; 2])> = frames
+            .iter()
+            .zip(1..)
+            .map(|((uuid, _), n)| (uuid.clone(), [2 * n - 1, 2 * n]))
+            .collect();
+
+        let mut space_windows: HashMap<u64, Vec<u32>> = HashMap::new();
+        let mut bounds = HashMap::new();
+        let listed = window_list.as_deref().map(macos::window_frames);
+        for (wid, frame, on_screen) in listed.unwrap_or_default() {
+            // Without AX there's no telling real windows from offscreen helpers
+            if !on_screen && !ax_trusted {
+                continue;
+            }
+            let display = macos::display_of(&frames, frame).unwrap_or_default();
+            let Some((_, spaces)) = displays.get(display) else {
+                continue;
+            };
+            let space = spaces[usize::from(!on_screen)];
+            space_windows.entry(space).or_default().push(wid);
+            bounds.insert(wid, frame);
+        }
+
+        Self {
+            window_list,
+            displays,
+            space_windows,
+            bounds,
+            cursor_display: macos::display_uuid_at_cursor(),
+            ax_trusted,
+        }
+    }
+}
+
+impl System for Public {
+    fn display_spaces(&self) -> CFRetained<CFArray<macos::CFDict>> {
+        // Type -1 so none of them gets numbered like a desktop
+        let displays: Vec<serde_json::Value> = self
+            .displays
+            .iter()
+            .map(|(uuid, spaces)| {
+                serde_json::json!({
+                    "Display Identifier": uuid,
+                    "Spaces": spaces.map(|id| serde_json::json!({ "id64": id, "type": -1 })),
+                })
+            })
+            .collect();
+        let displays = macos::cf_from_json(&serde_json::Value::Array(displays))
+            .and_then(|displays| displays.downcast::<CFArray>().ok())
+            .expect("displays are an array");
+        unsafe { CFRetained::cast_unchecked(displays) }
+    }
+
+    fn spans_displays(&self) -> bool {
+        false
+    }
+
+    fn windows_on_space(&self, space_id: u64) -> Vec<u32> {
+        self.space_windows
+            .get(&space_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn window_list(&self) -> Option<CFRetained<CFArray<macos::CFDict>>> {
+        self.window_list.clone()
+    }
+
+    fn running_apps(&self) -> Vec<RunningApp> {
+        Live.running_apps()
+    }
+
+    fn display_names(&self) -> HashMap<String, String> {
+        Live.display_names()
+    }
+
+    fn display_frames(&self) -> Vec<(String, CGRect)> {
+        Live.display_frames()
+    }
+
+    /// The on-screen space of the display under the cursor
+    fn active_space(&self) -> u64 {
+        self.displays
+            .iter()
+            .find(|(uuid, _)| self.cursor_display.as_ref() == Some(uuid))
+            .map(|(_, spaces)| spaces[0])
+            .unwrap_or_default()
+    }
+
+    fn cursor_display(&self) -> Option<String> {
+        self.cursor_display.clone()
+    }
+
+    /// Never times out, AX lists an app's windows in one go.
+    fn resolve_windows(
+        &self,
+        pid: i32,
+        wids: &HashSet<u32>,
+        _budget: Duration,
+    ) -> (HashMap<u32, Retained<AXUIElement>>, bool) {
+        let mut resolved = HashMap::new();
+        if !self.ax_trusted {
+            return (resolved, false);
+        }
+        for element in macos::ax_windows(pid) {
+            if !macos::is_window(&element) {
+                continue;
+            }
+            let Some(frame) = macos::ax_frame(&element) else {
+                continue;
+            };
+            // Windows stacked exactly on top of each other can get swapped, nothing else
+            // tells them apart without private APIs
+            let wid = wids.iter().copied().find(|wid| {
+                !resolved.contains_key(wid)
+                    && self.bounds.get(wid).is_some_and(|b| same_frame(*b, frame))
+            });
+            if let Some(wid) = wid {
+                resolved.insert(wid, element);
+            }
+        }
+        (resolved, false)
+    }
+
+    fn window_state(&self, wid: u32, element: &AXUIElement) -> WindowState {
+        Live.window_state(wid, element)
+    }
+
+    fn document(&self, wid: u32, element: &AXUIElement) -> Option<String> {
+        Live.document(wid, element)
+    }
+
+    fn ax_trusted(&self) -> bool {
+        self.ax_trusted
+    }
+}
+
+/// The window server's side of a refresh, taken with [`Snapshot::take`] (on any thread) and
+/// applied with [`Manager::apply`] on the main thread.
+pub enum Snapshot {
+    Live(LiveSnapshot),
+    Public(Public),
+}
+
+// Both only hold immutable CF collections and plain data, which are fine to hand around
+unsafe impl Send for Snapshot {}
+unsafe impl Sync for Snapshot {}
+
+impl Snapshot {
+    pub fn take(private_apis: bool) -> Self {
+        if private_apis {
+            Self::Live(LiveSnapshot::take())
+        } else {
+            Self::Public(Public::snapshot())
+        }
+    }
+}
+
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Live(_) => "Snapshot::Live",
+            Self::Public(_) => "Snapshot::Public",
+        })
+    }
+}
+
+/// Equal up to rounding, AX and the window list don't always agree on fractions.
+fn same_frame(a: CGRect, b: CGRect) -> bool {
+    (a.origin.x - b.origin.x).abs() < 1.
+        && (a.origin.y - b.origin.y).abs() < 1.
+        && (a.size.width - b.size.width).abs() < 1.
+        && (a.size.height - b.size.height).abs() < 1.
+}
 
 #[derive(Default)]
 pub struct Manager {
     app_map: HashMap<i32, App>,
     ax_cache: HashMap<u32, Retained<AXUIElement>>,
-    icon_cache: HashMap<i32, macos::IconData>,
+    /// PNG-compressed, a long-running process collects a lot of them
+    icon_cache: HashMap<i32, Vec<u8>>,
+    /// By pid, dropped when the app quits since pids get reused
+    psn_cache: HashMap<i32, ProcessSerialNumber>,
+    z_order: Vec<u32>,
+    display_names: HashMap<String, String>,
+    /// Space id to its number on its display, counting from 1
+    space_numbers: HashMap<u64, usize>,
+    /// Space and display the user was on at refresh
+    active_space: u64,
+    cursor_display: Option<String>,
+    layers: Layers,
+    accessory_apps: Vec<String>,
+    /// Bundle ids or app names whose windows are left out
+    exclude_apps: Vec<String>,
+    /// Lowercased title globs of windows to leave out
+    exclude_titles: Vec<String>,
+    /// Icon size in points
+    icon_size: u16,
+    /// Of the display the picker is on, None for the main display's
+    backing_scale: Option<f64>,
+    /// Icon size in pixels
+    icon_pixels: f64,
+    /// Most time spent finding one app's windows
+    lookup_budget: Duration,
+    focus_mode: FocusMode,
+    private_apis: bool,
+    slow_apps: SlowApps,
+    /// Slow apps aren't saved, see [`Manager::read_only`]
+    read_only: bool,
+    /// When windows were last seen in front, kept only for listed windows
+    last_active: HashMap<u32, SystemTime>,
 }
 
 impl Manager {
-    pub fn new() -> Result<Self> {
-        let mut m = Self::default();
-        m.refresh()?;
-        Ok(m)
+    /// An empty manager, filled by [`Manager::refresh`].
+    pub fn new(config: &Config) -> Self {
+        let mut m = Self {
+            slow_apps: SlowApps::load(),
+            ..Self::default()
+        };
+        m.configure(config);
+        m
+    }
+
+    /// Keeps refreshes from saving anything, slow apps stay in memory.
+    pub fn read_only(&mut self) {
+        self.read_only = true;
+    }
+
+    /// Picks up which windows to list from the config, applied on the next refresh.
+    pub fn configure(&mut self, config: &Config) {
+        self.layers = config.layers.clone();
+        self.accessory_apps = config.accessory_apps.clone();
+        self.exclude_apps = config.exclude_apps.clone();
+        self.exclude_titles = config
+            .exclude_titles
+            .iter()
+            .map(|g| g.to_lowercase())
+            .collect();
+        self.lookup_budget = config.ax.lookup();
+        self.focus_mode = config.focus_mode;
+        let private_apis = config.private_apis && macos::private_apis_available();
+        if config.private_apis && !private_apis && self.private_apis != private_apis {
+            log!("[warn] private APIs aren't available here, sticking to public ones");
+        }
+        self.private_apis = private_apis;
+        macos::set_ax_timeout(config.ax.timeout());
+
+        self.icon_size = config.icon_size();
+        self.update_icon_pixels();
+    }
+
+    /// Rasterizes icons for a display with this scale from the next refresh on. Returns
+    /// whether that changes anything.
+    pub fn set_backing_scale(&mut self, scale: f64) -> bool {
+        self.backing_scale = Some(scale);
+        self.update_icon_pixels()
+    }
+
+    fn update_icon_pixels(&mut self) -> bool {
+        let scale = self.backing_scale.unwrap_or_else(macos::backing_scale);
+        let icon_pixels = f64::from(self.icon_size) * scale;
+        if icon_pixels == self.icon_pixels {
+            return false;
+        }
+        self.icon_pixels = icon_pixels;
+        self.icon_cache.clear();
+        true
+    }
+
+    /// Makes the next refresh look up every window's AX element again.
+    pub fn forget_ax_elements(&mut self) {
+        self.ax_cache.clear();
     }
 
     pub fn refresh(&mut self) -> Result<()> {
-        let visible = macos::get_visible_window_ids();
-        let window_infos =
-            macos::get_window_info_list(&visible).context("Failed to get window info list")?;
+        if self.private_apis {
+            self.refresh_from(&Live)
+        } else {
+            self.refresh_from(&Public::snapshot())
+        }
+    }
+
+    /// [`Manager::refresh`] from a snapshot that was taken elsewhere.
+    pub fn apply(&mut self, snapshot: &Snapshot) -> Result<()> {
+        match snapshot {
+            Snapshot::Live(live) => self.refresh_from(live),
+            Snapshot::Public(public) => self.refresh_from(public),
+        }
+    }
+
+    /// [`Manager::refresh`] from whatever `system` reports, a recorded fixture in tests.
+    pub fn refresh_from(&mut self, system: &impl System) -> Result<()> {
+        let spans = system.spans_displays();
+        let displays = macos::parse_display_spaces(&system.display_spaces(), spans);
+        let visible = macos::locate_windows(&displays, |space| system.windows_on_space(space));
+        let window_list = system
+            .window_list()
+            .context("Failed to get window info list")?;
+        let mut window_infos = macos::parse_window_list(&window_list, &visible);
+        let running_apps = system.running_apps();
+        // Never list ourselves (the picker, anything else we open), whatever our activation policy
+        let own_pid = std::process::id() as i32;
+        window_infos.retain(|info| info.pid != own_pid && self.layer_included(info, &running_apps));
+        if !self.exclude_titles.is_empty() {
+            window_infos.retain(|info| {
+                let title = info.title.to_lowercase();
+                !self
+                    .exclude_titles
+                    .iter()
+                    .any(|glob| glob_matches(glob, &title))
+            });
+        }
+        // Shared spaces put every window on the one "Main" entry, its frame tells the display
+        if spans {
+            let frames = system.display_frames();
+            for info in &mut window_infos {
+                info.display_uuid = info
+                    .bounds
+                    .and_then(|bounds| macos::display_of(&frames, bounds))
+                    .map(|idx| frames[idx].0.clone());
+            }
+        }
+
+        self.display_names = system.display_names();
+        let desktops = macos::desktop_spaces(&displays).into_iter();
+        // Shared spaces count up across every display, separate ones restart on each
+        self.space_numbers = if spans {
+            desktops.flat_map(|(_, spaces)| spaces).zip(1..).collect()
+        } else {
+            desktops
+                .flat_map(|(_, spaces)| spaces.into_iter().zip(1..))
+                .collect()
+        };
+        self.active_space = system.active_space();
+        self.cursor_display = system.cursor_display();
+
+        // Window infos come back front-to-back
+        self.z_order = window_infos.iter().map(|w| w.id).collect();
 
         let active_pids: HashSet<i32> = window_infos.iter().map(|w| w.pid).collect();
         let active_wids: HashSet<u32> = window_infos.iter().map(|w| w.id).collect();
 
         let mut new_app_map = HashMap::new();
-        let ws = NSWorkspace::sharedWorkspace();
-        for app in ws.runningApplications() {
-            let pid = app.processIdentifier();
-            if !active_pids.contains(&pid) {
+        let mut hidden_pids = HashSet::new();
+        for app in &running_apps {
+            let pid = app.pid;
+            if !active_pids.contains(&pid) || !self.policy_included(app) {
                 continue;
             }
-            if app.activationPolicy() != NSApplicationActivationPolicy::Regular
-                || app.isTerminated()
-            {
-                continue;
+            if app.hidden {
+                hidden_pids.insert(pid);
             }
-            let name = app
-                .localizedName()
-                .map(|n| n.to_string())
-                .unwrap_or_default();
 
             if !self.icon_cache.contains_key(&pid)
-                && let Some(data) = app.icon().and_then(|icon| macos::ns_image_to_rgba(&icon))
+                && let Some(data) = app
+                    .handle
+                    .icon()
+                    .and_then(|icon| macos::ns_image_to_rgba(&icon, self.icon_pixels))
+                    .and_then(|icon| icon.to_png())
             {
                 self.icon_cache.insert(pid, data);
             }
 
-            new_app_map.insert(pid, App::new(app.clone(), name));
+            if !self.psn_cache.contains_key(&pid)
+                && let Some(psn) = macos::psn_for_pid(pid)
+            {
+                self.psn_cache.insert(pid, psn);
+            }
+
+            new_app_map.insert(
+                pid,
+                App {
+                    app: app.handle.clone(),
+                    pid,
+                    name: app.name.clone(),
+                    bundle_id: app.bundle_id.clone(),
+                    windows: Vec::new(),
+                },
+            );
         }
 
         self.ax_cache.retain(|wid, _| active_wids.contains(wid));
+        self.last_active.retain(|wid, _| active_wids.contains(wid));
+        // Catches switches within an app, which don't activate anything
+        if let Some(front) = self.front_window() {
+            self.last_active.insert(front, SystemTime::now());
+        }
         self.icon_cache.retain(|pid, _| active_pids.contains(pid));
+        self.psn_cache.retain(|pid, _| active_pids.contains(pid));
 
         let mut uncached_by_pid: HashMap<i32, HashSet<u32>> = HashMap::new();
         for info in &window_infos {
@@ -68,85 +652,521 @@ impl Manager {
             }
         }
 
+        // Every lookup would come back empty (or time out and get the app marked slow)
+        let ax_trusted = system.ax_trusted();
+        if !ax_trusted {
+            uncached_by_pid.clear();
+        }
+
+        let mut slow_apps_changed = false;
         for (pid, wids) in &uncached_by_pid {
-            let resolved = macos::resolve_ax_for_pid(*pid, wids);
+            let Some(app) = new_app_map.get(pid) else {
+                continue;
+            };
+            let key = app_key(app);
+            if self.slow_apps.is_slow(&key) {
+                continue;
+            }
+            // Small lookups should stay snappy, large ones get room to complete
+            let budget = Duration::from_millis(50 + 25 * wids.len() as u64).min(self.lookup_budget);
+            let (resolved, timed_out) = system.resolve_windows(*pid, wids, budget);
             self.ax_cache.extend(resolved);
+            slow_apps_changed |= self.slow_apps.record(&key, timed_out);
+            if self.slow_apps.is_slow(&key) {
+                log!(
+                    "[warn] AX lookups for {key} keep timing out, resolving its windows on demand"
+                );
+            }
+        }
+        if slow_apps_changed
+            && !self.read_only
+            && let Err(e) = self.slow_apps.save()
+        {
+            log!("[warn] could not save slow apps: {e:#}");
         }
 
         for info in window_infos {
-            if let Some(ax_element) = self.ax_cache.get(&info.id)
-                && let Some(app) = new_app_map.get_mut(&info.pid)
-            {
-                app.windows.push(Window {
-                    title: info.title,
-                    id: info.id,
-                    space_id: info.space_id,
-                    display_uuid: info.display_uuid,
-                    ax_element: ax_element.clone(),
-                });
+            let Some(app) = new_app_map.get_mut(&info.pid) else {
+                continue;
+            };
+            let ax_element = OnceCell::new();
+            match self.ax_cache.get(&info.id) {
+                Some(element) => {
+                    let _ = ax_element.set(element.clone());
+                }
+                None if !ax_trusted || self.slow_apps.is_slow(&app_key(app)) => {}
+                None => continue,
+            }
+            let mut window = Window {
+                title: info.title,
+                id: info.id,
+                pid: info.pid,
+                psn: self.psn_cache.get(&info.pid).copied(),
+                lookup_budget: self.lookup_budget,
+                focus_mode: self.focus_mode,
+                private_apis: self.private_apis,
+                app_level_only: !ax_trusted,
+                space_id: info.space_id,
+                display_uuid: info.display_uuid,
+                cached_bounds: info.bounds.map(|bounds| (bounds, Instant::now())),
+                folder: None,
+                state: WindowState::default(),
+                ax_element,
+                ax_missed: Cell::new(None),
+            };
+            if let Some(element) = window.ax_element.get() {
+                window.state = system.window_state(info.id, element);
+                // Finder titles are just the folder name, so the path tells them apart
+                if app.bundle_id.as_deref() == Some(FINDER) {
+                    window.folder = system
+                        .document(info.id, element)
+                        .as_deref()
+                        .and_then(folder_breadcrumb);
+                }
             }
+            window.state.hidden = hidden_pids.contains(&info.pid);
+            app.windows.push(window);
         }
 
         self.app_map = new_app_map;
         Ok(())
     }
 
+    /// Regular apps, plus accessory apps that were asked for, minus excluded ones.
+    fn policy_included(&self, app: &RunningApp) -> bool {
+        if self
+            .exclude_apps
+            .iter()
+            .any(|a| app.name == *a || app.bundle_id.as_ref() == Some(a))
+        {
+            return false;
+        }
+        match app.policy {
+            NSApplicationActivationPolicy::Regular => true,
+            NSApplicationActivationPolicy::Accessory => app
+                .bundle_id
+                .as_ref()
+                .is_some_and(|b| self.accessory_apps.contains(b)),
+            _ => false,
+        }
+    }
+
+    fn layer_included(&self, info: &macos::WindowInfo, running_apps: &[RunningApp]) -> bool {
+        if info.layer == 0 || self.layers.include.contains(&info.layer) {
+            return true;
+        }
+        if self.layers.apps.is_empty() {
+            return false;
+        }
+        running_apps
+            .iter()
+            .find(|app| app.pid == info.pid)
+            .is_some_and(|app| {
+                self.layers
+                    .apps
+                    .iter()
+                    .any(|a| app.name == *a || app.bundle_id.as_ref() == Some(a))
+            })
+    }
+
     pub const fn app_map(&self) -> &HashMap<i32, App> {
         &self.app_map
     }
 
-    pub fn get_icon(&self, pid: i32) -> Option<&macos::IconData> {
-        self.icon_cache.get(&pid)
+    /// The frontmost window as of the last refresh.
+    pub fn front_window(&self) -> Option<u32> {
+        self.z_order.first().copied()
+    }
+
+    /// When the window was last focused, as far as switcheroo noticed.
+    pub fn last_active(&self, wid: u32) -> Option<SystemTime> {
+        self.last_active.get(&wid).copied()
+    }
+
+    pub fn mark_active(&mut self, wid: u32) {
+        self.last_active.insert(wid, SystemTime::now());
+    }
+
+    pub fn app_terminated(&mut self, pid: i32) {
+        self.psn_cache.remove(&pid);
+    }
+
+    /// Marks the focused window of an app that just came to the front.
+    pub fn app_activated(&mut self, pid: i32) {
+        // The picker coming up activates switcheroo itself. Without private APIs there's no
+        // telling which window got focus, the next refresh catches it
+        if pid == std::process::id() as i32 || !self.private_apis {
+            return;
+        }
+        if let Some(wid) = macos::focused_window(pid) {
+            self.mark_active(wid);
+        }
+    }
+
+    /// Up to `n` listed windows in front-to-back order, a decent stand-in for recency.
+    pub fn recent_windows(&self, n: usize) -> Vec<(&App, &Window)> {
+        self.z_order
+            .iter()
+            .filter_map(|wid| self.window(*wid))
+            .take(n)
+            .collect()
+    }
+
+    pub fn window(&self, wid: u32) -> Option<(&App, &Window)> {
+        self.app_map
+            .values()
+            .find_map(|app| app.windows.iter().find(|w| w.id == wid).map(|w| (app, w)))
+    }
+
+    /// Name of the display the window is on, e.g. "LG UltraFine".
+    pub fn display_name(&self, window: &Window) -> Option<&str> {
+        let uuid = window.display_uuid.as_deref()?;
+        self.display_names.get(uuid).map(String::as_str)
+    }
+
+    /// Switches to the `number`th desktop (counting from 1 like Mission Control) of the display
+    /// whose name contains `display`, or of the display under the cursor.
+    pub fn switch_to_space(&self, number: usize, display: Option<&str>) -> Result<()> {
+        if !self.private_apis {
+            return Err(anyhow!("Switching spaces needs private_apis"));
+        }
+        let uuid = match display {
+            Some(name) => {
+                let name = name.to_lowercase();
+                self.display_names
+                    .iter()
+                    .find(|(_, n)| n.to_lowercase().contains(&name))
+                    .map(|(uuid, _)| uuid.clone())
+                    .ok_or_else(|| anyhow!("No display matching {name:?}"))?
+            }
+            None => macos::display_uuid_at_cursor().context("No display under the cursor")?,
+        };
+
+        let spans = macos::displays_span_spaces();
+        let displays = macos::user_spaces();
+        let (display_uuid, spaces) = displays
+            .iter()
+            .find(|(d, _)| spans || *d == uuid)
+            .ok_or_else(|| anyhow!("No spaces found for display {uuid}"))?;
+        let space = number
+            .checked_sub(1)
+            .and_then(|idx| spaces.get(idx))
+            .ok_or_else(|| anyhow!("No space {number}, there are {}", spaces.len()))?;
+
+        if display.is_some() {
+            macos::warp_to_display(&uuid);
+        }
+        macos::switch_to_space_instant(*space, display_uuid);
+        Ok(())
     }
+
+    /// The window's space number as Mission Control counts them, None for fullscreen spaces.
+    pub fn space_number(&self, window: &Window) -> Option<usize> {
+        self.space_numbers.get(&window.space_id).copied()
+    }
+
+    /// Whether the window is on the active space, as of the last refresh.
+    pub fn on_active_space(&self, window: &Window) -> bool {
+        window.space_id == self.active_space
+    }
+
+    /// Whether the window is on the display under the cursor, as of the last refresh.
+    pub fn on_cursor_display(&self, window: &Window) -> bool {
+        window.display_uuid.is_some() && window.display_uuid == self.cursor_display
+    }
+
+    /// Whether private APIs (SkyLight) are used, see [`macos::private_apis_available`].
+    pub const fn private_apis(&self) -> bool {
+        self.private_apis
+    }
+
+    /// Whether there's more than one display to tell apart.
+    pub fn multiple_displays(&self) -> bool {
+        self.display_names.len() > 1
+    }
+
+    /// Watches the listed windows for title changes, see [`macos::set_title_handler`].
+    /// Windows of [`SlowApps`] that haven't been resolved yet are left out.
+    pub fn watch_titles(&self) -> macos::TitleWatch {
+        macos::TitleWatch::new(self.app_map.iter().flat_map(|(pid, app)| {
+            app.windows
+                .iter()
+                .filter_map(|w| w.ax_element.get().map(|element| (*pid, w.id, &**element)))
+        }))
+    }
+
+    /// Updates a window's title in place, false if it isn't listed or didn't change.
+    pub fn set_title(&mut self, wid: u32, title: String) -> bool {
+        let window = self
+            .app_map
+            .values_mut()
+            .find_map(|app| app.windows.iter_mut().find(|w| w.id == wid));
+        match window {
+            Some(window) if window.title != title => {
+                window.title = title;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The app's icon as PNG, see [`macos::IconData::from_png`].
+    pub fn get_icon(&self, pid: i32) -> Option<&[u8]> {
+        self.icon_cache.get(&pid).map(Vec::as_slice)
+    }
+}
+
+const FINDER: &str = "com.apple.finder";
+
+/// "~ › code › switcheroo" for a folder's file URL.
+fn folder_breadcrumb(url: &str) -> Option<String> {
+    let path = NSURL::URLWithString(&NSString::from_str(url))?
+        .path()?
+        .to_string();
+    let home = std::env::var("HOME").unwrap_or_default();
+    let path = match path.strip_prefix(home.as_str()) {
+        Some(rest) if !home.is_empty() => format!("~{rest}"),
+        _ => path,
+    };
+    let breadcrumb = path
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" › ");
+    (!breadcrumb.is_empty()).then_some(breadcrumb)
+}
+
+/// Whether `text` matches `glob` as a whole, `*` being any run of chars and `?` any one.
+pub fn glob_matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut g, mut t) = (0, 0);
+    // Where to pick up after the last `*` if what followed it stops matching
+    let mut retry = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                retry = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match retry {
+                Some((star, from)) => {
+                    g = star + 1;
+                    t = from + 1;
+                    retry = Some((star, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
 }
 
 #[derive(Debug)]
 pub struct App {
     pub app: Retained<NSRunningApplication>,
-    #[allow(dead_code)]
     pub pid: i32,
     pub name: String,
+    pub bundle_id: Option<String>,
     pub windows: Vec<Window>,
 }
 
 impl App {
-    pub fn new(app: Retained<NSRunningApplication>, name: String) -> Self {
-        Self {
-            pid: app.processIdentifier(),
-            app,
-            name,
-            windows: Vec::new(),
-        }
+    /// Seconds since 1970, None for apps not launched through LaunchServices.
+    pub fn launched(&self) -> Option<f64> {
+        self.app
+            .launchDate()
+            .map(|date| date.timeIntervalSince1970())
     }
 }
 
+/// How long bounds from a refresh are trusted, windows rarely move while the picker is up
+const BOUNDS_TTL: Duration = Duration::from_secs(5);
+
+/// As of the last refresh, for `is:` in queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowState {
+    pub minimized: bool,
+    /// Its app is hidden (Cmd+H)
+    pub hidden: bool,
+    pub fullscreen: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct Window {
     pub title: String,
     pub id: u32,
     pub space_id: u64,
     pub display_uuid: Option<String>,
-    ax_element: Retained<AXUIElement>,
+    /// Breadcrumb of the folder a Finder window shows
+    pub folder: Option<String>,
+    pub state: WindowState,
+    /// Listed without Accessibility access, so focusing it just activates its app
+    pub app_level_only: bool,
+    pid: i32,
+    psn: Option<ProcessSerialNumber>,
+    lookup_budget: Duration,
+    focus_mode: FocusMode,
+    /// Whether focusing may go through SkyLight
+    private_apis: bool,
+    /// Frame from the window list at refresh, and when that was
+    cached_bounds: Option<(CGRect, Instant)>,
+    /// Left empty for [`SlowApps`] until something needs it
+    ax_element: OnceCell<Retained<AXUIElement>>,
+    /// When looking it up on demand came up empty, not tried again until the next refresh
+    ax_missed: Cell<Option<Instant>>,
 }
 
 impl Window {
-    pub fn focus(&self, app: &NSRunningApplication) -> Result<()> {
+    fn ax_element(&self) -> Option<&AXUIElement> {
+        if self.ax_element.get().is_none()
+            && !self.app_level_only
+            && self.ax_missed.get().is_none()
+        {
+            let found = if self.private_apis {
+                let wids = HashSet::from([self.id]);
+                let (mut resolved, _) =
+                    macos::resolve_ax_for_pid(self.pid, &wids, self.lookup_budget);
+                resolved.remove(&self.id)
+            } else {
+                // Same matching as [`Public::resolve_windows`]
+                let (frame, _) = self.cached_bounds?;
+                macos::ax_windows(self.pid)
+                    .into_iter()
+                    .find(|element| macos::ax_frame(element).is_some_and(|f| same_frame(f, frame)))
+            };
+            match found {
+                Some(element) => {
+                    let _ = self.ax_element.set(element);
+                }
+                None => {
+                    log!("[warn] could not find the AX element of window {}", self.id);
+                    self.ax_missed.set(Some(Instant::now()));
+                }
+            }
+        }
+        self.ax_element.get().map(|element| &**element)
+    }
+
+    /// File URL of the document shown in this window, if the app exposes one via AXDocument.
+    pub fn document_url(&self) -> Option<String> {
+        Live.document(self.id, self.ax_element()?)
+    }
+
+    /// Reveals the window's document in Finder, falling back to the owning app's bundle.
+    pub fn reveal_in_finder(&self, app: &NSRunningApplication) -> Result<()> {
+        let url = self
+            .document_url()
+            .and_then(|s| NSURL::URLWithString(&NSString::from_str(&s)))
+            .or_else(|| app.bundleURL())
+            .ok_or_else(|| anyhow!("No document or bundle URL to reveal"))?;
+
+        macos::reveal_in_finder(&url);
+        Ok(())
+    }
+
+    /// The window's frame, as of the last refresh if that was recent enough.
+    pub fn bounds(&self) -> Result<CGRect> {
+        if let Some((bounds, at)) = self.cached_bounds
+            && at.elapsed() < BOUNDS_TTL
+        {
+            return Ok(bounds);
+        }
+        if !self.private_apis {
+            return self
+                .ax_element()
+                .and_then(macos::ax_frame)
+                .or(self.cached_bounds.map(|(bounds, _)| bounds))
+                .context("Could not get window bounds");
+        }
         let cid = unsafe { macos::SLSMainConnectionID() };
         let mut rect = std::mem::MaybeUninit::<CGRect>::uninit();
-        let bounds = unsafe {
+        unsafe {
             let res = macos::SLSGetWindowBounds(cid, self.id, rect.as_mut_ptr());
             if res != CGError::Success {
                 return Err(anyhow!("Could not get window bounds"));
             }
-            rect.assume_init()
+            Ok(rect.assume_init())
+        }
+    }
+
+    /// Moves and resizes the window through AX, in global top-left-origin coordinates.
+    pub fn set_frame(&self, frame: CGRect) -> Result<()> {
+        let element = self.ax_element().context("No AX element for the window")?;
+        macos::set_ax_frame(element, frame)
+    }
+
+    /// Moves the window to the active space, centers it on the display under the cursor
+    /// and focuses it.
+    /// Without private APIs the window stays on its space and only gets centered.
+    pub fn bring_here(&self, app: &NSRunningApplication) -> Result<()> {
+        let mut active_space = self.space_id;
+        if self.private_apis {
+            let cid = unsafe { macos::SLSMainConnectionID() };
+            active_space = unsafe { macos::SLSGetActiveSpace(cid) };
+            if self.space_id != active_space {
+                macos::move_window_to_space(self.id, active_space);
+            }
+        }
+
+        if let Some((sx, sy, sw, sh)) = macos::active_display_frame_at_cursor() {
+            let (sx, sy, sw, sh) = (sx as f64, sy as f64, sw as f64, sh as f64);
+            let bounds = self.bounds()?;
+            let w = bounds.size.width.min(sw);
+            let h = bounds.size.height.min(sh);
+            self.set_frame(CGRect::new(
+                CGPoint::new(sx + (sw - w) / 2., sy + (sh - h) / 2.),
+                CGSize::new(w, h),
+            ))?;
+        }
+
+        // Already on this space now, so focusing must not switch away
+        let here = Self {
+            space_id: active_space,
+            // Just moved, so the frame from the refresh is off
+            cached_bounds: None,
+            ..self.clone()
         };
+        here.focus(app)
+    }
 
-        let center = CGPoint::new(
-            bounds.origin.x + bounds.size.width / 2.,
-            bounds.origin.y + bounds.size.height / 2.,
-        );
-        CGWarpMouseCursorPosition(center);
+    pub fn focus(&self, app: &NSRunningApplication) -> Result<()> {
+        // Nothing to pick the window with, the app comes forward with whichever it likes
+        if self.app_level_only {
+            return activate(app);
+        }
+        let bounds = self.bounds()?;
+
+        match self.focus_mode {
+            FocusMode::WarpCursor => {
+                let center = CGPoint::new(
+                    bounds.origin.x + bounds.size.width / 2.,
+                    bounds.origin.y + bounds.size.height / 2.,
+                );
+                CGWarpMouseCursorPosition(center);
+            }
+            FocusMode::Stay => {}
+            FocusMode::MoveWindow => {
+                // Not worth failing the focus over, it just stays where it is
+                if let Err(e) = self.move_to_cursor(bounds) {
+                    log!(
+                        "[warn] could not move window {} to the cursor: {e}",
+                        self.id
+                    );
+                }
+            }
+        }
+
+        // Activating the app has macOS switch to its space, with the usual animation
+        if !self.private_apis {
+            return self.focus_public(app);
+        }
 
+        let cid = unsafe { macos::SLSMainConnectionID() };
         if let Some(uuid) = self.display_uuid.as_deref() {
             macos::switch_to_space_instant(self.space_id, uuid);
             let deadline =
@@ -158,22 +1178,63 @@ impl Window {
                 std::thread::sleep(std::time::Duration::from_millis(5));
             }
         } else {
-            eprintln!(
+            log!(
                 "[warn] window {} has no display UUID; skipping space switch",
                 self.id
             );
         }
 
-        let pid = app.processIdentifier();
-        let mut psn = ProcessSerialNumber::default();
+        // The private route breaks now and then with new macOS releases
+        if let Err(e) = self.make_front() {
+            log!(
+                "[warn] SkyLight focus of window {} failed ({e}), using activate + AXRaise",
+                self.id
+            );
+            return self.focus_public(app);
+        }
+
+        self.raise();
+
+        Ok(())
+    }
 
-        #[allow(deprecated)]
-        let res = unsafe { GetProcessForPID(pid, (&mut psn as *mut _) as _) };
+    /// Focuses the window without moving the cursor, whatever the focus mode.
+    pub fn focus_in_place(&self, app: &NSRunningApplication) -> Result<()> {
+        let stay = Self {
+            focus_mode: FocusMode::Stay,
+            ..self.clone()
+        };
+        stay.focus(app)
+    }
 
-        if res != 0 {
-            return Err(anyhow!("Couldn't get PSN for PID"));
-        }
+    /// Focuses the window, then fills the visible part of the display it's on with it.
+    pub fn focus_and_maximize(&self, app: &NSRunningApplication) -> Result<()> {
+        self.focus(app)?;
+        let bounds = self.bounds()?;
+        let frame = macos::visible_frame_around(bounds).context("The window is on no display")?;
+        self.set_frame(frame)
+    }
 
+    /// Centers the window under the cursor, kept within the visible part of its display.
+    fn move_to_cursor(&self, bounds: CGRect) -> Result<()> {
+        let (x, y) = macos::cursor_location().context("No cursor location")?;
+        let frame = macos::visible_frame_at_cursor().context("No display under the cursor")?;
+        let clamp = |center: f64, size: f64, start: f64, len: f64| {
+            (center - size / 2.).min(start + len - size).max(start)
+        };
+        let origin = CGPoint::new(
+            clamp(x, bounds.size.width, frame.origin.x, frame.size.width),
+            clamp(y, bounds.size.height, frame.origin.y, frame.size.height),
+        );
+        let element = self.ax_element().context("No AX element for the window")?;
+        macos::set_ax_position(element, origin)
+    }
+
+    /// Brings the app forward with this exact window as key, through SkyLight.
+    fn make_front(&self) -> Result<()> {
+        let psn = self
+            .psn
+            .ok_or_else(|| anyhow!("Couldn't get PSN for PID"))?;
         let res = unsafe { _SLPSSetFrontProcessWithOptions(&psn, self.id, 0x200) };
         if res != CGError::Success {
             return Err(anyhow!("Setting front process failed with: {res:?}"));
@@ -183,11 +1244,164 @@ impl Window {
         if res != CGError::Success {
             return Err(anyhow!("Failed at setting key window."));
         }
+        Ok(())
+    }
 
-        unsafe {
-            AXUIElement::perform_action(&self.ax_element, &CFString::from_static_str("AXRaise"))
+    /// Public API fallback: activates the app, then raises the window and makes it main.
+    fn focus_public(&self, app: &NSRunningApplication) -> Result<()> {
+        activate(app)?;
+        // Without Accessibility access (App Sandbox) the app coming forward is all there is
+        let Some(element) = self.ax_element() else {
+            log!("[warn] no AX element to raise window {} with", self.id);
+            return Ok(());
         };
+        let res =
+            unsafe { AXUIElement::perform_action(element, &CFString::from_static_str("AXRaise")) };
+        if res != AXError::Success {
+            return Err(anyhow!("AXRaise failed with {res:?}"));
+        }
+        macos::set_ax_bool(element, "AXMain", true)
+    }
 
+    /// Presses the window's close button.
+    pub fn close(&self) -> Result<()> {
+        let element = self.ax_element().context("No AX element for the window")?;
+        let button = macos::get_attribute(element, "AXCloseButton")
+            .and_then(|button| button.downcast::<AXUIElement>().ok())
+            .context("The window has no close button")?;
+        let res =
+            unsafe { AXUIElement::perform_action(&button, &CFString::from_static_str("AXPress")) };
+        if res != AXError::Success {
+            return Err(anyhow!("AXPress failed with {res:?}"));
+        }
         Ok(())
     }
+
+    /// Raises the window within the window stack without activating its app.
+    pub fn raise(&self) {
+        if let Some(element) = self.ax_element() {
+            unsafe { AXUIElement::perform_action(element, &CFString::from_static_str("AXRaise")) };
+        }
+    }
+}
+
+fn activate(app: &NSRunningApplication) -> Result<()> {
+    if !app.activateWithOptions(NSApplicationActivationOptions::empty()) {
+        return Err(anyhow!("Could not activate the app"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, two_displays as fixture};
+
+    fn refreshed(fixture: &Fixture, setup: impl FnOnce(&mut Manager)) -> Manager {
+        let mut manager = Manager::default();
+        setup(&mut manager);
+        manager.refresh_from(fixture).unwrap();
+        manager
+    }
+
+    fn listed(manager: &Manager) -> Vec<u32> {
+        let mut wids: Vec<u32> = manager
+            .app_map()
+            .values()
+            .flat_map(|app| app.windows.iter().map(|w| w.id))
+            .collect();
+        wids.sort_unstable();
+        wids
+    }
+
+    #[test]
+    fn lists_windows_on_known_spaces() {
+        let manager = refreshed(&fixture(), |_| {});
+        // 103 has no owner, 104 is a floating layer, 201 belongs to an accessory app, 999 is on
+        // no space at all
+        assert_eq!(listed(&manager), [101, 102, 105, 106, 202, 203]);
+        assert_eq!(manager.front_window(), Some(101));
+        assert_eq!(manager.window(102).unwrap().1.title, "");
+    }
+
+    #[test]
+    fn window_state_and_location() {
+        let manager = refreshed(&fixture(), |_| {});
+        let window = |wid| manager.window(wid).unwrap().1;
+
+        assert!(window(202).state.minimized);
+        assert!(window(106).state.fullscreen);
+        assert!(window(203).state.hidden);
+        assert_eq!(window(101).state, WindowState::default());
+
+        assert_eq!(manager.space_number(window(101)), Some(1));
+        assert_eq!(manager.space_number(window(105)), Some(2));
+        assert_eq!(manager.space_number(window(106)), None);
+        assert_eq!(manager.space_number(window(203)), Some(2));
+
+        assert_eq!(
+            manager.display_name(window(101)),
+            Some("Built-in Retina Display")
+        );
+        assert_eq!(manager.display_name(window(202)), Some("DELL U2720Q"));
+        assert!(manager.on_active_space(window(101)));
+        assert!(!manager.on_active_space(window(105)));
+        assert!(manager.on_cursor_display(window(203)));
+        assert!(!manager.on_cursor_display(window(101)));
+    }
+
+    #[test]
+    fn finder_windows_get_their_folder() {
+        let manager = refreshed(&fixture(), |_| {});
+        let (app, window) = manager.window(105).unwrap();
+        assert_eq!(app.name, "Finder");
+        assert_eq!(window.folder.as_deref(), Some("Volumes › Work › code"));
+        assert_eq!(manager.window(101).unwrap().1.folder, None);
+    }
+
+    #[test]
+    fn configured_layers_and_accessory_apps() {
+        let manager = refreshed(&fixture(), |manager| {
+            manager.layers.include = vec![3];
+            manager.accessory_apps = vec!["com.example.MenuClock".to_string()];
+        });
+        assert_eq!(listed(&manager), [101, 102, 104, 105, 106, 201, 202, 203]);
+    }
+
+    #[test]
+    fn spanning_displays_only_reads_the_first() {
+        let mut fixture = fixture();
+        fixture.spans_displays = true;
+        let manager = refreshed(&fixture, |_| {});
+        assert_eq!(listed(&manager), [101, 102, 105, 106]);
+
+        // Displays come from window frames, spaces are numbered once across all of them
+        let window = |wid| manager.window(wid).unwrap().1;
+        assert_eq!(
+            manager.display_name(window(101)),
+            Some("Built-in Retina Display")
+        );
+        assert_eq!(manager.display_name(window(105)), Some("DELL U2720Q"));
+        assert_eq!(manager.display_name(window(102)), None);
+        assert_eq!(manager.space_number(window(101)), Some(1));
+        assert_eq!(manager.space_number(window(105)), Some(2));
+        assert_eq!(manager.space_number(window(106)), None);
+    }
+
+    #[test]
+    fn app_level_only_without_accessibility() {
+        let mut denied = fixture();
+        denied.ax_denied = true;
+        let manager = refreshed(&denied, |_| {});
+        assert_eq!(listed(&manager), [101, 102, 105, 106, 202, 203]);
+        let window = |wid| manager.window(wid).unwrap().1;
+        assert!(window(101).app_level_only);
+        // No AX, no state beyond what the app itself says
+        assert!(!window(202).state.minimized);
+        assert!(window(203).state.hidden);
+        assert_eq!(window(105).folder, None);
+
+        let manager = refreshed(&fixture(), |_| {});
+        assert!(!manager.window(101).unwrap().1.app_level_only);
+    }
 }
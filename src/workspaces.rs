@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use objc2_core_foundation::{CGPoint, CGRect, CGSize};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::crash::log;
+use crate::macos;
+use crate::stats::app_key;
+use crate::windows::{Manager, Window};
+
+const WORKSPACES_FILE: &str = "workspaces.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub app: String,
+    pub title: String,
+    pub space_id: u64,
+    /// x, y, width, height
+    pub frame: [f64; 4],
+}
+
+/// Named window layouts, saved with `>snapshot <name>` and restored with `>restore <name>`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspaces {
+    workspaces: BTreeMap<String, Vec<WindowSnapshot>>,
+}
+
+impl Workspaces {
+    pub fn load() -> Self {
+        config::load_data(WORKSPACES_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        config::save_data(WORKSPACES_FILE, self)
+    }
+
+    pub fn snapshot(&mut self, name: &str, manager: &Manager) {
+        let mut snapshot = Vec::new();
+        for app in manager.app_map().values() {
+            for window in &app.windows {
+                let Ok(bounds) = window.bounds() else {
+                    continue;
+                };
+                snapshot.push(WindowSnapshot {
+                    app: app_key(app),
+                    title: window.title.clone(),
+                    space_id: window.space_id,
+                    frame: [
+                        bounds.origin.x,
+                        bounds.origin.y,
+                        bounds.size.width,
+                        bounds.size.height,
+                    ],
+                });
+            }
+        }
+        self.workspaces.insert(name.to_string(), snapshot);
+    }
+
+    /// Puts every snapshotted window that's currently open back on its space and frame,
+    /// launching apps that have no windows at all when `launch` is set.
+    /// Returns whether any apps were launched, whose windows will only show up later.
+    pub fn restore(&self, name: &str, manager: &Manager, launch: bool) -> Result<bool> {
+        let snapshot = self
+            .workspaces
+            .get(name)
+            .ok_or_else(|| anyhow!("No workspace named {name:?}"))?;
+
+        let mut used = HashSet::new();
+        let mut launched = HashSet::new();
+        for entry in snapshot {
+            let candidates: Vec<&Window> = manager
+                .app_map()
+                .values()
+                .filter(|app| app_key(app) == entry.app)
+                .flat_map(|app| &app.windows)
+                .filter(|w| !used.contains(&w.id))
+                .collect();
+
+            let Some(window) = candidates
+                .iter()
+                .find(|w| w.title == entry.title)
+                .or_else(|| candidates.first())
+            else {
+                if launch && launched.insert(entry.app.as_str()) {
+                    macos::launch_app(&entry.app);
+                }
+                continue;
+            };
+            used.insert(window.id);
+
+            // Stays on its space without private APIs, the frame still gets restored
+            if window.space_id != entry.space_id && manager.private_apis() {
+                macos::move_window_to_space(window.id, entry.space_id);
+            }
+            let [x, y, w, h] = entry.frame;
+            let frame = CGRect::new(CGPoint::new(x, y), CGSize::new(w, h));
+            if let Err(e) = window.set_frame(frame) {
+                log!(
+                    "[warn] could not restore frame of window {}: {e}",
+                    window.id
+                );
+            }
+        }
+
+        Ok(!launched.is_empty())
+    }
+}